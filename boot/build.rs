@@ -1,3 +1,46 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Default location of the public key, relative to the crate root.
+const DEFAULT_KEY: &str = "keys/boot.pub";
+
+/// Embeds the team's public key into the bootloader as `PUBLIC_KEY`. The key
+/// is read from `$BOOT_PUBLIC_KEY` if set and from `keys/boot.pub` otherwise.
+fn write_public_key(out_dir: &Path) {
+    let key_path = env::var("BOOT_PUBLIC_KEY").unwrap_or_else(|_| DEFAULT_KEY.into());
+    println!("cargo:rerun-if-env-changed=BOOT_PUBLIC_KEY");
+    println!("cargo:rerun-if-changed={}", key_path);
+
+    let dev_unsigned = env::var_os("CARGO_FEATURE_DEV_UNSIGNED").is_some();
+    let key = match fs::read(&key_path) {
+        Ok(key) => key,
+        Err(_) if dev_unsigned => vec![0; 32],
+        Err(e) => panic!(
+            "failed to read public key '{}': {}\n\
+             help: run `make keygen` to create a signing key pair, or build \
+             with `--features dev-unsigned` to skip signature checks",
+            key_path, e
+        ),
+    };
+
+    if key.len() != 32 {
+        panic!("public key '{}' must be 32 bytes, found {}", key_path, key.len());
+    }
+
+    let bytes: Vec<String> = key.iter().map(|b| format!("{:#04x}", b)).collect();
+    let source = format!(
+        "/// The Ed25519 public key kernel images must be signed with.\n\
+         pub const PUBLIC_KEY: [u8; 32] = [{}];\n",
+        bytes.join(", ")
+    );
+
+    fs::write(out_dir.join("public_key.rs"), source).expect("failed to write public_key.rs");
+}
+
 pub fn main() {
     println!("cargo:rerun-if-changed=.cargo/layout.ld");
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    write_public_key(&out_dir);
 }
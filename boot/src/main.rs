@@ -8,8 +8,14 @@
 mod init;
 
 use xmodem::Xmodem;
+use core::fmt::Write;
 use core::time::Duration;
 use pi;
+use pi::uart::MiniUart;
+use kernsign::ed25519::PublicKey;
+use kernsign::HEADER_SIZE;
+
+include!(concat!(env!("OUT_DIR"), "/public_key.rs"));
 
 /// Start address of the binary to load and of the bootloader.
 const BINARY_START_ADDR: usize = 0x80000;
@@ -21,6 +27,11 @@ const BINARY_START: *mut u8 = BINARY_START_ADDR as *mut u8;
 /// Free space between the bootloader and the loaded binary's start address.
 const MAX_BINARY_SIZE: usize = BOOTLOADER_START_ADDR - BINARY_START_ADDR;
 
+/// Pointer to where the signed image header is received. The header is
+/// received just below the binary so the binary itself lands at
+/// `BINARY_START`.
+const IMAGE_START: *mut u8 = (BINARY_START_ADDR - HEADER_SIZE) as *mut u8;
+
 /// Branches to the address `addr` unconditionally.
 unsafe fn jump_to(addr: *mut u8) -> ! {
     asm!("br $0" : : "r"(addr as usize));
@@ -29,19 +40,59 @@ unsafe fn jump_to(addr: *mut u8) -> ! {
     }
 }
 
+/// Checks the signature of the `len` byte image received at `IMAGE_START`.
+/// Returns `true` if the binary at `BINARY_START` may be booted.
+#[cfg(not(feature = "dev-unsigned"))]
+fn check_image(uart: &mut MiniUart, len: usize) -> bool {
+    let data = unsafe { core::slice::from_raw_parts(IMAGE_START, len) };
+
+    match kernsign::verify(&PublicKey(PUBLIC_KEY), data) {
+        Ok(image) => {
+            let _ = writeln!(uart, "boot: signature ok, booting {} bytes", image.len());
+            true
+        }
+        Err(e) => {
+            let _ = writeln!(uart, "boot: rejected image: {}", e);
+            false
+        }
+    }
+}
+
+/// Development builds boot anything, but still say what they would have done.
+/// An unsigned image is moved down to `BINARY_START` since it was received at
+/// `IMAGE_START`.
+#[cfg(feature = "dev-unsigned")]
+fn check_image(uart: &mut MiniUart, len: usize) -> bool {
+    let data = unsafe { core::slice::from_raw_parts(IMAGE_START, len) };
+
+    let _ = match kernsign::verify(&PublicKey(PUBLIC_KEY), data) {
+        Ok(_) => writeln!(uart, "boot: signature ok"),
+        Err(kernsign::Error::NotSigned) => {
+            unsafe { core::ptr::copy(IMAGE_START, BINARY_START, len) };
+            writeln!(uart, "boot: WARNING: booting unsigned image (dev-unsigned build)")
+        }
+        Err(e) => writeln!(uart, "boot: WARNING: ignoring bad signature: {} (dev-unsigned build)", e),
+    };
+
+    true
+}
+
 fn kmain() -> ! {
-    // FIXME: Implement the bootloader.
     loop {
-        let mut uart = pi::uart::MiniUart::new();
+        let mut uart = MiniUart::new();
         uart.set_read_timeout(Duration::new(1,0));
 
         let prog_buf = unsafe {
-            core::slice::from_raw_parts_mut(BINARY_START, MAX_BINARY_SIZE)
+            core::slice::from_raw_parts_mut(IMAGE_START, HEADER_SIZE + MAX_BINARY_SIZE)
         };
 
-        match Xmodem::receive(uart, prog_buf) {
-            Ok(_) => unsafe {jump_to(BINARY_START); },
+        let received = match Xmodem::receive(&mut uart, prog_buf) {
+            Ok(received) => received,
             Err(_) => continue
+        };
+
+        if check_image(&mut uart, received) {
+            unsafe { jump_to(BINARY_START); }
         }
     }
 }
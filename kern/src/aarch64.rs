@@ -0,0 +1,125 @@
+//! Access to the processor's system registers and hint instructions.
+//!
+//! On the host, where the tests run, each thread stands in for a core and
+//! there are no interrupts or caches to manage.
+
+/// The IRQ mask bit (I) of `DAIF`.
+pub const DAIF_I: u64 = 1 << 7;
+
+/// Returns the ID of the core this runs on: `MPIDR_EL1.Aff0`.
+#[cfg(all(target_arch = "aarch64", not(test)))]
+#[inline(always)]
+pub fn affinity() -> usize {
+    let mpidr: u64;
+    unsafe { asm!("mrs $0, MPIDR_EL1" : "=r"(mpidr) ::: "volatile") };
+    (mpidr & 0b11) as usize
+}
+
+/// Returns an ID unique to the current thread.
+#[cfg(not(all(target_arch = "aarch64", not(test))))]
+pub fn affinity() -> usize {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    thread_local!(static ID: usize = NEXT.fetch_add(1, Ordering::Relaxed));
+    ID.with(|id| *id)
+}
+
+/// Returns `true` if the MMU and the data cache are on. Until they are,
+/// memory is Device memory, on which exclusive loads and stores (and so
+/// atomic read-modify-writes) do not work.
+#[cfg(all(target_arch = "aarch64", not(test)))]
+#[inline(always)]
+pub fn caches_enabled() -> bool {
+    let sctlr: u64;
+    unsafe { asm!("mrs $0, SCTLR_EL1" : "=r"(sctlr) ::: "volatile") };
+    sctlr & 0b101 == 0b101
+}
+
+/// Returns `true`: the host's memory is always cached.
+#[cfg(not(all(target_arch = "aarch64", not(test))))]
+pub fn caches_enabled() -> bool {
+    true
+}
+
+/// Returns the current interrupt masks, `DAIF`.
+#[cfg(all(target_arch = "aarch64", not(test)))]
+#[inline(always)]
+pub fn daif() -> u64 {
+    let daif: u64;
+    unsafe { asm!("mrs $0, DAIF" : "=r"(daif) ::: "volatile") };
+    daif
+}
+
+/// Returns 0: there are no interrupts to mask on the host.
+#[cfg(not(all(target_arch = "aarch64", not(test))))]
+pub fn daif() -> u64 {
+    0
+}
+
+/// Sets the interrupt masks, `DAIF`, to `daif`.
+///
+/// # Safety
+///
+/// Unmasking interrupts lets their handlers run; they must not find state
+/// they rely on half-updated.
+#[inline(always)]
+pub unsafe fn set_daif(daif: u64) {
+    #[cfg(all(target_arch = "aarch64", not(test)))]
+    asm!("msr DAIF, $0" :: "r"(daif) : "memory" : "volatile");
+    #[cfg(not(all(target_arch = "aarch64", not(test))))]
+    let _ = daif;
+}
+
+/// Masks IRQs, returning the interrupt masks as they were so that they can
+/// be restored with `set_daif()`.
+#[inline(always)]
+pub fn disable_irqs() -> u64 {
+    let daif = daif();
+    #[cfg(all(target_arch = "aarch64", not(test)))]
+    unsafe {
+        asm!("msr DAIFSet, #2" ::: "memory" : "volatile");
+    }
+    daif
+}
+
+/// Waits for an event, such as `sev()` on another core or an interrupt.
+#[inline(always)]
+pub fn wfe() {
+    #[cfg(all(target_arch = "aarch64", not(test)))]
+    unsafe {
+        asm!("wfe" :::: "volatile");
+    }
+    #[cfg(not(all(target_arch = "aarch64", not(test))))]
+    std::thread::yield_now();
+}
+
+/// Wakes the cores waiting in `wfe()`.
+#[inline(always)]
+pub fn sev() {
+    #[cfg(all(target_arch = "aarch64", not(test)))]
+    unsafe {
+        asm!("sev" :::: "volatile");
+    }
+}
+
+/// Traps into the debugger with a `brk` instruction; execution resumes after
+/// it once the debugger exits.
+#[inline(always)]
+pub fn brk() {
+    #[cfg(all(target_arch = "aarch64", not(test)))]
+    unsafe {
+        asm!("brk #0" :::: "volatile");
+    }
+}
+
+/// Unmasks IRQs.
+///
+/// # Safety
+///
+/// As for `set_daif()`.
+#[inline(always)]
+pub unsafe fn enable_irqs() {
+    #[cfg(all(target_arch = "aarch64", not(test)))]
+    asm!("msr DAIFClr, #2" ::: "memory" : "volatile");
+}
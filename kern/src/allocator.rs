@@ -1,30 +1,142 @@
 mod linked_list;
+mod stats;
 mod util;
 
 mod bin;
+#[cfg_attr(not(feature = "debug-alloc"), allow(dead_code))]
+mod debug;
+#[cfg_attr(not(feature = "buddy"), allow(dead_code))]
+mod buddy;
 mod bump;
+mod region;
+mod slab;
 
-type AllocatorImpl = bin::Allocator;
+#[cfg(not(feature = "buddy"))]
+type BaseAllocator = bin::Allocator;
+#[cfg(feature = "buddy")]
+type BaseAllocator = buddy::Allocator;
 
+#[cfg(not(feature = "debug-alloc"))]
+type AllocatorImpl = BaseAllocator;
+#[cfg(feature = "debug-alloc")]
+type AllocatorImpl = debug::Allocator<BaseAllocator>;
+
+#[cfg(test)]
+mod fuzz;
 #[cfg(test)]
 mod tests;
 
 use core::alloc::{GlobalAlloc, Layout};
+use core::cmp::min;
 use core::fmt;
+use core::ptr;
 
-use crate::console::kprintln;
-use crate::mutex::Mutex;
+use crate::mutex::{IrqMutex, Mutex};
 use pi::atags::{Atag, Atags};
+use pi::mailbox;
+
+pub use self::debug::Record;
+pub use self::region::{Region, Regions, MAX_REGIONS};
+pub use self::slab::{SlabBox, SlabCache, SlabStats};
+pub use self::stats::Stats;
 
 /// `LocalAlloc` is an analogous trait to the standard library's `GlobalAlloc`,
-/// but it takes `&mut self` in `alloc()` and `dealloc()`.
+/// but it takes `&mut self` in `alloc()`, `dealloc()` and friends.
 pub trait LocalAlloc {
+    /// Hands the memory `[start, end)` to the allocator, in addition to the
+    /// memory it already manages.
+    ///
+    /// # Safety
+    ///
+    /// The memory must be unused and must not overlap memory the allocator
+    /// already manages.
+    unsafe fn add_region(&mut self, start: usize, end: usize);
+
     unsafe fn alloc(&mut self, layout: Layout) -> *mut u8;
     unsafe fn dealloc(&mut self, ptr: *mut u8, layout: Layout);
+
+    /// Allocates memory on behalf of the code at address `caller`. Allocators
+    /// that do not keep track of callers just `alloc()`.
+    ///
+    /// # Safety
+    ///
+    /// As for `alloc()`.
+    unsafe fn alloc_from(&mut self, layout: Layout, _caller: usize) -> *mut u8 {
+        self.alloc(layout)
+    }
+
+    /// Allocates zeroed memory.
+    unsafe fn alloc_zeroed(&mut self, layout: Layout) -> *mut u8 {
+        self.alloc_zeroed_from(layout, 0)
+    }
+
+    /// Allocates zeroed memory on behalf of the code at address `caller`.
+    ///
+    /// # Safety
+    ///
+    /// As for `alloc()`.
+    unsafe fn alloc_zeroed_from(&mut self, layout: Layout, caller: usize) -> *mut u8 {
+        let ptr = self.alloc_from(layout, caller);
+        if !ptr.is_null() {
+            ptr::write_bytes(ptr, 0, layout.size());
+        }
+        ptr
+    }
+
+    /// Resizes the memory at `ptr`, allocated with `layout`, to `new_size`
+    /// bytes, keeping its contents up to the smaller of the two sizes. Returns
+    /// null, leaving the memory as it was, if it cannot be resized.
+    ///
+    /// By default the memory is always moved; allocators that can resize in
+    /// place override this.
+    unsafe fn realloc(&mut self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        realloc_by_copy(self, ptr, layout, new_size)
+    }
+
+    /// Resizes memory on behalf of the code at address `caller`. Allocators
+    /// that do not keep track of callers just `realloc()`.
+    ///
+    /// # Safety
+    ///
+    /// As for `realloc()`.
+    unsafe fn realloc_from(
+        &mut self,
+        ptr: *mut u8,
+        layout: Layout,
+        new_size: usize,
+        _caller: usize,
+    ) -> *mut u8 {
+        self.realloc(ptr, layout, new_size)
+    }
 }
 
-/// Thread-safe (locking) wrapper around a particular memory allocator.
-pub struct Allocator(Mutex<Option<AllocatorImpl>>);
+/// Resizes the memory at `ptr` by moving it to a new allocation.
+unsafe fn realloc_by_copy<A: LocalAlloc + ?Sized>(
+    allocator: &mut A,
+    ptr: *mut u8,
+    layout: Layout,
+    new_size: usize,
+) -> *mut u8 {
+    let new_layout = match Layout::from_size_align(new_size, layout.align()) {
+        Ok(new_layout) => new_layout,
+        Err(_) => return ptr::null_mut(),
+    };
+
+    let new_ptr = allocator.alloc(new_layout);
+    if !new_ptr.is_null() {
+        ptr::copy_nonoverlapping(ptr, new_ptr, min(layout.size(), new_size));
+        allocator.dealloc(ptr, layout);
+    }
+    new_ptr
+}
+
+/// Thread-safe (locking) wrapper around a particular memory allocator, along
+/// with the regions of memory it was given.
+///
+/// IRQs are masked while the allocator is locked, so interrupt handlers may
+/// allocate and free memory without finding the lock held by the code they
+/// interrupted.
+pub struct Allocator(IrqMutex<Option<AllocatorImpl>>, IrqMutex<Regions>);
 
 impl Allocator {
     /// Returns an uninitialized `Allocator`.
@@ -32,7 +144,7 @@ impl Allocator {
     /// The allocator must be initialized by calling `initialize()` before the
     /// first memory allocation. Failure to do will result in panics.
     pub const fn uninitialized() -> Self {
-        Allocator(Mutex::new(None))
+        Allocator(IrqMutex::named("allocator", None), IrqMutex::named("heap regions", Regions::new()))
     }
 
     /// Initializes the memory allocator.
@@ -41,20 +153,63 @@ impl Allocator {
     ///
     /// # Panics
     ///
-    /// Panics if the system's memory map could not be retrieved.
+    /// Panics if the system's memory map could not be retrieved or if it has
+    /// no usable memory.
     pub unsafe fn initialize(&self) {
-        let (start, end) = memory_map().expect("failed to find memory map");
-        *self.0.lock() = Some(AllocatorImpl::new(start, end));
+        let regions = memory_map().expect("failed to find memory map");
+
+        // The allocator starts out with the largest region, so that large
+        // allocations are carved from it, and is handed the rest.
+        let largest = *regions.iter().max_by_key(|r| r.size()).expect("no usable memory");
+        let mut allocator = AllocatorImpl::from(BaseAllocator::new(largest.start, largest.end));
+        for region in regions.iter().filter(|&&r| r != largest) {
+            allocator.add_region(region.start, region.end);
+        }
+
+        *self.0.lock() = Some(allocator);
+        *self.1.lock() = regions;
+    }
+
+    /// Returns the regions of memory the heap manages, which are empty if the
+    /// allocator has not been initialized.
+    pub fn regions(&self) -> Regions {
+        *self.1.lock()
+    }
+
+    /// Returns a snapshot of the heap's usage, or `None` if the allocator
+    /// has not been initialized.
+    pub fn stats(&self) -> Option<Stats> {
+        self.0.lock().as_ref().map(|allocator| allocator.stats())
+    }
+
+    /// Copies the newest live allocations into `records`, returning how many
+    /// there are in all, or `None` if the kernel was built without the
+    /// `debug-alloc` feature or the allocator has not been initialized.
+    #[cfg(feature = "debug-alloc")]
+    pub fn live(&self, records: &mut [Record]) -> Option<usize> {
+        self.0.lock().as_ref().map(|allocator| allocator.live(records))
+    }
+
+    /// Returns `None`: live allocations are only recorded with the
+    /// `debug-alloc` feature.
+    #[cfg(not(feature = "debug-alloc"))]
+    pub fn live(&self, _records: &mut [Record]) -> Option<usize> {
+        None
     }
 }
 
+// The entry points that hand out memory are never inlined, so that x30 still
+// holds their return address when they read it first thing. The allocator
+// shims tail-call them, so that is the code that asked for the memory.
 unsafe impl GlobalAlloc for Allocator {
+    #[inline(never)]
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let caller = debug::return_address();
         self.0
             .lock()
             .as_mut()
             .expect("allocator uninitialized")
-            .alloc(layout)
+            .alloc_from(layout, caller)
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
@@ -64,44 +219,141 @@ unsafe impl GlobalAlloc for Allocator {
             .expect("allocator uninitialized")
             .dealloc(ptr, layout);
     }
+
+    #[inline(never)]
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let caller = debug::return_address();
+        self.0
+            .lock()
+            .as_mut()
+            .expect("allocator uninitialized")
+            .alloc_zeroed_from(layout, caller)
+    }
+
+    #[inline(never)]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let caller = debug::return_address();
+        self.0
+            .lock()
+            .as_mut()
+            .expect("allocator uninitialized")
+            .realloc_from(ptr, layout, new_size, caller)
+    }
 }
 
 extern "C" {
     static __text_end: u8;
 }
 
-/// Returns the (start address, end address) of the available memory on this
-/// system if it can be determined. If it cannot, `None` is returned.
+/// Returns the address of the end of the kernel binary.
+pub fn kernel_end() -> usize {
+    unsafe { (&__text_end as *const u8) as usize }
+}
+
+/// A region of memory kept out of the heap.
+#[derive(Debug, Copy, Clone)]
+pub struct Reservation {
+    /// What the memory is kept for.
+    pub name: &'static str,
+    pub region: Region,
+}
+
+/// The regions registered with `reserve()`. Kept in a fixed table, since
+/// they are registered before there is a heap.
+static RESERVATIONS: Mutex<([Reservation; MAX_REGIONS], usize)> = Mutex::named("reservations", (
+    [Reservation { name: "", region: Region::new(0, 0) }; MAX_REGIONS],
+    0,
+));
+
+/// Keeps the memory `[start, end)` out of the heap, naming it `name`. Returns
+/// `false` if `MAX_REGIONS` regions are reserved already.
+///
+/// Only memory reserved before the allocator is initialized is kept out of
+/// it; the heap's memory must not be reserved afterwards.
+pub fn reserve(name: &'static str, start: usize, end: usize) -> bool {
+    let mut reservations = RESERVATIONS.lock();
+    let (ref mut table, ref mut len) = *reservations;
+    if *len == MAX_REGIONS {
+        return false;
+    }
+
+    table[*len] = Reservation { name, region: Region::new(start, end) };
+    *len += 1;
+    true
+}
+
+/// Calls `f` with every region registered with `reserve()`, in the order
+/// they were registered.
+pub fn reservations<F: FnMut(&Reservation)>(f: F) {
+    let reservations = RESERVATIONS.lock();
+    let (ref table, len) = *reservations;
+    table[..len].iter().for_each(f);
+}
+
+/// Where the bootloader is loaded (see `boot/.cargo/layout.ld`). Its stack
+/// grows down from the same address.
+const BOOTLOADER_START: usize = 0x400_0000;
+
+/// How much memory on either side of `BOOTLOADER_START` the bootloader's
+/// image and stack may take.
+const BOOTLOADER_SPAN: usize = 1 << 20;
+
+/// Registers the memory known to need keeping out of the heap: the
+/// bootloader, which may be jumped back to, and the VideoCore's memory,
+/// which holds the framebuffer and the buffers the firmware DMAs to.
 ///
-/// This function is expected to return `Some` under all normal cirumstances.
-pub fn memory_map() -> Option<(usize, usize)> {
-    let page_size = 1 << 12;
-    let binary_end = unsafe { (&__text_end as *const u8) as usize };
-    
-    let mut mem_tag = None;
+/// Must be called before the allocator is initialized.
+pub fn reserve_boot_regions() {
+    reserve("boot", BOOTLOADER_START - BOOTLOADER_SPAN, BOOTLOADER_START + BOOTLOADER_SPAN);
+    if let Some((base, size)) = mailbox::vc_memory() {
+        reserve("vc", base, base + size);
+    }
+}
 
+/// Returns the regions of memory available for the heap on this system if
+/// they can be determined. If they cannot, `None` is returned.
+///
+/// These are the memory of every `Mem` ATAG, limited to the ARM's share of
+/// memory as split from the VideoCore's by the firmware, less the kernel
+/// binary and the regions registered with `reserve()`.
+///
+/// This function is expected to return `Some` under all normal cirumstances.
+pub fn memory_map() -> Option<Regions> {
+    let mut ram = Regions::new();
     for atag in Atags::get() {
-        match atag {
-            Atag::Mem(mem) => {
-                mem_tag = Some(mem);
-                break;
-            },
-            _ => continue,
+        if let Atag::Mem(mem) = atag {
+            let start = mem.start as usize;
+            if !ram.add(Region::new(start, start + mem.size as usize)) {
+                return None;
+            }
         }
     }
 
-    if mem_tag.is_none() {
+    let arm = mailbox::arm_memory().map(|(base, size)| Region::new(base, base + size));
+    usable(ram, arm, kernel_end())
+}
+
+/// Returns `ram` limited to `arm`, if it is known, less `[0, kernel_end)`
+/// and the regions registered with `reserve()`. Returns `None` if there is no
+/// RAM or if there are too many regions left to keep.
+fn usable(mut ram: Regions, arm: Option<Region>, kernel_end: usize) -> Option<Regions> {
+    if ram.is_empty() {
         return None;
     }
 
-    let mem_tag = mem_tag.unwrap();
+    if let Some(arm) = arm {
+        ram.intersect(arm);
+    }
 
-    let start_addr = mem_tag.start as usize;
-    let bin_mem_used: usize = binary_end - start_addr;
-    let end_addr = binary_end + (mem_tag.size as usize - bin_mem_used);
+    // Removing a region can split another in two; if there are too many to
+    // keep, nothing can be trusted to be free.
+    let mut complete = ram.remove(Region::new(0, kernel_end));
+    reservations(|r| complete &= ram.remove(r.region));
 
-    // Memory pool starts at binary_end
-    return Some((binary_end, end_addr))
+    match complete {
+        true => Some(ram),
+        false => None,
+    }
 }
 
 impl fmt::Debug for Allocator {
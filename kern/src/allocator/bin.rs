@@ -5,8 +5,9 @@ use core::fmt;
 use core::ptr;
 
 use crate::allocator::linked_list::LinkedList;
+use crate::allocator::stats::{Stats, SIZE_CLASSES};
 use crate::allocator::util::*;
-use crate::allocator::LocalAlloc;
+use crate::allocator::{realloc_by_copy, LocalAlloc};
 
 
 /// A simple allocator that allocates based on size classes.
@@ -19,17 +20,18 @@ use crate::allocator::LocalAlloc;
 ///   
 
 pub struct Allocator {
-    bins: [LinkedList; 30],
+    bins: [LinkedList; SIZE_CLASSES],
     start: usize,
     end: usize,
     unallocated_addr: usize,
+    stats: Stats,
 }
 
 impl Allocator {
     /// Creates a new bin allocator that will allocate memory from the region
     /// starting at address `start` and ending at address `end`.
     pub fn new(start: usize, end: usize) -> Allocator {
-        let bins = [LinkedList::new(); 30];
+        let bins = [LinkedList::new(); SIZE_CLASSES];
 
         let start = align_up(start, 1 << 3); 
 
@@ -38,33 +40,33 @@ impl Allocator {
             start: start,
             end: end,
             unallocated_addr: start,
+            stats: Stats::default(),
         }
     }
-}
 
-impl LocalAlloc for Allocator {
-    /// Allocates memory. Returns a pointer meeting the size and alignment
-    /// properties of `layout.size()` and `layout.align()`.
-    ///
-    /// If this method returns an `Ok(addr)`, `addr` will be non-null address
-    /// pointing to a block of storage suitable for holding an instance of
-    /// `layout`. In particular, the block will be at least `layout.size()`
-    /// bytes large and will be aligned to `layout.align()`. The returned block
-    /// of storage may or may not have its contents initialized or zeroed.
-    ///
-    /// # Safety
-    ///
-    /// The _caller_ must ensure that `layout.size() > 0` and that
-    /// `layout.align()` is a power of two. Parameters not meeting these
-    /// conditions may result in undefined behavior.
-    ///
-    /// # Errors
-    ///
-    /// Returning null pointer (`core::ptr::null_mut`)
-    /// indicates that either memory is exhausted
-    /// or `layout` does not meet this allocator's
-    /// size or alignment constraints.
-    unsafe fn alloc(&mut self, layout: Layout) -> *mut u8 {
+    /// Returns a snapshot of the heap's usage. Walks every free list.
+    pub fn stats(&self) -> Stats {
+        let mut stats = self.stats;
+        let unallocated = self.end.saturating_sub(self.unallocated_addr);
+        stats.free = unallocated;
+        stats.largest_free = unallocated;
+
+        for (class, bin) in self.bins.iter().enumerate() {
+            let len = bin.iter().count();
+            let size = 1 << (class + 3);
+            stats.free_lists[class] = len;
+            stats.free += len * size;
+            if len > 0 && size > stats.largest_free {
+                stats.largest_free = size;
+            }
+        }
+
+        stats
+    }
+
+    /// Allocates a block for `layout` from its bin, from unallocated memory
+    /// or, once that runs out, from a larger block in the bins.
+    unsafe fn alloc_block(&mut self, layout: Layout) -> *mut u8 {
         let layout = transform_layout(layout);
 
         if layout.is_err() {
@@ -81,34 +83,10 @@ impl LocalAlloc for Allocator {
             return bin.pop().unwrap() as *mut u8;
         }
 
-        /*
-        // Get memory for larger bins
-        'outer: for (index, bin) in self.bins[(bin_index + 1)..].iter_mut().enumerate() {
-            if bin.peek().is_none() {
-                continue
-            }
-
-            let index = index + (bin_index + 1);
-
-            let required_bin_size = 1 << bin_index;
-            let cur_bin_size = 1 << index;
-
-            let mem_block = bin.pop().unwrap() as usize;
-
-            let nsplit_blocks = cur_bin_size/required_bin_size;
-            for n in (1..nsplit_blocks) {
-                let new_block = (mem_block as usize) + n*required_bin_size;
-                bin.push(new_block as *mut usize); 
-            }
-
-            return mem_block as *mut u8
-        }
-        */
-
         let alloc_addr = align_up(self.unallocated_addr, layout.align());
 
         if (alloc_addr + layout.size() > self.end) {
-            return core::ptr::null_mut();
+            return self.split(bin_index);
         }
 
         let prev_unalloc = self.unallocated_addr;
@@ -116,7 +94,7 @@ impl LocalAlloc for Allocator {
 
         //Put memory between prev_unalloc and the alloc_addr into the smallest bin
         let mut cur_addr = prev_unalloc;
-        while (cur_addr + 8 < alloc_addr) {
+        while (cur_addr + 8 <= alloc_addr) {
             self.bins[0].push(cur_addr as *mut usize);
             cur_addr += 8;
         }
@@ -124,6 +102,93 @@ impl LocalAlloc for Allocator {
         return alloc_addr as *mut u8;
     }
 
+    /// Takes a block for bin `index` from the smallest larger block in the
+    /// bins, which is aligned to its size, and puts the rest of that block in
+    /// the bins in between. Returns null if the larger bins are empty too.
+    unsafe fn split(&mut self, index: usize) -> *mut u8 {
+        let larger = match (index + 1..SIZE_CLASSES).find(|&k| self.bins[k].peek().is_some()) {
+            Some(larger) => larger,
+            None => return ptr::null_mut(),
+        };
+
+        let addr = self.bins[larger].pop().unwrap() as usize;
+        for k in index..larger {
+            self.bins[k].push((addr + (1 << (k + 3))) as *mut usize);
+        }
+
+        addr as *mut u8
+    }
+
+    /// Resizes the block of `old` bytes at `addr` to `new` bytes in place, if
+    /// possible. Both are block sizes, i.e. powers of two.
+    ///
+    /// A smaller block keeps the start of the old one and the rest goes back
+    /// into the bins. A larger block is only possible when the old one is the
+    /// last block taken from unallocated memory and is aligned to the new
+    /// size.
+    unsafe fn resize(&mut self, addr: usize, old: usize, new: usize) -> bool {
+        if new < old {
+            // The old block is aligned to `old`, so each piece of the rest is
+            // aligned to its size.
+            for k in log2(new)..log2(old) {
+                self.bins[k - 3].push((addr + (1 << k)) as *mut usize);
+            }
+            return true;
+        }
+
+        if new > old {
+            let last = addr + old == self.unallocated_addr;
+            if !last || addr % new != 0 || addr + new > self.end {
+                return false;
+            }
+            self.unallocated_addr = addr + new;
+        }
+
+        true
+    }
+}
+
+impl LocalAlloc for Allocator {
+    /// Carves `[start, end)` into the largest blocks aligned to their size
+    /// and puts them in their bins.
+    ///
+    /// # Safety
+    ///
+    /// The memory must be unused and must not overlap memory the allocator
+    /// already manages.
+    unsafe fn add_region(&mut self, start: usize, end: usize) {
+        for (addr, order) in blocks(start, end, 3, SIZE_CLASSES + 2) {
+            self.bins[order - 3].push(addr as *mut usize);
+        }
+    }
+
+    /// Allocates memory. Returns a pointer meeting the size and alignment
+    /// properties of `layout.size()` and `layout.align()`.
+    ///
+    /// If this method returns an `Ok(addr)`, `addr` will be non-null address
+    /// pointing to a block of storage suitable for holding an instance of
+    /// `layout`. In particular, the block will be at least `layout.size()`
+    /// bytes large and will be aligned to `layout.align()`. The returned block
+    /// of storage may or may not have its contents initialized or zeroed.
+    ///
+    /// # Safety
+    ///
+    /// The _caller_ must ensure that `layout.size() > 0` and that
+    /// `layout.align()` is a power of two. Parameters not meeting these
+    /// conditions may result in undefined behavior.
+    ///
+    /// # Errors
+    ///
+    /// Returning null pointer (`core::ptr::null_mut`)
+    /// indicates that either memory is exhausted
+    /// or `layout` does not meet this allocator's
+    /// size or alignment constraints.
+    unsafe fn alloc(&mut self, layout: Layout) -> *mut u8 {
+        let ptr = self.alloc_block(layout);
+        self.stats.record_alloc(layout.size(), ptr);
+        ptr
+    }
+
     /// Deallocates the memory referenced by `ptr`.
     ///
     /// # Safety
@@ -142,6 +207,7 @@ impl LocalAlloc for Allocator {
             return;
         }
 
+        self.stats.record_dealloc(layout.size());
         let layout = transform_layout(layout).unwrap();
 
         // Can use layout.size() or layout.align())
@@ -151,6 +217,26 @@ impl LocalAlloc for Allocator {
         bin.push(ptr as *mut usize);
 
     }
+
+    /// Resizes the memory at `ptr` in place when its block has room for
+    /// `new_size` bytes, when the block can be split or when it can grow into
+    /// unallocated memory. Otherwise the memory is moved.
+    unsafe fn realloc(&mut self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_layout = match Layout::from_size_align(new_size, layout.align()) {
+            Ok(new_layout) => new_layout,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        if let (Ok(old), Ok(new)) = (transform_layout(layout), transform_layout(new_layout)) {
+            if self.resize(ptr as usize, old.size(), new.size()) {
+                self.stats.record_dealloc(layout.size());
+                self.stats.record_alloc(new_size, ptr);
+                return ptr;
+            }
+        }
+
+        realloc_by_copy(self, ptr, layout, new_size)
+    }
 }
 
 fn transform_layout(layout: Layout) -> Result<Layout, ()> {
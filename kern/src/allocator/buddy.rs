@@ -0,0 +1,202 @@
+use core::alloc::Layout;
+use core::cmp::max;
+use core::fmt;
+use core::ptr;
+
+use crate::allocator::linked_list::LinkedList;
+use crate::allocator::stats::{Stats, SIZE_CLASSES};
+use crate::allocator::util::blocks;
+use crate::allocator::LocalAlloc;
+
+/// The largest alignment a `Layout` may ask for.
+const PAGE_SIZE: usize = 1 << 12;
+
+/// The smallest block: every free block holds a `LinkedList` pointer.
+const MIN_ORDER: usize = 3;
+
+/// The largest block is `2^MAX_ORDER` bytes.
+const MAX_ORDER: usize = MIN_ORDER + SIZE_CLASSES - 1;
+
+/// A buddy allocator.
+///
+/// Memory is handed out in blocks of `2^k` bytes, each aligned to its size.
+/// A request is served from the smallest free block that fits, halving it
+/// until it is just large enough; the other halves go on their free lists.
+/// When a block is freed and its buddy (the other half of the block it was
+/// split from) is also free, the two are merged back, and so on upwards, so
+/// freed memory becomes available to larger requests again.
+///
+///   order k (2^k bytes): free list `lists[k - 3]`, for k in [3, 32]
+///
+/// Since blocks are aligned to their size, the buddy of the block at `addr`
+/// of order `k` is at `addr ^ 2^k`.
+pub struct Allocator {
+    lists: [LinkedList; SIZE_CLASSES],
+    start: usize,
+    end: usize,
+    stats: Stats,
+}
+
+/// Returns the order of the block that holds `layout`, or `None` if none
+/// can.
+fn order(layout: Layout) -> Option<usize> {
+    if layout.size() == 0 || layout.align() > PAGE_SIZE {
+        return None;
+    }
+
+    let size = max(layout.size(), layout.align()).checked_next_power_of_two()?;
+    let order = max(size.trailing_zeros() as usize, MIN_ORDER);
+    match order <= MAX_ORDER {
+        true => Some(order),
+        false => None,
+    }
+}
+
+impl Allocator {
+    /// Creates a new buddy allocator that will allocate memory from the
+    /// region starting at address `start` and ending at address `end`.
+    pub fn new(start: usize, end: usize) -> Allocator {
+        let mut allocator = Allocator {
+            lists: [LinkedList::new(); SIZE_CLASSES],
+            start,
+            end,
+            stats: Stats::default(),
+        };
+
+        unsafe { allocator.add_region(start, end) };
+        allocator
+    }
+
+    /// Returns a snapshot of the heap's usage. Walks every free list.
+    pub fn stats(&self) -> Stats {
+        let mut stats = self.stats;
+        for (class, list) in self.lists.iter().enumerate() {
+            let len = list.iter().count();
+            let size = 1 << (class + MIN_ORDER);
+            stats.free_lists[class] = len;
+            stats.free += len * size;
+            if len > 0 {
+                stats.largest_free = size;
+            }
+        }
+
+        stats
+    }
+
+    /// Puts the free block at `addr` on the list for `order`.
+    unsafe fn push(&mut self, addr: usize, order: usize) {
+        self.lists[order - MIN_ORDER].push(addr as *mut usize);
+    }
+
+    /// Removes the block at `addr` from the list for `order`. Returns `false`
+    /// if it is not there, i.e. if the block is not free.
+    fn remove(&mut self, addr: usize, order: usize) -> bool {
+        for node in self.lists[order - MIN_ORDER].iter_mut() {
+            if node.value() as usize == addr {
+                node.pop();
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Takes a free block of `order`, splitting a larger one if needed.
+    unsafe fn take(&mut self, order: usize) -> Option<usize> {
+        let found = (order..=MAX_ORDER).find(|&k| !self.lists[k - MIN_ORDER].is_empty())?;
+        let addr = self.lists[found - MIN_ORDER].pop()? as usize;
+
+        // Keep the lower half each time and free the upper one.
+        for k in (order..found).rev() {
+            self.push(addr + (1 << k), k);
+        }
+
+        Some(addr)
+    }
+
+    /// Frees the block at `addr` of `order`, merging it with its buddy for as
+    /// long as the buddy is free.
+    unsafe fn free(&mut self, mut addr: usize, mut order: usize) {
+        while order < MAX_ORDER && self.remove(addr ^ (1 << order), order) {
+            addr &= !(1 << order);
+            order += 1;
+        }
+
+        self.push(addr, order);
+    }
+}
+
+impl LocalAlloc for Allocator {
+    /// Carves `[start, end)` into the largest blocks aligned to their size
+    /// and frees them.
+    ///
+    /// # Safety
+    ///
+    /// The memory must be unused and must not overlap memory the allocator
+    /// already manages.
+    unsafe fn add_region(&mut self, start: usize, end: usize) {
+        for (addr, order) in blocks(start, end, MIN_ORDER, MAX_ORDER) {
+            self.free(addr, order);
+        }
+    }
+
+    /// Allocates memory. Returns a pointer meeting the size and alignment
+    /// properties of `layout.size()` and `layout.align()`.
+    ///
+    /// The block handed out is `layout.size()` or `layout.align()` rounded
+    /// up to a power of two, whichever is larger.
+    ///
+    /// # Safety
+    ///
+    /// The _caller_ must ensure that `layout.size() > 0` and that
+    /// `layout.align()` is a power of two. Parameters not meeting these
+    /// conditions may result in undefined behavior.
+    ///
+    /// # Errors
+    ///
+    /// Returns a null pointer if memory is exhausted or `layout.align()` is
+    /// larger than a page.
+    unsafe fn alloc(&mut self, layout: Layout) -> *mut u8 {
+        let ptr = match order(layout).and_then(|order| self.take(order)) {
+            Some(addr) => addr as *mut u8,
+            None => ptr::null_mut(),
+        };
+
+        self.stats.record_alloc(layout.size(), ptr);
+        ptr
+    }
+
+    /// Deallocates the memory referenced by `ptr`.
+    ///
+    /// # Safety
+    ///
+    /// The _caller_ must ensure the following:
+    ///
+    ///   * `ptr` must denote a block of memory currently allocated via this
+    ///     allocator
+    ///   * `layout` must properly represent the original layout used in the
+    ///     allocation call that returned `ptr`
+    ///
+    /// Parameters not meeting these conditions may result in undefined
+    /// behavior.
+    unsafe fn dealloc(&mut self, ptr: *mut u8, layout: Layout) {
+        if ptr.is_null() {
+            return;
+        }
+
+        self.stats.record_dealloc(layout.size());
+        if let Some(order) = order(layout) {
+            self.free(ptr as usize, order);
+        }
+    }
+}
+
+impl fmt::Debug for Allocator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Buddy Alloc")
+            .field("lists", &self.lists)
+            .field("start", &self.start)
+            .field("end", &self.end)
+            .finish()
+    }
+}
@@ -1,6 +1,9 @@
 use core::alloc::Layout;
+use core::cmp::max;
 use core::ptr;
 
+use crate::allocator::region::{Region, Regions};
+use crate::allocator::stats::Stats;
 use crate::allocator::util::*;
 use crate::allocator::LocalAlloc;
 
@@ -9,6 +12,9 @@ use crate::allocator::LocalAlloc;
 pub struct Allocator {
     current: usize,
     end: usize,
+    /// Regions to move on to once the current one is used up.
+    spare: Regions,
+    stats: Stats,
 }
 
 impl Allocator {
@@ -19,11 +25,82 @@ impl Allocator {
         Allocator {
             current: start,
             end: end,
+            spare: Regions::new(),
+            stats: Stats::default(),
         }
     }
+
+    /// Returns a snapshot of the heap's usage. Freed memory is never reused,
+    /// so only what lies past the bump pointer, and the spare regions, are
+    /// free.
+    #[allow(dead_code)]
+    pub fn stats(&self) -> Stats {
+        let mut stats = self.stats;
+        let current = self.end.saturating_sub(self.current);
+        stats.free = current + self.spare.size();
+        stats.largest_free = self.spare.iter().map(Region::size).fold(current, max);
+        stats
+    }
+
+    /// Moves the pointer past a block for `layout`, returning the block or
+    /// null if it does not fit. When the block does not fit in the current
+    /// region, the rest of it is given up for the first spare region it fits
+    /// in.
+    fn bump(&mut self, layout: Layout) -> *mut u8 {
+        let ptr = self.bump_current(layout);
+        if !ptr.is_null() {
+            return ptr;
+        }
+
+        let fits = |region: &Region| {
+            let start = align_up(region.start, layout.align());
+            start.checked_add(layout.size()).map_or(false, |end| end <= region.end)
+        };
+        match self.spare.iter().cloned().find(fits) {
+            Some(region) => {
+                self.spare.remove(region);
+                self.current = region.start;
+                self.end = region.end;
+                self.bump_current(layout)
+            }
+            None => core::ptr::null_mut(),
+        }
+    }
+
+    /// Moves the pointer past a block for `layout` within the current region.
+    fn bump_current(&mut self, layout: Layout) -> *mut u8 {
+        let ptr = align_up(self.current, layout.align());
+
+        if ptr.checked_add(layout.size()).is_none() {
+            return core::ptr::null_mut();
+        }
+
+        let new_cur = ptr.saturating_add(layout.size());
+
+        if (new_cur > self.end) {
+            return core::ptr::null_mut();
+        }
+
+        self.current = new_cur;
+        return ptr as *mut u8;
+    }
 }
 
 impl LocalAlloc for Allocator {
+    /// Adds `[start, end)` to the regions to bump through.
+    ///
+    /// # Safety
+    ///
+    /// The memory must be unused and must not overlap memory the allocator
+    /// already manages.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocator already holds `MAX_REGIONS` spare regions.
+    unsafe fn add_region(&mut self, start: usize, end: usize) {
+        assert!(self.spare.add(Region::new(start, end)), "too many memory regions");
+    }
+
     /// Allocates memory. Returns a pointer meeting the size and alignment
     /// properties of `layout.size()` and `layout.align()`.
     ///
@@ -46,20 +123,9 @@ impl LocalAlloc for Allocator {
     /// or `layout` does not meet this allocator's
     /// size or alignment constraints.
     unsafe fn alloc(&mut self, layout: Layout) -> *mut u8 {
-        let ptr = align_up(self.current, layout.align());
-
-        if ptr.checked_add(layout.size()).is_none() {
-            return core::ptr::null_mut();
-        }
-
-        let new_cur = ptr.saturating_add(layout.size());
-
-        if (new_cur > self.end) {
-            return core::ptr::null_mut();
-        }
-
-        self.current = new_cur;
-        return ptr as *mut u8;
+        let ptr = self.bump(layout);
+        self.stats.record_alloc(layout.size(), ptr);
+        ptr
     }
 
     /// Deallocates the memory referenced by `ptr`.
@@ -75,7 +141,8 @@ impl LocalAlloc for Allocator {
     ///
     /// Parameters not meeting these conditions may result in undefined
     /// behavior.
-    unsafe fn dealloc(&mut self, _ptr: *mut u8, _layout: Layout) {
+    unsafe fn dealloc(&mut self, _ptr: *mut u8, layout: Layout) {
         // LEAKED
+        self.stats.record_dealloc(layout.size());
     }
 }
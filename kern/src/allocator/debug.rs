@@ -0,0 +1,305 @@
+use core::alloc::Layout;
+use core::cmp::{max, min};
+use core::fmt;
+use core::mem::{align_of, size_of};
+use core::ops::Deref;
+use core::ptr;
+
+use crate::allocator::util::align_up;
+use crate::allocator::LocalAlloc;
+
+/// Bytes of canary on either side of every allocation.
+const RED_ZONE: usize = 16;
+
+/// The byte red zones are filled with.
+const CANARY: u8 = 0xfd;
+
+/// The byte freed memory is filled with.
+pub const POISON: u8 = 0x6b;
+
+/// `Header::magic` of a live allocation.
+const LIVE: usize = 0xa110_c8ed;
+
+/// `Header::magic` of a freed allocation.
+const FREED: usize = 0xdead_f4ee;
+
+/// The bookkeeping in front of every allocation, followed by the front red
+/// zone, the caller's memory and the back red zone:
+///
+///   | pad | Header | red zone | memory (layout.size()) | red zone |
+///
+/// `magic` is last so that it survives the inner allocator writing its free
+/// list pointer to the start of a freed block.
+#[repr(C)]
+struct Header {
+    prev: *mut Header,
+    next: *mut Header,
+    caller: usize,
+    size: usize,
+    align: usize,
+    magic: usize,
+}
+
+/// An allocation that has not been freed.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Record {
+    /// The address handed out.
+    pub ptr: usize,
+    /// The size that was asked for.
+    pub size: usize,
+    /// The alignment that was asked for.
+    pub align: usize,
+    /// The return address of the call into the allocator, or 0 if unknown.
+    pub caller: usize,
+}
+
+/// Returns the return address of the enclosing function. It must be inlined
+/// into the function whose caller is wanted, before that makes any calls.
+#[cfg(all(target_arch = "aarch64", not(test)))]
+#[inline(always)]
+pub fn return_address() -> usize {
+    let lr: usize;
+    unsafe { asm!("mov $0, x30" : "=r"(lr) ::: "volatile") };
+    lr
+}
+
+/// Returns 0: return addresses are only read on the Pi.
+#[cfg(not(all(target_arch = "aarch64", not(test))))]
+pub fn return_address() -> usize {
+    0
+}
+
+/// An allocator that checks how `A` is used.
+///
+/// Every allocation is surrounded by red zones filled with a canary, which
+/// are checked when it is freed, and freed memory is filled with `POISON`.
+/// Freeing memory twice, freeing memory that was not allocated and freeing
+/// with a different `Layout` than was allocated with are caught too. All of
+/// these panic with the address of the allocation.
+///
+/// Live allocations are kept in a list along with the caller that made them,
+/// which `live()` copies out to find leaks.
+///
+/// The wrapper derefs to `A`, whose statistics include the extra memory the
+/// checks take.
+pub struct Allocator<A> {
+    inner: A,
+    head: *mut Header,
+}
+
+unsafe impl<A: Send> Send for Allocator<A> {}
+
+impl<A> From<A> for Allocator<A> {
+    fn from(inner: A) -> Allocator<A> {
+        Allocator { inner, head: ptr::null_mut() }
+    }
+}
+
+impl<A> Deref for Allocator<A> {
+    type Target = A;
+
+    fn deref(&self) -> &A {
+        &self.inner
+    }
+}
+
+/// Returns the alignment of the inner allocation for `layout`.
+fn inner_align(layout: Layout) -> usize {
+    max(layout.align(), align_of::<Header>())
+}
+
+/// Returns the offset of the caller's memory in the inner allocation.
+fn offset(layout: Layout) -> usize {
+    align_up(size_of::<Header>() + RED_ZONE, inner_align(layout))
+}
+
+/// Returns the layout of the inner allocation for `layout`.
+fn inner_layout(layout: Layout) -> Option<Layout> {
+    let size = offset(layout).checked_add(layout.size())?.checked_add(RED_ZONE)?;
+    Layout::from_size_align(size, inner_align(layout)).ok()
+}
+
+/// Returns the header of the allocation handed out at `ptr`.
+fn header(ptr: *mut u8) -> *mut Header {
+    (ptr as usize - RED_ZONE - size_of::<Header>()) as *mut Header
+}
+
+/// Returns the offset of the first byte of the `len` bytes at `ptr` that is
+/// not `CANARY`, if any.
+unsafe fn damaged(ptr: *const u8, len: usize) -> Option<usize> {
+    (0..len).find(|&i| *ptr.add(i) != CANARY)
+}
+
+/// Returns the header of the allocation at `ptr` after checking that it is
+/// live, was allocated with `layout` and has intact red zones.
+///
+/// # Panics
+///
+/// Panics if any of these does not hold.
+unsafe fn check(ptr: *mut u8, layout: Layout) -> *mut Header {
+    let header = header(ptr);
+    match (*header).magic {
+        LIVE => {}
+        FREED => panic!("double free of {:p}", ptr),
+        _ => panic!("free of {:p}, which is not a live allocation", ptr),
+    }
+
+    let (size, align) = ((*header).size, (*header).align);
+    if layout.size() != size || layout.align() != align {
+        panic!(
+            "free of {:p} with size {} and align {}, but it was allocated with size {} and align {}",
+            ptr,
+            layout.size(),
+            layout.align(),
+            size,
+            align
+        );
+    }
+
+    if let Some(i) = damaged(ptr.sub(RED_ZONE), RED_ZONE) {
+        panic!("heap underflow: {} bytes before {:p} overwritten", RED_ZONE - i, ptr);
+    }
+    if let Some(i) = damaged(ptr.add(size), RED_ZONE) {
+        panic!("heap overflow: byte {} past the end of {:p} ({} bytes) overwritten", i, ptr, size);
+    }
+
+    header
+}
+
+impl<A> Allocator<A> {
+    /// Copies the newest live allocations into `records`, returning how many
+    /// there are in all.
+    pub fn live(&self, records: &mut [Record]) -> usize {
+        let mut count = 0;
+        let mut header = self.head;
+        while !header.is_null() {
+            unsafe {
+                if let Some(record) = records.get_mut(count) {
+                    *record = Record {
+                        ptr: header as usize + size_of::<Header>() + RED_ZONE,
+                        size: (*header).size,
+                        align: (*header).align,
+                        caller: (*header).caller,
+                    };
+                }
+                header = (*header).next;
+            }
+            count += 1;
+        }
+
+        count
+    }
+}
+
+impl<A: LocalAlloc> LocalAlloc for Allocator<A> {
+    unsafe fn add_region(&mut self, start: usize, end: usize) {
+        self.inner.add_region(start, end)
+    }
+
+    unsafe fn alloc(&mut self, layout: Layout) -> *mut u8 {
+        self.alloc_from(layout, 0)
+    }
+
+    /// Allocates memory for `layout`, recording `caller` and surrounding it
+    /// with red zones.
+    unsafe fn alloc_from(&mut self, layout: Layout, caller: usize) -> *mut u8 {
+        let inner = match inner_layout(layout) {
+            Some(inner) => inner,
+            None => return ptr::null_mut(),
+        };
+
+        let block = self.inner.alloc(inner);
+        if block.is_null() {
+            return block;
+        }
+
+        let ptr = block.add(offset(layout));
+        let header = header(ptr);
+        ptr::write(
+            header,
+            Header {
+                prev: ptr::null_mut(),
+                next: self.head,
+                caller,
+                size: layout.size(),
+                align: layout.align(),
+                magic: LIVE,
+            },
+        );
+        if !self.head.is_null() {
+            (*self.head).prev = header;
+        }
+        self.head = header;
+
+        ptr::write_bytes(ptr.sub(RED_ZONE), CANARY, RED_ZONE);
+        ptr::write_bytes(ptr.add(layout.size()), CANARY, RED_ZONE);
+        ptr
+    }
+
+    unsafe fn realloc(&mut self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.realloc_from(ptr, layout, new_size, 0)
+    }
+
+    /// Moves the memory at `ptr` to a new allocation made on behalf of
+    /// `caller`. The memory is always moved, so that stale pointers to the
+    /// old allocation find it poisoned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory at `ptr` does not pass the checks of `dealloc()`.
+    unsafe fn realloc_from(
+        &mut self,
+        ptr: *mut u8,
+        layout: Layout,
+        new_size: usize,
+        caller: usize,
+    ) -> *mut u8 {
+        check(ptr, layout);
+        let new_layout = match Layout::from_size_align(new_size, layout.align()) {
+            Ok(new_layout) => new_layout,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        let new_ptr = self.alloc_from(new_layout, caller);
+        if !new_ptr.is_null() {
+            ptr::copy_nonoverlapping(ptr, new_ptr, min(layout.size(), new_size));
+            self.dealloc(ptr, layout);
+        }
+        new_ptr
+    }
+
+    /// Checks and poisons the memory at `ptr`, then frees it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ptr` is not a live allocation, if `layout` is not the
+    /// layout it was allocated with or if either of its red zones was
+    /// overwritten.
+    unsafe fn dealloc(&mut self, ptr: *mut u8, layout: Layout) {
+        if ptr.is_null() {
+            return;
+        }
+
+        let header = check(ptr, layout);
+        let size = layout.size();
+
+        let (prev, next) = ((*header).prev, (*header).next);
+        match prev.is_null() {
+            true => self.head = next,
+            false => (*prev).next = next,
+        }
+        if !next.is_null() {
+            (*next).prev = prev;
+        }
+
+        (*header).magic = FREED;
+        ptr::write_bytes(ptr.sub(RED_ZONE), POISON, RED_ZONE + size + RED_ZONE);
+        self.inner.dealloc(ptr.sub(offset(layout)), inner_layout(layout).expect("checked on alloc"));
+    }
+}
+
+impl<A: fmt::Debug> fmt::Debug for Allocator<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Debug Alloc").field("inner", &self.inner).finish()
+    }
+}
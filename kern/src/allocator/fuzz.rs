@@ -0,0 +1,397 @@
+//! Randomized testing of the allocators.
+//!
+//! `generate()` makes a random sequence of operations, `replay()` runs it
+//! against an allocator over a region of host memory and checks the
+//! allocator's invariants after every step, and `shrink()` cuts a failing
+//! sequence down to a minimal one that still fails.
+//!
+//! Every allocator the harness knows of implements `Subject`; `mod fuzz` in
+//! `tests.rs` runs each of them over many seeds. Set `FUZZ_SEEDS` to run
+//! more of them:
+//!
+//! ```sh
+//! FUZZ_SEEDS=10000 cargo test fuzz
+//! ```
+
+extern crate alloc;
+use alloc::raw_vec::RawVec;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::panic::{self, AssertUnwindSafe};
+
+use core::alloc::Layout;
+use core::cell::Cell;
+use core::cmp::min;
+use core::fmt;
+use core::ptr;
+use core::slice;
+
+use crate::allocator::util::align_up;
+use crate::allocator::{bin, buddy, bump, debug, LocalAlloc};
+
+const PAGE_SIZE: usize = 1 << 12;
+
+/// An allocator the harness can test.
+pub trait Subject: LocalAlloc {
+    /// Creates the allocator over `[start, end)`.
+    fn create(start: usize, end: usize) -> Self;
+
+    /// Returns the bytes available for allocation, or `None` if freed memory
+    /// is never reused, in which case it is not checked that all memory comes
+    /// back once everything is freed.
+    fn free(&self) -> Option<usize>;
+}
+
+impl Subject for bin::Allocator {
+    fn create(start: usize, end: usize) -> Self {
+        bin::Allocator::new(start, end)
+    }
+
+    fn free(&self) -> Option<usize> {
+        Some(self.stats().free)
+    }
+}
+
+impl Subject for bump::Allocator {
+    fn create(start: usize, end: usize) -> Self {
+        bump::Allocator::new(start, end)
+    }
+
+    fn free(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl Subject for buddy::Allocator {
+    fn create(start: usize, end: usize) -> Self {
+        buddy::Allocator::new(start, end)
+    }
+
+    fn free(&self) -> Option<usize> {
+        Some(self.stats().free)
+    }
+}
+
+impl<A: Subject> Subject for debug::Allocator<A> {
+    fn create(start: usize, end: usize) -> Self {
+        debug::Allocator::from(A::create(start, end))
+    }
+
+    fn free(&self) -> Option<usize> {
+        (**self).free()
+    }
+}
+
+/// A step of a test sequence. Allocations are referred to by their position
+/// among the live ones, modulo their number, so that a sequence stays valid
+/// when steps are removed from it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Op {
+    Alloc { size: usize, align: usize },
+    Dealloc(usize),
+    Realloc(usize, usize),
+}
+
+/// A small deterministic pseudo-random number generator.
+pub struct Lcg(u64);
+
+impl Lcg {
+    pub fn new(seed: u64) -> Lcg {
+        Lcg(seed ^ 0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Returns a number in `[0, bound)`.
+    pub fn next(&mut self, bound: usize) -> usize {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.0 >> 33) as usize % bound
+    }
+}
+
+/// Returns a random size, mostly small ones.
+fn size(rng: &mut Lcg) -> usize {
+    match rng.next(10) {
+        0..=5 => 1 + rng.next(64),
+        6..=8 => 1 + rng.next(1024),
+        _ => 1 + rng.next(8192),
+    }
+}
+
+/// Returns `len` random operations.
+pub fn generate(seed: u64, len: usize) -> Vec<Op> {
+    let mut rng = Lcg::new(seed);
+    (0..len)
+        .map(|_| match rng.next(10) {
+            0..=4 => {
+                let align = match rng.next(20) {
+                    0 => PAGE_SIZE,
+                    _ => 1 << rng.next(7),
+                };
+                Op::Alloc { size: size(&mut rng), align }
+            }
+            5..=7 => Op::Dealloc(rng.next(64)),
+            _ => Op::Realloc(rng.next(64), size(&mut rng)),
+        })
+        .collect()
+}
+
+/// How a sequence failed: the step it failed at and what went wrong.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    pub step: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "step {}: {}", self.step, self.message)
+    }
+}
+
+/// A live allocation.
+#[derive(Debug, Copy, Clone)]
+struct Live {
+    ptr: usize,
+    layout: Layout,
+    tag: u8,
+}
+
+/// Fills the memory of `live` with its tag.
+unsafe fn fill(live: &Live) {
+    ptr::write_bytes(live.ptr as *mut u8, live.tag, live.layout.size());
+}
+
+/// Checks that the first `len` bytes of `live` still hold its tag.
+unsafe fn intact(live: &Live, len: usize) -> Result<(), String> {
+    let bytes = slice::from_raw_parts(live.ptr as *const u8, len);
+    match bytes.iter().position(|&byte| byte != live.tag) {
+        Some(i) => Err(format!("byte {} of the allocation at {:#x} was overwritten", i, live.ptr)),
+        None => Ok(()),
+    }
+}
+
+/// The state of a replay: the allocator, its region and what is live.
+struct Run<A> {
+    allocator: A,
+    start: usize,
+    end: usize,
+    live: Vec<Live>,
+    next_tag: u8,
+}
+
+impl<A: Subject> Run<A> {
+    /// Checks a pointer the allocator returned for `layout`, and records it
+    /// as live if it is not null.
+    fn allocated(&mut self, ptr: *mut u8, layout: Layout) -> Result<(), String> {
+        if ptr.is_null() {
+            return Ok(());
+        }
+
+        let (addr, end) = (ptr as usize, ptr as usize + layout.size());
+        if addr % layout.align() != 0 {
+            return Err(format!("{:#x} is not aligned to {}", addr, layout.align()));
+        }
+        if addr < self.start || end > self.end {
+            return Err(format!(
+                "{:#x}..{:#x} is outside the region {:#x}..{:#x}",
+                addr, end, self.start, self.end
+            ));
+        }
+
+        let mut by_addr: BTreeMap<usize, &Live> = BTreeMap::new();
+        for live in &self.live {
+            by_addr.insert(live.ptr, live);
+        }
+        let before = by_addr.range(..end).next_back();
+        if let Some((_, other)) = before.filter(|(_, other)| other.ptr + other.layout.size() > addr) {
+            return Err(format!(
+                "{:#x}..{:#x} overlaps the live allocation at {:#x}..{:#x}",
+                addr,
+                end,
+                other.ptr,
+                other.ptr + other.layout.size()
+            ));
+        }
+
+        self.next_tag = self.next_tag.wrapping_add(1);
+        let live = Live { ptr: addr, layout, tag: self.next_tag };
+        unsafe { fill(&live) };
+        self.live.push(live);
+        Ok(())
+    }
+
+    /// Runs one step.
+    unsafe fn step(&mut self, op: Op) -> Result<(), String> {
+        match op {
+            Op::Alloc { size, align } => {
+                let layout = Layout::from_size_align(size, align).unwrap();
+                let ptr = self.allocator.alloc(layout);
+                self.allocated(ptr, layout)?;
+            }
+            Op::Dealloc(i) if !self.live.is_empty() => {
+                let live = self.live.remove(i % self.live.len());
+                intact(&live, live.layout.size())?;
+                self.allocator.dealloc(live.ptr as *mut u8, live.layout);
+            }
+            Op::Realloc(i, new_size) if !self.live.is_empty() => {
+                let i = i % self.live.len();
+                let live = self.live[i];
+                intact(&live, live.layout.size())?;
+
+                let ptr = self.allocator.realloc(live.ptr as *mut u8, live.layout, new_size);
+                if !ptr.is_null() {
+                    self.live.remove(i);
+                    let moved = Live { ptr: ptr as usize, ..live };
+                    intact(&moved, min(live.layout.size(), new_size))
+                        .map_err(|e| format!("after realloc, {}", e))?;
+                    self.allocated(ptr, Layout::from_size_align(new_size, live.layout.align()).unwrap())?;
+                }
+            }
+            _ => {}
+        }
+
+        for live in &self.live {
+            intact(live, live.layout.size())?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs `ops` against a new `A` over `mem` bytes starting `offset` bytes
+/// past a page boundary, checking after each step that:
+///
+///   * every allocation is aligned and lies within the region,
+///   * no two live allocations overlap,
+///   * no live allocation is overwritten, and
+///   * once everything is freed, all memory is available again.
+///
+/// A panic in the allocator is a failure too.
+pub fn replay<A: Subject>(ops: &[Op], mem: usize, offset: usize) -> Result<(), Failure> {
+    let region: RawVec<u8> = RawVec::with_capacity(mem + 2 * PAGE_SIZE);
+    let start = align_up(region.ptr() as usize, PAGE_SIZE) + offset;
+    let end = start + mem;
+
+    let current = Cell::new(0);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| unsafe {
+        let mut run = Run { allocator: A::create(start, end), start, end, live: Vec::new(), next_tag: 0 };
+        let initial = run.allocator.free();
+
+        for (step, &op) in ops.iter().enumerate() {
+            current.set(step);
+            run.step(op).map_err(|message| Failure { step, message })?;
+        }
+
+        current.set(ops.len());
+        for live in run.live.drain(..) {
+            run.allocator.dealloc(live.ptr as *mut u8, live.layout);
+        }
+        match (initial, run.allocator.free()) {
+            (Some(initial), Some(free)) if free != initial => Err(Failure {
+                step: ops.len(),
+                message: format!("memory lost: {} bytes free after freeing everything, {} at first", free, initial),
+            }),
+            _ => Ok(()),
+        }
+    }));
+
+    result.unwrap_or_else(|cause| {
+        let message = match cause.downcast_ref::<String>() {
+            Some(message) => message.clone(),
+            None => cause.downcast_ref::<&str>().map_or("panicked", |message| message).to_string(),
+        };
+        Err(Failure { step: current.get(), message: format!("panicked: {}", message) })
+    })
+}
+
+/// Returns the smallest sequence found, by removing steps from `ops` and
+/// making allocations smaller and less aligned, that still fails
+/// `fails`.
+pub fn shrink<F: Fn(&[Op]) -> bool>(ops: &[Op], fails: F) -> Vec<Op> {
+    let mut ops = ops.to_vec();
+
+    // Remove ever smaller chunks of steps for as long as that still fails.
+    let mut chunk = ops.len() / 2;
+    while chunk > 0 {
+        let mut i = 0;
+        while i + chunk <= ops.len() {
+            let mut shorter = ops.clone();
+            shorter.drain(i..i + chunk);
+            if fails(&shorter) {
+                ops = shorter;
+            } else {
+                i += chunk;
+            }
+        }
+        chunk /= 2;
+    }
+
+    // Then make each step that is left as small as it can be.
+    for i in 0..ops.len() {
+        let with = |op: Op| {
+            let mut candidate = ops.clone();
+            candidate[i] = op;
+            candidate
+        };
+
+        let simplest = match ops[i] {
+            Op::Alloc { size, align } => {
+                let size = smallest(size, |size| fails(&with(Op::Alloc { size, align })));
+                let mut align = align;
+                while align > 1 && fails(&with(Op::Alloc { size, align: align / 2 })) {
+                    align /= 2;
+                }
+                Op::Alloc { size, align }
+            }
+            Op::Realloc(j, size) => Op::Realloc(j, smallest(size, |size| fails(&with(Op::Realloc(j, size))))),
+            Op::Dealloc(j) => Op::Dealloc(smallest(j + 1, |j| fails(&with(Op::Dealloc(j - 1)))) - 1),
+        };
+        ops[i] = simplest;
+    }
+
+    ops
+}
+
+/// Returns the smallest `n` in `[1, max]` for which `fails(n)`, assuming
+/// `fails(max)` and that it fails for every number above the smallest one.
+fn smallest<F: Fn(usize) -> bool>(max: usize, fails: F) -> usize {
+    let (mut lo, mut hi) = (1, max);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        match fails(mid) {
+            true => hi = mid,
+            false => lo = mid + 1,
+        }
+    }
+    hi
+}
+
+/// Replays `seeds` random sequences of `len` steps against `A`, or
+/// `FUZZ_SEEDS` of them if it is set, panicking with a shrunk reproducer at
+/// the first one that fails.
+pub fn fuzz<A: Subject>(seeds: u64, len: usize) {
+    const MEM: usize = 1 << 16;
+
+    let seeds = env::var("FUZZ_SEEDS").ok().and_then(|seeds| seeds.parse().ok()).unwrap_or(seeds);
+
+    for seed in 0..seeds {
+        let ops = generate(seed, len);
+        let offset = (seed as usize * 7) % 64;
+        if let Err(failure) = replay::<A>(&ops, MEM, offset) {
+            let hook = panic::take_hook();
+            panic::set_hook(Box::new(|_| {}));
+            let minimal = shrink(&ops, |ops| replay::<A>(ops, MEM, offset).is_err());
+            panic::set_hook(hook);
+
+            let shrunk = replay::<A>(&minimal, MEM, offset).unwrap_err();
+            panic!(
+                "seed {} failed at {}\nminimal reproducer ({} steps, offset {}): {:?}\nwhich fails at {}",
+                seed,
+                failure,
+                minimal.len(),
+                offset,
+                minimal,
+                shrunk
+            );
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use core::cmp::{max, min};
+use core::fmt;
+use core::ops::Deref;
+
+/// The most disjoint regions a `Regions` holds.
+pub const MAX_REGIONS: usize = 16;
+
+/// The range of physical memory `[start, end)`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Region {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Region {
+    /// Returns the region `[start, end)`.
+    pub const fn new(start: usize, end: usize) -> Region {
+        Region { start, end }
+    }
+
+    /// Returns the number of bytes in the region.
+    pub fn size(&self) -> usize {
+        self.end.saturating_sub(self.start)
+    }
+
+    /// Returns `true` if the region holds no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.size() == 0
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#010x}-{:#010x}", self.start, self.end)
+    }
+}
+
+/// A set of disjoint, non-adjacent regions, sorted by address. Needs no heap,
+/// so that it can describe memory before there is one.
+#[derive(Copy, Clone, PartialEq)]
+pub struct Regions {
+    regions: [Region; MAX_REGIONS],
+    len: usize,
+}
+
+impl Regions {
+    /// Returns an empty set.
+    pub const fn new() -> Regions {
+        Regions { regions: [Region::new(0, 0); MAX_REGIONS], len: 0 }
+    }
+
+    /// Returns the number of bytes in all regions.
+    pub fn size(&self) -> usize {
+        self.iter().map(Region::size).sum()
+    }
+
+    /// Adds `region` to the set, merging it with the regions it overlaps or
+    /// touches. Returns `false`, leaving the set as it was, if it would take
+    /// more than `MAX_REGIONS` regions.
+    pub fn add(&mut self, region: Region) -> bool {
+        if region.is_empty() {
+            return true;
+        }
+
+        // The regions below `first` end before `region` starts, the regions
+        // from `last` on start after it ends; those in between merge with it.
+        let first = self.iter().position(|r| r.end >= region.start).unwrap_or(self.len);
+        let last = self.iter().position(|r| r.start > region.end).unwrap_or(self.len);
+
+        let mut merged = region;
+        for r in &self.regions[first..last] {
+            merged = Region::new(min(merged.start, r.start), max(merged.end, r.end));
+        }
+
+        let len = self.len - (last - first) + 1;
+        if len > MAX_REGIONS {
+            return false;
+        }
+
+        self.regions.copy_within(last..self.len, first + 1);
+        self.regions[first] = merged;
+        self.len = len;
+        true
+    }
+
+    /// Removes `region` from the set, shrinking or splitting the regions it
+    /// overlaps. Returns `false`, leaving the set as it was, if it would take
+    /// more than `MAX_REGIONS` regions.
+    pub fn remove(&mut self, region: Region) -> bool {
+        if region.is_empty() {
+            return true;
+        }
+
+        let mut result = Regions::new();
+        for r in self.iter() {
+            let below = Region::new(r.start, min(r.end, region.start));
+            let above = Region::new(max(r.start, region.end), r.end);
+            for part in &[below, above] {
+                if !part.is_empty() && !result.push(*part) {
+                    return false;
+                }
+            }
+        }
+
+        *self = result;
+        true
+    }
+
+    /// Removes everything outside of `region` from the set.
+    pub fn intersect(&mut self, region: Region) {
+        let mut result = Regions::new();
+        for r in self.iter() {
+            let part = Region::new(max(r.start, region.start), min(r.end, region.end));
+            if !part.is_empty() {
+                result.push(part);
+            }
+        }
+
+        *self = result;
+    }
+
+    /// Appends `region`, which must come after every region in the set.
+    fn push(&mut self, region: Region) -> bool {
+        if self.len == MAX_REGIONS {
+            return false;
+        }
+
+        self.regions[self.len] = region;
+        self.len += 1;
+        true
+    }
+}
+
+impl Deref for Regions {
+    type Target = [Region];
+
+    fn deref(&self) -> &[Region] {
+        &self.regions[..self.len]
+    }
+}
+
+impl fmt::Debug for Regions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
@@ -0,0 +1,288 @@
+use alloc::alloc::{alloc, dealloc};
+use core::alloc::Layout;
+use core::cmp::max;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::{align_of, size_of};
+use core::ops::{Deref, DerefMut};
+use core::ptr::{self, NonNull};
+
+use crate::allocator::linked_list::LinkedList;
+use crate::allocator::util::align_up;
+use crate::mutex::Mutex;
+
+/// Slabs are at least a page.
+const PAGE_SIZE: usize = 1 << 12;
+
+/// Slabs are made large enough to hold at least this many objects.
+const MIN_OBJECTS: usize = 8;
+
+/// The header at the start of every slab, followed by its objects.
+struct Slab {
+    /// The next slab of the cache.
+    next: *mut Slab,
+    /// The free object slots of this slab.
+    free: LinkedList,
+    /// The number of objects in use.
+    used: usize,
+}
+
+/// Usage counters of a `SlabCache`.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct SlabStats {
+    /// The number of slabs the cache holds.
+    pub slabs: usize,
+    /// The number of objects each slab holds.
+    pub objects_per_slab: usize,
+    /// The number of objects in use.
+    pub live: usize,
+    /// The number of objects handed out so far.
+    pub allocated: usize,
+    /// The number of empty slabs returned to the global allocator so far.
+    pub reclaimed: usize,
+    /// The number of allocations that failed for want of memory.
+    pub failed: usize,
+}
+
+/// The counters of a cache that has never been used.
+const NO_STATS: SlabStats = SlabStats {
+    slabs: 0,
+    objects_per_slab: 0,
+    live: 0,
+    allocated: 0,
+    reclaimed: 0,
+    failed: 0,
+};
+
+// Slab caches are not used by the kernel yet.
+#[allow(dead_code)]
+impl SlabStats {
+    /// Returns the number of objects that can be allocated without taking
+    /// a new slab.
+    pub fn free(&self) -> usize {
+        self.slabs * self.objects_per_slab - self.live
+    }
+}
+
+/// The slabs of a cache, linked through their headers.
+struct Slabs {
+    head: *mut Slab,
+    stats: SlabStats,
+}
+
+unsafe impl Send for Slabs {}
+
+/// A cache of objects of type `T`.
+///
+/// The cache takes memory from the global allocator a slab at a time (a page
+/// or more) and carves each slab into slots for `T`. Freed slots go back on
+/// their slab's free list and are reused before a new slab is taken. Slabs
+/// that no longer hold any objects are returned by `reclaim()`.
+///
+/// ```rust,ignore
+/// static FILES: SlabCache<File> = SlabCache::new("files");
+///
+/// let file = FILES.alloc(File::new()).expect("out of memory");
+/// ```
+#[allow(dead_code)]
+pub struct SlabCache<T> {
+    name: &'static str,
+    slabs: Mutex<Slabs>,
+    _marker: PhantomData<T>,
+}
+
+unsafe impl<T: Send> Sync for SlabCache<T> {}
+
+#[allow(dead_code)]
+impl<T> SlabCache<T> {
+    /// Returns a new, empty cache called `name`. No memory is taken until
+    /// the first object is allocated.
+    pub const fn new(name: &'static str) -> SlabCache<T> {
+        SlabCache {
+            name,
+            slabs: Mutex::new(Slabs { head: ptr::null_mut(), stats: NO_STATS }),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the name of the cache.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the alignment of each object slot. Free slots hold a
+    /// `LinkedList` pointer, so they are at least as aligned as `usize`.
+    fn slot_align() -> usize {
+        max(align_of::<T>(), align_of::<usize>())
+    }
+
+    /// Returns the distance between object slots.
+    fn slot_size() -> usize {
+        align_up(max(size_of::<T>(), size_of::<usize>()), Self::slot_align())
+    }
+
+    /// Returns the offset of the first slot from the start of a slab.
+    fn first_slot() -> usize {
+        align_up(size_of::<Slab>(), Self::slot_align())
+    }
+
+    /// Returns the layout of each slab.
+    fn slab_layout() -> Layout {
+        let size = Self::first_slot() + MIN_OBJECTS * Self::slot_size();
+        let size = max(size.next_power_of_two(), PAGE_SIZE);
+        let align = max(Self::slot_align(), align_of::<Slab>());
+        Layout::from_size_align(size, align).expect("slab layout")
+    }
+
+    /// Returns the number of objects in each slab.
+    pub fn objects_per_slab() -> usize {
+        (Self::slab_layout().size() - Self::first_slot()) / Self::slot_size()
+    }
+
+    /// Moves `value` into a free slot and returns a box owning it, or `None`
+    /// if no memory is left.
+    pub fn alloc(&self, value: T) -> Option<SlabBox<T>> {
+        self.alloc_with(|| value)
+    }
+
+    /// Constructs an object with `ctor` directly in a free slot and returns a
+    /// box owning it, or `None` if no memory is left, in which case `ctor` is
+    /// not called.
+    pub fn alloc_with<F: FnOnce() -> T>(&self, ctor: F) -> Option<SlabBox<T>> {
+        let (slab, slot) = unsafe { self.take()? };
+        unsafe { ptr::write(slot.as_ptr(), ctor()) };
+        Some(SlabBox { ptr: slot, slab, cache: self })
+    }
+
+    /// Takes a free slot from the first slab that has one, taking a new slab
+    /// if none does.
+    unsafe fn take(&self) -> Option<(NonNull<Slab>, NonNull<T>)> {
+        let mut slabs = self.slabs.lock();
+
+        let mut slab = slabs.head;
+        while !slab.is_null() && (*slab).free.is_empty() {
+            slab = (*slab).next;
+        }
+
+        if slab.is_null() {
+            slab = alloc(Self::slab_layout()) as *mut Slab;
+            if slab.is_null() {
+                slabs.stats.failed += 1;
+                return None;
+            }
+
+            ptr::write(slab, Slab { next: slabs.head, free: LinkedList::new(), used: 0 });
+            for i in (0..Self::objects_per_slab()).rev() {
+                let slot = slab as usize + Self::first_slot() + i * Self::slot_size();
+                (*slab).free.push(slot as *mut usize);
+            }
+
+            slabs.head = slab;
+            slabs.stats.slabs += 1;
+        }
+
+        let slot = (*slab).free.pop()? as *mut T;
+        (*slab).used += 1;
+        slabs.stats.live += 1;
+        slabs.stats.allocated += 1;
+        Some((NonNull::new_unchecked(slab), NonNull::new_unchecked(slot)))
+    }
+
+    /// Puts `slot`, whose object has been dropped, back on the free list of
+    /// `slab`.
+    unsafe fn free(&self, slab: NonNull<Slab>, slot: NonNull<T>) {
+        let mut slabs = self.slabs.lock();
+        let slab = slab.as_ptr();
+        (*slab).free.push(slot.as_ptr() as *mut usize);
+        (*slab).used -= 1;
+        slabs.stats.live -= 1;
+    }
+
+    /// Returns every slab without objects to the global allocator. Returns the
+    /// number of slabs returned.
+    pub fn reclaim(&self) -> usize {
+        let mut slabs = self.slabs.lock();
+        let mut reclaimed = 0;
+
+        let mut link: *mut *mut Slab = &mut slabs.head;
+        unsafe {
+            while !(*link).is_null() {
+                let slab = *link;
+                if (*slab).used == 0 {
+                    *link = (*slab).next;
+                    dealloc(slab as *mut u8, Self::slab_layout());
+                    reclaimed += 1;
+                } else {
+                    link = &mut (*slab).next;
+                }
+            }
+        }
+
+        slabs.stats.slabs -= reclaimed;
+        slabs.stats.reclaimed += reclaimed;
+        reclaimed
+    }
+
+    /// Returns the cache's usage counters.
+    pub fn stats(&self) -> SlabStats {
+        let mut stats = self.slabs.lock().stats;
+        stats.objects_per_slab = Self::objects_per_slab();
+        stats
+    }
+}
+
+impl<T> Drop for SlabCache<T> {
+    /// Returns every slab. No objects can be left, as they borrow the cache.
+    fn drop(&mut self) {
+        self.reclaim();
+    }
+}
+
+impl<T> fmt::Debug for SlabCache<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SlabCache")
+            .field("name", &self.name)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+/// An object of type `T` in a slot of a `SlabCache`, which it returns to
+/// the cache when dropped.
+pub struct SlabBox<'a, T> {
+    ptr: NonNull<T>,
+    slab: NonNull<Slab>,
+    cache: &'a SlabCache<T>,
+}
+
+unsafe impl<'a, T: Send> Send for SlabBox<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for SlabBox<'a, T> {}
+
+impl<'a, T> Deref for SlabBox<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<'a, T> DerefMut for SlabBox<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<'a, T> Drop for SlabBox<'a, T> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(self.ptr.as_ptr());
+            self.cache.free(self.slab, self.ptr);
+        }
+    }
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for SlabBox<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
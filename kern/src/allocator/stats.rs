@@ -0,0 +1,95 @@
+use core::fmt;
+
+/// The number of size classes free memory is reported in: class `k` holds
+/// blocks of `2^(k + 3)` bytes.
+pub const SIZE_CLASSES: usize = 30;
+
+/// A snapshot of an allocator's heap usage.
+///
+/// The counters are kept as memory is allocated and freed; `free`,
+/// `largest_free` and `free_lists` are measured when the snapshot is taken.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Stats {
+    /// Bytes requested by every successful allocation so far.
+    pub allocated: usize,
+    /// Bytes released by every deallocation so far.
+    pub freed: usize,
+    /// The number of allocations not yet freed.
+    pub live: usize,
+    /// The most bytes that were in use at once.
+    pub peak: usize,
+    /// The number of allocations that returned null.
+    pub failed: usize,
+    /// Bytes available for allocation.
+    pub free: usize,
+    /// The size of the largest block that can be allocated at once.
+    pub largest_free: usize,
+    /// The number of free blocks in each size class.
+    pub free_lists: [usize; SIZE_CLASSES],
+}
+
+impl Stats {
+    /// Returns the bytes currently allocated.
+    pub fn in_use(&self) -> usize {
+        self.allocated - self.freed
+    }
+
+    /// Returns how fragmented free memory is, from 0 (all of it is one
+    /// block) to 100 percent.
+    pub fn fragmentation(&self) -> usize {
+        match self.free {
+            0 => 0,
+            free => 100 - self.largest_free * 100 / free,
+        }
+    }
+
+    /// Records an allocation of `size` bytes that returned `ptr`.
+    pub fn record_alloc(&mut self, size: usize, ptr: *mut u8) {
+        if ptr.is_null() {
+            self.failed += 1;
+            return;
+        }
+
+        self.allocated += size;
+        self.live += 1;
+        if self.in_use() > self.peak {
+            self.peak = self.in_use();
+        }
+    }
+
+    /// Records the deallocation of `size` bytes.
+    pub fn record_dealloc(&mut self, size: usize) {
+        self.freed += size;
+        self.live -= 1;
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "in use:     {} bytes in {} allocations", self.in_use(), self.live)?;
+        writeln!(f, "peak:       {} bytes", self.peak)?;
+        writeln!(f, "allocated:  {} bytes", self.allocated)?;
+        writeln!(f, "freed:      {} bytes", self.freed)?;
+        writeln!(f, "failed:     {} allocations", self.failed)?;
+        writeln!(
+            f,
+            "free:       {} bytes, largest block {} bytes ({}% fragmented)",
+            self.free,
+            self.largest_free,
+            self.fragmentation()
+        )?;
+
+        f.write_str("free lists:")?;
+        let mut empty = true;
+        for (class, &len) in self.free_lists.iter().enumerate() {
+            if len > 0 {
+                write!(f, " {}x{}", len, 1usize << (class + 3))?;
+                empty = false;
+            }
+        }
+        if empty {
+            f.write_str(" none")?;
+        }
+        Ok(())
+    }
+}
@@ -1,3 +1,31 @@
+extern crate alloc;
+use alloc::raw_vec::RawVec;
+
+use core::alloc::Layout;
+
+use crate::allocator::util::align_up;
+
+const PAGE_SIZE: usize = 1 << 12;
+
+macro layout($size:expr, $align:expr) {
+    Layout::from_size_align($size, $align).unwrap()
+}
+
+/// Runs `$block` with `$region` bound to the `(start, end)` of `$mem` bytes
+/// of memory, which live until `$block` returns. The memory is page-aligned,
+/// so that the outcome does not depend on where the host's allocator happens
+/// to put it.
+macro with_region($mem:expr, |$region:pat| $block:expr) {{
+    let mem: RawVec<u8> = RawVec::with_capacity($mem + PAGE_SIZE);
+    let start = align_up(mem.ptr() as usize, PAGE_SIZE);
+    let $region = (start, start + $mem);
+
+    #[allow(unused_unsafe)]
+    unsafe {
+        $block
+    }
+}}
+
 mod align_util {
     use crate::allocator::util::{align_down, align_up};
 
@@ -71,39 +99,33 @@ mod align_util {
 }
 
 mod allocator {
-    extern crate alloc;
-    use alloc::raw_vec::RawVec;
-
     use core::alloc::Layout;
 
-    use crate::allocator::{bin, bump, LocalAlloc};
+    use super::{layout, with_region};
+    use crate::allocator::{bin, buddy, bump, LocalAlloc};
 
     macro_rules! test_allocators {
         (@$kind:ident, $name:ident, $mem:expr, |$info:pat| $block:expr) => {
             #[test]
             fn $name() {
-                let mem: RawVec<u8> = RawVec::with_capacity($mem);
-                let start = mem.ptr() as usize;
-                let end = start + $mem;
-
-                let allocator = $kind::Allocator::new(start, end);
-                let $info = (start, end, allocator);
-
-                #[allow(unused_unsafe)]
-                unsafe {
+                with_region!($mem, |(start, end)| {
+                    let allocator = $kind::Allocator::new(start, end);
+                    let $info = (start, end, allocator);
                     $block
-                }
+                })
             }
         };
 
-        ($bin:ident, $bump:ident, $mem:expr, |$info:pat| $block:expr) => (
+        (@bin $bin:ident, @buddy $buddy:ident, $mem:expr, |$info:pat| $block:expr) => (
             test_allocators!(@bin, $bin, $mem, |$info| $block);
-            test_allocators!(@bump, $bump, $mem, |$info| $block);
+            test_allocators!(@buddy, $buddy, $mem, |$info| $block);
         );
-    }
 
-    macro layout($size:expr, $align:expr) {
-        Layout::from_size_align($size, $align).unwrap()
+        ($bin:ident, $bump:ident, $buddy:ident, $mem:expr, |$info:pat| $block:expr) => (
+            test_allocators!(@bin, $bin, $mem, |$info| $block);
+            test_allocators!(@bump, $bump, $mem, |$info| $block);
+            test_allocators!(@buddy, $buddy, $mem, |$info| $block);
+        );
     }
 
     macro test_layouts($layouts:expr, $start:expr, $end:expr, $a:expr) {
@@ -157,12 +179,12 @@ mod allocator {
         }
     }
 
-    test_allocators!(bin_exhausted, bump_exhausted, 128, |(_, _, mut a)| {
+    test_allocators!(bin_exhausted, bump_exhausted, buddy_exhausted, 128, |(_, _, mut a)| {
         let result = a.alloc(layout!(1024, 128));
         assert!(result.is_null());
     });
 
-    test_allocators!(bin_alloc, bump_alloc, 8 * (1 << 20), |(start, end, a)| {
+    test_allocators!(bin_alloc, bump_alloc, buddy_alloc, 8 * (1 << 20), |(start, end, a)| {
         let layouts = [
             layout!(16, 16),
             layout!(16, 128),
@@ -188,7 +210,7 @@ mod allocator {
         test_layouts!(layouts, start, end, a);
     });
 
-    test_allocators!(bin_alloc_2, bump_alloc_2, 16 * (1 << 20), |(
+    test_allocators!(bin_alloc_2, bump_alloc_2, buddy_alloc_2, 16 * (1 << 20), |(
         start,
         end,
         a,
@@ -208,7 +230,7 @@ mod allocator {
         }
     }
 
-    test_allocators!(bin_dealloc_s, bump_dealloc_s, 4096, |(_, _, mut a)| {
+    test_allocators!(bin_dealloc_s, bump_dealloc_s, buddy_dealloc_s, 4096, |(_, _, mut a)| {
         let layouts = [layout!(16, 16), layout!(16, 128), layout!(16, 256)];
 
         let mut pointers: Vec<(usize, Layout)> = vec![];
@@ -226,7 +248,7 @@ mod allocator {
         }
     });
 
-    test_allocators!(@bin, bin_dealloc_1, 65536, |(_, _, mut a)| {
+    test_allocators!(@bin bin_dealloc_1, @buddy buddy_dealloc_1, 65536, |(_, _, mut a)| {
         let layouts = [
             layout!(16, 16),
             layout!(16, 256),
@@ -266,7 +288,7 @@ mod allocator {
         }
     });
 
-    test_allocators!(@bin, bin_dealloc_2, 8192, |(_, _, mut a)| {
+    test_allocators!(@bin bin_dealloc_2, @buddy buddy_dealloc_2, 8192, |(_, _, mut a)| {
         let layouts = [
             layout!(3072, 16),
             layout!(512, 32),
@@ -291,6 +313,748 @@ mod allocator {
     });
 }
 
+mod buddy {
+    use core::alloc::Layout;
+
+    use super::{layout, with_region};
+    use crate::allocator::fuzz::Lcg;
+    use crate::allocator::{buddy, LocalAlloc};
+
+    #[test]
+    fn merges_freed_blocks() {
+        with_region!(1 << 16, |(start, end)| {
+            let mut a = buddy::Allocator::new(start, end);
+            let initial = a.stats();
+
+            // Fill memory with small blocks, then free them all.
+            let mut ptrs = vec![];
+            loop {
+                let ptr = a.alloc(layout!(64, 8));
+                if ptr.is_null() {
+                    break;
+                }
+                ptrs.push(ptr);
+            }
+            assert!(a.alloc(layout!(4096, 8)).is_null());
+
+            for ptr in ptrs {
+                a.dealloc(ptr, layout!(64, 8));
+            }
+
+            // The blocks have been merged back into the original ones.
+            let stats = a.stats();
+            assert_eq!(stats.free_lists, initial.free_lists);
+            assert_eq!(stats.largest_free, initial.largest_free);
+
+            let ptr = a.alloc(layout!(initial.largest_free, 8));
+            assert!(!ptr.is_null());
+        });
+    }
+
+    #[test]
+    fn survives_random_churn() {
+        with_region!(1 << 20, |(start, end)| {
+            let mut a = buddy::Allocator::new(start, end);
+            let initial = a.stats();
+            let mut rng = Lcg::new(0x5eed);
+            let mut live: Vec<(*mut u8, Layout)> = vec![];
+
+            for _ in 0..20_000 {
+                if live.is_empty() || rng.next(3) > 0 {
+                    let layout = layout!(1 + rng.next(2048), 1 << rng.next(13));
+                    let ptr = a.alloc(layout);
+                    if ptr.is_null() {
+                        continue;
+                    }
+
+                    assert_eq!(ptr as usize % layout.align(), 0);
+                    ::core::ptr::write_bytes(ptr, 0xAF, layout.size());
+                    live.push((ptr, layout));
+                } else {
+                    let (ptr, layout) = live.swap_remove(rng.next(live.len()));
+                    a.dealloc(ptr, layout);
+                }
+            }
+
+            for (ptr, layout) in live {
+                a.dealloc(ptr, layout);
+            }
+
+            let stats = a.stats();
+            assert_eq!(stats.free, initial.free);
+            assert_eq!(stats.free_lists, initial.free_lists);
+            assert_eq!(stats.live, 0);
+        });
+    }
+
+    #[test]
+    fn reuses_memory_across_sizes() {
+        // The bin allocator cannot do this: freed small blocks never serve
+        // larger requests.
+        with_region!(1 << 16, |(start, end)| {
+            let mut a = buddy::Allocator::new(start, end);
+            for size in &[16, 256, 4096, 32, 8192, 8] {
+                let mut ptrs = vec![];
+                for _ in 0..(1 << 14) / size {
+                    let ptr = a.alloc(layout!(*size, 8));
+                    assert!(!ptr.is_null());
+                    ptrs.push(ptr);
+                }
+
+                for ptr in ptrs {
+                    a.dealloc(ptr, layout!(*size, 8));
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn refuses_large_alignments() {
+        with_region!(1 << 16, |(start, end)| {
+            let mut a = buddy::Allocator::new(start, end);
+            assert!(!a.alloc(layout!(8, 4096)).is_null());
+            assert!(a.alloc(layout!(8, 8192)).is_null());
+        });
+    }
+}
+
+mod stats {
+    use super::{layout, with_region};
+    use crate::allocator::{bin, bump, LocalAlloc, Stats};
+
+    #[test]
+    fn counts_allocations() {
+        with_region!(4096, |(start, end)| {
+            let mut a = bin::Allocator::new(start, end);
+            let x = a.alloc(layout!(100, 8));
+            let y = a.alloc(layout!(20, 8));
+            assert!(a.alloc(layout!(8192, 8)).is_null());
+            a.dealloc(x, layout!(100, 8));
+
+            let stats = a.stats();
+            assert_eq!(stats.allocated, 120);
+            assert_eq!(stats.freed, 100);
+            assert_eq!(stats.in_use(), 20);
+            assert_eq!(stats.live, 1);
+            assert_eq!(stats.peak, 120);
+            assert_eq!(stats.failed, 1);
+            assert!(stats.free <= end - start);
+
+            a.dealloc(y, layout!(20, 8));
+            assert_eq!(a.stats().live, 0);
+        });
+
+        with_region!(4096, |(start, end)| {
+            let mut a = bump::Allocator::new(start, end);
+            let x = a.alloc(layout!(100, 8));
+            a.dealloc(x, layout!(100, 8));
+            a.alloc(layout!(20, 8));
+
+            let stats = a.stats();
+            assert_eq!((stats.allocated, stats.freed, stats.live, stats.peak), (120, 100, 1, 100));
+            assert!(stats.free <= end - start - 120);
+            assert_eq!(stats.largest_free, stats.free);
+            assert_eq!(stats.fragmentation(), 0);
+        });
+    }
+
+    #[test]
+    fn measures_free_lists() {
+        with_region!(4096, |(start, end)| {
+            let mut a = bin::Allocator::new(start, end);
+            let small = a.alloc(layout!(16, 16));
+            let large = a.alloc(layout!(1024, 1024));
+            a.dealloc(small, layout!(16, 16));
+            a.dealloc(large, layout!(1024, 1024));
+
+            let stats = a.stats();
+            assert_eq!(stats.free_lists[1], 1);
+            assert_eq!(stats.free_lists[7], 1);
+            assert_eq!(stats.in_use(), 0);
+
+            // Everything not handed out is free, in bins or past them.
+            assert!(stats.free <= end - start);
+            assert!(stats.largest_free >= 1024);
+            assert!(stats.fragmentation() < 100);
+        });
+    }
+
+    #[test]
+    fn fragmentation() {
+        let mut stats = Stats::default();
+        assert_eq!(stats.fragmentation(), 0);
+
+        stats.free = 1000;
+        stats.largest_free = 1000;
+        assert_eq!(stats.fragmentation(), 0);
+
+        stats.largest_free = 250;
+        assert_eq!(stats.fragmentation(), 75);
+    }
+}
+
+mod debug {
+    use super::{layout, with_region};
+    use crate::allocator::debug::{self, POISON};
+    use crate::allocator::{bin, buddy, bump, LocalAlloc, Record};
+
+    #[test]
+    fn allocates_and_frees() {
+        with_region!(1 << 16, |(start, end)| {
+            let mut a = debug::Allocator::from(bin::Allocator::new(start, end));
+            for &(size, align) in &[(1, 1), (7, 2), (64, 8), (100, 64), (4096, 4096)] {
+                let ptr = a.alloc(layout!(size, align));
+                assert!(!ptr.is_null());
+                assert_eq!(ptr as usize % align, 0);
+                core::ptr::write_bytes(ptr, 0xaa, size);
+                a.dealloc(ptr, layout!(size, align));
+            }
+        });
+
+        with_region!(1 << 16, |(start, end)| {
+            let mut a = debug::Allocator::from(buddy::Allocator::new(start, end));
+            let ptr = a.alloc(layout!(256, 16));
+            a.dealloc(ptr, layout!(256, 16));
+            assert_eq!(a.stats().live, 0);
+        });
+    }
+
+    #[test]
+    fn poisons_freed_memory() {
+        with_region!(4096, |(start, end)| {
+            let mut a = debug::Allocator::from(bump::Allocator::new(start, end));
+            let ptr = a.alloc(layout!(32, 8));
+            core::ptr::write_bytes(ptr, 0, 32);
+            a.dealloc(ptr, layout!(32, 8));
+            assert!((0..32).all(|i| *ptr.add(i) == POISON));
+        });
+    }
+
+    #[test]
+    fn records_live_allocations() {
+        with_region!(1 << 16, |(start, end)| {
+            let mut a = debug::Allocator::from(bin::Allocator::new(start, end));
+            let x = a.alloc_from(layout!(10, 1), 0x1000);
+            let y = a.alloc_from(layout!(20, 4), 0x2000);
+            let z = a.alloc_from(layout!(30, 8), 0x3000);
+            a.dealloc(y, layout!(20, 4));
+
+            let mut records = [Record::default(); 4];
+            assert_eq!(a.live(&mut records), 2);
+            assert_eq!(records[0], Record { ptr: z as usize, size: 30, align: 8, caller: 0x3000 });
+            assert_eq!(records[1], Record { ptr: x as usize, size: 10, align: 1, caller: 0x1000 });
+            assert_eq!(records[2], Record::default());
+
+            let mut newest = [Record::default(); 1];
+            assert_eq!(a.live(&mut newest), 2);
+            assert_eq!(newest[0].ptr, z as usize);
+
+            a.dealloc(x, layout!(10, 1));
+            a.dealloc(z, layout!(30, 8));
+            assert_eq!(a.live(&mut records), 0);
+        });
+    }
+
+    #[test]
+    fn records_callers_of_zeroed_and_resized_allocations() {
+        with_region!(1 << 16, |(start, end)| {
+            let mut a = debug::Allocator::from(bin::Allocator::new(start, end));
+            let x = a.alloc_zeroed_from(layout!(16, 8), 0x1000);
+            assert!(core::slice::from_raw_parts(x, 16).iter().all(|&b| b == 0));
+            let y = a.realloc_from(x, layout!(16, 8), 64, 0x2000);
+
+            let mut records = [Record::default(); 2];
+            assert_eq!(a.live(&mut records), 1);
+            assert_eq!(records[0], Record { ptr: y as usize, size: 64, align: 8, caller: 0x2000 });
+
+            a.dealloc(y, layout!(64, 8));
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "heap overflow")]
+    fn catches_overflows() {
+        with_region!(4096, |(start, end)| {
+            let mut a = debug::Allocator::from(bin::Allocator::new(start, end));
+            let ptr = a.alloc(layout!(24, 8));
+            *ptr.add(24) = 0;
+            a.dealloc(ptr, layout!(24, 8));
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "heap underflow")]
+    fn catches_underflows() {
+        with_region!(4096, |(start, end)| {
+            let mut a = debug::Allocator::from(bin::Allocator::new(start, end));
+            let ptr = a.alloc(layout!(24, 8));
+            *ptr.sub(1) = 0;
+            a.dealloc(ptr, layout!(24, 8));
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "double free")]
+    fn catches_double_frees() {
+        with_region!(4096, |(start, end)| {
+            let mut a = debug::Allocator::from(bump::Allocator::new(start, end));
+            let ptr = a.alloc(layout!(16, 8));
+            a.dealloc(ptr, layout!(16, 8));
+            a.dealloc(ptr, layout!(16, 8));
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "allocated with size 16 and align 8")]
+    fn catches_layout_mismatches() {
+        with_region!(4096, |(start, end)| {
+            let mut a = debug::Allocator::from(bin::Allocator::new(start, end));
+            let ptr = a.alloc(layout!(16, 8));
+            a.dealloc(ptr, layout!(16, 16));
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "not a live allocation")]
+    fn catches_wild_frees() {
+        with_region!(4096, |(start, end)| {
+            let mut a = debug::Allocator::from(bin::Allocator::new(start, end));
+            let ptr = a.alloc(layout!(64, 8));
+            a.dealloc(ptr.add(8), layout!(8, 8));
+        });
+    }
+}
+
+mod realloc {
+    use super::{layout, with_region, PAGE_SIZE};
+    use crate::allocator::{bin, buddy, bump, LocalAlloc};
+
+    /// Grows `vectors` vectors of `elem`-byte elements side by side to
+    /// `len` elements each and checks their contents survive. Capacity doubles
+    /// like `Vec`'s or, if `exact`, grows by one element at a time like after
+    /// `Vec::reserve_exact()`. Returns how many times the vectors grew and how
+    /// many of those moved the memory.
+    unsafe fn grow<A: LocalAlloc + ?Sized>(
+        a: &mut A,
+        vectors: usize,
+        elem: usize,
+        len: usize,
+        exact: bool,
+    ) -> (usize, usize) {
+        let (mut growths, mut copies) = (0, 0);
+        let mut ptrs = [core::ptr::null_mut(); 4];
+        let mut cap = 4;
+        for ptr in &mut ptrs[..vectors] {
+            *ptr = a.alloc(layout!(cap * elem, 8));
+            assert!(!ptr.is_null());
+        }
+
+        for i in 0..len {
+            if i == cap {
+                let new_cap = if exact { cap + 1 } else { cap * 2 };
+                for ptr in &mut ptrs[..vectors] {
+                    let new = a.realloc(*ptr, layout!(cap * elem, 8), new_cap * elem);
+                    assert!(!new.is_null());
+                    growths += 1;
+                    copies += (new != *ptr) as usize;
+                    *ptr = new;
+                }
+                cap = new_cap;
+            }
+
+            for (v, &ptr) in ptrs[..vectors].iter().enumerate() {
+                core::ptr::write_bytes(ptr.add(i * elem), (i + v) as u8, elem);
+            }
+        }
+
+        for (v, &ptr) in ptrs[..vectors].iter().enumerate() {
+            for i in 0..len {
+                assert_eq!(*ptr.add(i * elem), (i + v) as u8);
+            }
+            a.dealloc(ptr, layout!(cap * elem, 8));
+        }
+
+        (growths, copies)
+    }
+
+    #[test]
+    fn bin_grows_in_place() {
+        with_region!(1 << 16, |(start, end)| {
+            let mut a = bin::Allocator::new(start, end);
+            let (growths, copies) = grow(&mut a, 1, 1, 4096, false);
+            assert_eq!(growths, 10);
+            assert_eq!(copies, 0);
+        });
+    }
+
+    #[test]
+    fn fewer_copies_than_moving() {
+        let runs = [(1, 24, 1000, false), (2, 1, 3000, false), (3, 40, 200, true), (4, 8, 500, true)];
+        for &(vectors, elem, len, exact) in &runs {
+            let grow = |a: &mut dyn LocalAlloc| unsafe { grow(a, vectors, elem, len, exact) };
+            let (growths, bump_copies) = with_region!(1 << 23, |(start, end)| grow(&mut bump::Allocator::new(start, end)));
+            let (_, buddy_copies) = with_region!(1 << 23, |(start, end)| grow(&mut buddy::Allocator::new(start, end)));
+            let (_, bin_copies) = with_region!(1 << 23, |(start, end)| grow(&mut bin::Allocator::new(start, end)));
+
+            println!(
+                "{} x {} elements of {} bytes{}: {} growths, {} copies (bin), {} copies (bump, buddy)",
+                vectors,
+                len,
+                elem,
+                if exact { ", exact" } else { "" },
+                growths,
+                bin_copies,
+                bump_copies
+            );
+            assert_eq!(bump_copies, growths);
+            assert_eq!(buddy_copies, growths);
+            assert!(bin_copies < growths);
+        }
+    }
+
+    #[test]
+    fn bin_shrinks_in_place() {
+        with_region!(1 << 16, |(start, end)| {
+            let mut a = bin::Allocator::new(start, end);
+            let ptr = a.alloc(layout!(4096, 8));
+            core::ptr::write_bytes(ptr, 0x5a, 4096);
+            assert_eq!(a.realloc(ptr, layout!(4096, 8), 1000), ptr);
+            assert!((0..1000).all(|i| *ptr.add(i) == 0x5a));
+
+            // The rest of the block went back into the bins.
+            assert_eq!(a.alloc(layout!(2048, 8)), ptr.add(2048));
+            assert_eq!(a.alloc(layout!(1024, 8)), ptr.add(1024));
+
+            let stats = a.stats();
+            assert_eq!(stats.live, 3);
+            assert_eq!(stats.in_use(), 1000 + 2048 + 1024);
+        });
+    }
+
+    #[test]
+    fn bin_moves_when_blocked() {
+        with_region!(1 << 16, |(start, end)| {
+            let mut a = bin::Allocator::new(start, end);
+            let x = a.alloc(layout!(64, 8));
+            let y = a.alloc(layout!(64, 8));
+            core::ptr::write_bytes(x, 7, 64);
+
+            // Within the block's size class the memory stays put.
+            assert_eq!(a.realloc(x, layout!(64, 8), 40), x);
+            assert_eq!(a.realloc(x, layout!(40, 8), 64), x);
+
+            let moved = a.realloc(x, layout!(64, 8), 65);
+            assert!(moved != x && moved != y);
+            assert!((0..64).all(|i| *moved.add(i) == 7));
+            assert_eq!(a.alloc(layout!(64, 8)), x);
+        });
+    }
+
+    #[test]
+    fn alloc_zeroed() {
+        with_region!(1 << 16, |(start, end)| {
+            let mut a = bin::Allocator::new(start, end);
+            let ptr = a.alloc(layout!(256, 8));
+            core::ptr::write_bytes(ptr, 0xff, 256);
+            a.dealloc(ptr, layout!(256, 8));
+
+            let zeroed = a.alloc_zeroed(layout!(256, 8));
+            assert_eq!(zeroed, ptr);
+            assert!((0..256).all(|i| *zeroed.add(i) == 0));
+        });
+    }
+}
+
+mod fuzz {
+    use core::alloc::Layout;
+    use core::ptr;
+
+    use crate::allocator::fuzz::{fuzz, generate, replay, shrink, Op, Subject};
+    use crate::allocator::{bin, buddy, bump, debug, LocalAlloc};
+
+    #[test]
+    fn fuzz_bin() {
+        fuzz::<bin::Allocator>(64, 400);
+    }
+
+    #[test]
+    fn fuzz_bump() {
+        fuzz::<bump::Allocator>(64, 400);
+    }
+
+    #[test]
+    fn fuzz_buddy() {
+        fuzz::<buddy::Allocator>(64, 400);
+    }
+
+    #[test]
+    fn fuzz_debug() {
+        fuzz::<debug::Allocator<bin::Allocator>>(16, 400);
+        fuzz::<debug::Allocator<buddy::Allocator>>(16, 400);
+    }
+
+    /// A bin allocator that hands out the same memory twice once an
+    /// allocation larger than 100 bytes has been made.
+    struct Broken {
+        inner: bin::Allocator,
+        last_large: *mut u8,
+    }
+
+    impl LocalAlloc for Broken {
+        unsafe fn add_region(&mut self, start: usize, end: usize) {
+            self.inner.add_region(start, end)
+        }
+
+        unsafe fn alloc(&mut self, layout: Layout) -> *mut u8 {
+            if layout.size() > 100 && !self.last_large.is_null() && layout.align() <= 8 {
+                return self.last_large;
+            }
+
+            let ptr = self.inner.alloc(layout);
+            if layout.size() > 100 {
+                self.last_large = ptr;
+            }
+            ptr
+        }
+
+        unsafe fn dealloc(&mut self, ptr: *mut u8, layout: Layout) {
+            self.inner.dealloc(ptr, layout)
+        }
+    }
+
+    impl Subject for Broken {
+        fn create(start: usize, end: usize) -> Self {
+            Broken { inner: bin::Allocator::new(start, end), last_large: ptr::null_mut() }
+        }
+
+        fn free(&self) -> Option<usize> {
+            None
+        }
+    }
+
+    #[test]
+    fn finds_and_shrinks_failures() {
+        // Moving memory with `realloc()` would copy it onto itself.
+        let ops: Vec<Op> = generate(7, 300)
+            .into_iter()
+            .filter(|op| match op {
+                Op::Realloc(..) => false,
+                _ => true,
+            })
+            .collect();
+        let failure = replay::<Broken>(&ops, 1 << 16, 0).unwrap_err();
+        assert!(failure.message.contains("overlaps"), "{}", failure);
+
+        let minimal = shrink(&ops, |ops| replay::<Broken>(ops, 1 << 16, 0).is_err());
+        assert_eq!(minimal.len(), 2);
+        for op in minimal {
+            assert_eq!(op, Op::Alloc { size: 101, align: 1 });
+        }
+    }
+
+    #[test]
+    fn catches_lost_memory() {
+        /// A bin allocator that forgets every third block it is given back.
+        struct Leaky(bin::Allocator, usize);
+
+        impl LocalAlloc for Leaky {
+            unsafe fn add_region(&mut self, start: usize, end: usize) {
+                self.0.add_region(start, end)
+            }
+
+            unsafe fn alloc(&mut self, layout: Layout) -> *mut u8 {
+                self.0.alloc(layout)
+            }
+
+            unsafe fn dealloc(&mut self, ptr: *mut u8, layout: Layout) {
+                self.1 += 1;
+                if self.1 % 3 != 0 {
+                    self.0.dealloc(ptr, layout)
+                }
+            }
+        }
+
+        impl Subject for Leaky {
+            fn create(start: usize, end: usize) -> Self {
+                Leaky(bin::Allocator::new(start, end), 0)
+            }
+
+            fn free(&self) -> Option<usize> {
+                Some(self.0.stats().free)
+            }
+        }
+
+        let ops = generate(3, 100);
+        let failure = replay::<Leaky>(&ops, 1 << 16, 0).unwrap_err();
+        assert!(failure.message.contains("lost"), "{}", failure);
+        assert!(shrink(&ops, |ops| replay::<Leaky>(ops, 1 << 16, 0).is_err()).len() <= 3);
+    }
+}
+
+mod region {
+    use core::alloc::Layout;
+
+    use super::{layout, with_region, PAGE_SIZE};
+    use crate::allocator::util::align_up;
+    use crate::allocator::{bin, buddy, bump, LocalAlloc};
+    use crate::allocator::{reserve, usable, Region, Regions, MAX_REGIONS};
+
+    fn regions(list: &[(usize, usize)]) -> Regions {
+        let mut regions = Regions::new();
+        for &(start, end) in list {
+            assert!(regions.add(Region::new(start, end)));
+        }
+        regions
+    }
+
+    fn list(regions: &Regions) -> Vec<(usize, usize)> {
+        regions.iter().map(|r| (r.start, r.end)).collect()
+    }
+
+    #[test]
+    fn adds_and_merges() {
+        let mut r = regions(&[(0x3000, 0x4000), (0x1000, 0x2000)]);
+        assert_eq!(list(&r), [(0x1000, 0x2000), (0x3000, 0x4000)]);
+        assert_eq!(r.size(), 0x2000);
+
+        // Touching regions merge, and so do all those a region overlaps.
+        assert!(r.add(Region::new(0x4000, 0x5000)));
+        assert_eq!(list(&r), [(0x1000, 0x2000), (0x3000, 0x5000)]);
+        assert!(r.add(Region::new(0x1800, 0x3800)));
+        assert_eq!(list(&r), [(0x1000, 0x5000)]);
+
+        assert!(r.add(Region::new(0x8000, 0x8000)));
+        assert_eq!(list(&r), [(0x1000, 0x5000)]);
+    }
+
+    #[test]
+    fn removes_and_splits() {
+        let mut r = regions(&[(0x1000, 0x5000), (0x8000, 0x9000)]);
+        assert!(r.remove(Region::new(0x2000, 0x3000)));
+        assert_eq!(list(&r), [(0x1000, 0x2000), (0x3000, 0x5000), (0x8000, 0x9000)]);
+
+        assert!(r.remove(Region::new(0x4000, 0x8800)));
+        assert_eq!(list(&r), [(0x1000, 0x2000), (0x3000, 0x4000), (0x8800, 0x9000)]);
+
+        assert!(r.remove(Region::new(0, 0x1000)));
+        assert!(r.remove(Region::new(0x1000, 0x1000)));
+        assert_eq!(r.size(), 0x2800);
+
+        r.intersect(Region::new(0x1800, 0x3800));
+        assert_eq!(list(&r), [(0x1800, 0x2000), (0x3000, 0x3800)]);
+    }
+
+    #[test]
+    fn refuses_too_many_regions() {
+        let mut r = Regions::new();
+        for i in 0..MAX_REGIONS {
+            assert!(r.add(Region::new(i * 0x100, i * 0x100 + 0x10)));
+        }
+
+        let full = r;
+        assert!(!r.add(Region::new(0x10000, 0x10010)));
+        assert!(!r.remove(Region::new(0x4, 0x8)));
+        assert_eq!(r, full);
+
+        // Merging needs no more room.
+        assert!(r.add(Region::new(0x10, 0x100)));
+        assert_eq!(r.len(), MAX_REGIONS - 1);
+    }
+
+    /// Returns two disjoint regions of `size` bytes from the page-aligned
+    /// `start`, a page apart.
+    fn two_regions(start: usize, size: usize) -> (Region, Region) {
+        let second = start + size + PAGE_SIZE;
+        (Region::new(start, start + size), Region::new(second, second + size))
+    }
+
+    /// Fills `a` with `layout`s, checking that every one lies in `first` or
+    /// `second`, and returns how many lie in each.
+    unsafe fn fill<A: LocalAlloc>(a: &mut A, layout: Layout, first: Region, second: Region) -> (usize, usize) {
+        let mut counts = (0, 0);
+        loop {
+            let ptr = a.alloc(layout) as usize;
+            if ptr == 0 {
+                return counts;
+            }
+
+            if ptr >= first.start && ptr + layout.size() <= first.end {
+                counts.0 += 1;
+            } else if ptr >= second.start && ptr + layout.size() <= second.end {
+                counts.1 += 1;
+            } else {
+                panic!("{:#x} outside of both regions", ptr);
+            }
+        }
+    }
+
+    #[test]
+    fn bin_uses_every_region() {
+        with_region!(3 * PAGE_SIZE, |(start, _)| {
+            let (first, second) = two_regions(start, PAGE_SIZE);
+            let mut a = bin::Allocator::new(first.start, first.end);
+            a.add_region(second.start, second.end);
+            assert_eq!(a.stats().free, 2 * PAGE_SIZE);
+            assert_eq!(fill(&mut a, layout!(256, 8), first, second), (16, 16));
+        });
+    }
+
+    #[test]
+    fn buddy_uses_every_region() {
+        with_region!(3 * PAGE_SIZE, |(start, _)| {
+            let (first, second) = two_regions(start, PAGE_SIZE);
+            let mut a = buddy::Allocator::new(first.start, first.end);
+            a.add_region(second.start, second.end);
+            assert_eq!(a.stats().free, 2 * PAGE_SIZE);
+            assert_eq!(fill(&mut a, layout!(256, 8), first, second), (16, 16));
+        });
+    }
+
+    #[test]
+    fn buddy_merges_adjacent_regions() {
+        with_region!(3 * PAGE_SIZE, |(start, _)| {
+            let start = align_up(start, 2 * PAGE_SIZE);
+            let mut a = buddy::Allocator::new(start, start + PAGE_SIZE);
+            assert!(a.alloc(layout!(2 * PAGE_SIZE, 8)).is_null());
+            a.add_region(start + PAGE_SIZE, start + 2 * PAGE_SIZE);
+            assert_eq!(a.alloc(layout!(2 * PAGE_SIZE, 8)) as usize, start);
+        });
+    }
+
+    #[test]
+    fn bump_moves_to_the_next_region() {
+        with_region!(3 * PAGE_SIZE, |(start, _)| {
+            let (first, second) = two_regions(start, PAGE_SIZE);
+            let mut a = bump::Allocator::new(first.start, first.end);
+            a.add_region(second.start, second.end);
+            assert_eq!(a.stats().free, 2 * PAGE_SIZE);
+            assert_eq!(a.stats().largest_free, PAGE_SIZE);
+
+            // What is left of the first region is given up for an
+            // allocation that does not fit in it.
+            assert_eq!(a.alloc(layout!(3 * PAGE_SIZE / 4, 8)) as usize, first.start);
+            assert_eq!(a.alloc(layout!(PAGE_SIZE / 2, 8)) as usize, second.start);
+            assert_eq!(a.stats().free, PAGE_SIZE / 2);
+            assert_eq!(fill(&mut a, layout!(256, 8), first, second), (0, 8));
+        });
+    }
+
+    #[test]
+    fn memory_map_leaves_out_reservations() {
+        assert!(reserve("test", 0x30_0000, 0x40_0000));
+
+        // Two banks of RAM, the second only partly the ARM's.
+        let ram = regions(&[(0, 0x100_0000), (0x200_0000, 0x300_0000)]);
+        let map = usable(ram, Some(Region::new(0, 0x280_0000)), 0x10_0000).expect("regions fit");
+        assert_eq!(
+            list(&map),
+            [(0x10_0000, 0x30_0000), (0x40_0000, 0x100_0000), (0x200_0000, 0x280_0000)]
+        );
+
+        assert!(usable(Regions::new(), None, 0x10_0000).is_none());
+    }
+}
+
 mod linked_list {
     use crate::allocator::linked_list::LinkedList;
 
@@ -383,3 +1147,101 @@ mod linked_list {
         assert_eq!(iter.next(), None);
     }
 }
+
+mod slab {
+    use core::cell::Cell;
+
+    use crate::allocator::SlabCache;
+
+    #[derive(Debug, PartialEq)]
+    struct Entry {
+        id: u64,
+        name: [u8; 40],
+    }
+
+    #[test]
+    fn reuses_freed_slots() {
+        let cache: SlabCache<Entry> = SlabCache::new("entries");
+        let per_slab = SlabCache::<Entry>::objects_per_slab();
+        assert!(per_slab >= 8);
+
+        let first = cache.alloc(Entry { id: 1, name: [0; 40] }).unwrap();
+        let addr = &*first as *const Entry as usize;
+        assert_eq!(addr % core::mem::align_of::<Entry>(), 0);
+        drop(first);
+
+        let second = cache.alloc_with(|| Entry { id: 2, name: [7; 40] }).unwrap();
+        assert_eq!(&*second as *const Entry as usize, addr);
+        assert_eq!(second.id, 2);
+        assert_eq!(cache.stats().slabs, 1);
+    }
+
+    #[test]
+    fn grows_and_reclaims_slabs() {
+        let cache: SlabCache<Entry> = SlabCache::new("entries");
+        let per_slab = SlabCache::<Entry>::objects_per_slab();
+
+        let mut entries = vec![];
+        for id in 0..(per_slab * 3) as u64 {
+            let mut entry = cache.alloc(Entry { id, name: [0; 40] }).unwrap();
+            entry.name[0] = id as u8;
+            entries.push(entry);
+        }
+
+        for (id, entry) in entries.iter().enumerate() {
+            assert_eq!(entry.id, id as u64);
+            assert_eq!(entry.name[0], id as u8);
+        }
+
+        let stats = cache.stats();
+        assert_eq!((stats.slabs, stats.live, stats.allocated), (3, per_slab * 3, per_slab * 3));
+        assert_eq!(stats.free(), 0);
+        assert_eq!(cache.reclaim(), 0);
+
+        // Free everything but one object: only its slab must stay.
+        let kept = entries.remove(per_slab + 1);
+        entries.clear();
+        assert_eq!(cache.stats().free(), per_slab * 3 - 1);
+        assert_eq!(cache.reclaim(), 2);
+
+        let stats = cache.stats();
+        assert_eq!((stats.slabs, stats.live, stats.reclaimed), (1, 1, 2));
+        assert_eq!(kept.id, per_slab as u64 + 1);
+
+        drop(kept);
+        assert_eq!(cache.reclaim(), 1);
+        assert_eq!(cache.stats().slabs, 0);
+    }
+
+    #[test]
+    fn drops_objects() {
+        struct Counted<'a>(&'a Cell<usize>);
+
+        impl<'a> Drop for Counted<'a> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = Cell::new(0);
+        let cache = SlabCache::new("counted");
+        let a = cache.alloc(Counted(&drops)).unwrap();
+        let b = cache.alloc(Counted(&drops)).unwrap();
+        drop(a);
+        assert_eq!(drops.get(), 1);
+        drop(b);
+        assert_eq!(drops.get(), 2);
+    }
+
+    #[test]
+    fn handles_large_and_tiny_objects() {
+        let large: SlabCache<[u64; 200]> = SlabCache::new("large");
+        assert!(SlabCache::<[u64; 200]>::objects_per_slab() >= 8);
+        let block = large.alloc([3; 200]).unwrap();
+        assert_eq!(block[199], 3);
+
+        let tiny: SlabCache<u8> = SlabCache::new("tiny");
+        let bytes: Vec<_> = (0..100).map(|i| tiny.alloc(i as u8).unwrap()).collect();
+        assert!(bytes.iter().enumerate().all(|(i, b)| **b == i as u8));
+    }
+}
@@ -29,6 +29,29 @@ pub fn align_up(addr: usize, align: usize) -> usize {
     }
 }
 
+/// Returns the blocks that `[start, end)` is carved into: the largest ones,
+/// of at least `2^min_order` and at most `2^max_order` bytes, that are
+/// aligned to their size. Yields `(address, order)` pairs, where the block
+/// at `address` is `2^order` bytes.
+pub fn blocks(start: usize, end: usize, min_order: usize, max_order: usize) -> impl Iterator<Item = (usize, usize)> {
+    let mut addr = align_up(start, 1 << min_order);
+    core::iter::from_fn(move || {
+        if addr >= end || end - addr < 1 << min_order {
+            return None;
+        }
+
+        let aligned = core::cmp::min(addr.trailing_zeros() as usize, max_order);
+        let order = core::cmp::min(aligned, log2_floor(end - addr));
+        let block = (addr, order);
+        addr += 1 << order;
+        Some(block)
+    })
+}
+
+/// Returns `floor(log2(n))` for `n > 0`.
+pub fn log2_floor(n: usize) -> usize {
+    (0usize.count_zeros() - 1 - n.leading_zeros()) as usize
+}
 
 /// Checks `align` is a power of two
 /// Uses technique listed here
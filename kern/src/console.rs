@@ -1,8 +1,13 @@
+use alloc::boxed::Box;
 use core::fmt;
-use pi::uart::MiniUart;
+use pi::interrupt::{Controller, Interrupt};
+use pi::uart::{MiniUart, Overruns};
 use shim::io;
 
-use crate::mutex::Mutex;
+use crate::aarch64;
+use crate::mutex::IrqMutex;
+use crate::traps::TrapFrame;
+use crate::IRQ;
 
 /// A global singleton allowing read/write access to the console.
 pub struct Console {
@@ -36,6 +41,26 @@ impl Console {
         self.inner().read_byte()
     }
 
+    /// Returns a byte from the UART device if one is available.
+    pub fn try_read_byte(&mut self) -> Option<u8> {
+        self.inner().try_read()
+    }
+
+    /// Returns `true` once the UART device is interrupt driven.
+    pub fn interrupts_enabled(&mut self) -> bool {
+        self.inner().interrupts_enabled()
+    }
+
+    /// Returns the counts of received bytes the UART device lost.
+    pub fn overruns(&mut self) -> Overruns {
+        self.inner().overruns()
+    }
+
+    /// Returns `true` if a byte is ready to be read without blocking.
+    pub fn has_byte(&mut self) -> bool {
+        self.inner().has_byte()
+    }
+
     /// Writes the byte `byte` to the UART device.
     pub fn write_byte(&mut self, byte: u8) {
         self.inner().write_byte(byte);
@@ -54,6 +79,7 @@ impl io::Write for Console {
     }
 
     fn flush(&mut self) -> io::Result<()> {
+        self.inner().flush();
         Ok(())
     }
 }
@@ -64,8 +90,38 @@ impl fmt::Write for Console {
     }
 }
 
-/// Global `Console` singleton.
-pub static CONSOLE: Mutex<Console> = Mutex::new(Console::new());
+/// Global `Console` singleton. IRQs are masked while it is locked, so that
+/// interrupt handlers can print.
+pub static CONSOLE: IrqMutex<Console> = IrqMutex::named("console", Console::new());
+
+/// Makes the console interrupt driven: received bytes are buffered from the
+/// UART's IRQ, so none are lost while the kernel is busy, and written bytes
+/// are sent from it.
+pub fn enable_interrupts() {
+    IRQ.register(Interrupt::Aux, Box::new(|_: &mut TrapFrame| CONSOLE.lock().inner().handle_interrupt()));
+    CONSOLE.lock().inner().enable_interrupts();
+    Controller::new().enable(Interrupt::Aux);
+}
+
+/// Reads a byte from the console, blocking until one is available. Unlike
+/// `CONSOLE.lock().read_byte()`, the console is only locked while checking
+/// for a byte, so IRQs are handled while waiting, and once the console is
+/// interrupt driven the core sleeps until one arrives.
+pub fn read_byte() -> u8 {
+    loop {
+        let mut console = CONSOLE.lock();
+        if let Some(byte) = console.try_read_byte() {
+            return byte;
+        }
+
+        let sleep = console.interrupts_enabled();
+        drop(console);
+        // With IRQs masked, nothing would wake the core.
+        if sleep && aarch64::daif() & aarch64::DAIF_I == 0 {
+            aarch64::wfe();
+        }
+    }
+}
 
 /// Internal function called by the `kprint[ln]!` macros.
 #[doc(hidden)]
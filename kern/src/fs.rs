@@ -10,7 +10,9 @@ pub use fat32::traits;
 use fat32::vfat::{Dir, Entry, File, VFat, VFatHandle};
 
 use self::sd::Sd;
+use crate::console::kprintln;
 use crate::mutex::Mutex;
+use crate::sync::Once;
 
 #[derive(Clone)]
 pub struct PiVFatHandle(Rc<Mutex<VFat<Self>>>);
@@ -38,28 +40,60 @@ impl VFatHandle for PiVFatHandle {
         f(&mut self.0.lock())
     }
 }
-pub struct FileSystem(Mutex<Option<PiVFatHandle>>);
+/// The file system on the SD card, if it has been mounted.
+pub struct FileSystem(Once<Option<PiVFatHandle>>);
 
 impl FileSystem {
-    /// Returns an uninitialized `FileSystem`.
+    /// Returns an unmounted `FileSystem`.
     ///
-    /// The file system must be initialized by calling `initialize()` before the
-    /// first memory allocation. Failure to do will result in panics.
+    /// The file system must be mounted by calling `initialize()` after the
+    /// allocator is initialized. Until then, opening any path fails.
     pub const fn uninitialized() -> Self {
-        FileSystem(Mutex::new(None))
+        FileSystem(Once::new())
     }
 
-    /// Initializes the file system.
-    /// The caller should assure that the method is invoked only once during the
-    /// kernel initialization.
-    ///
-    /// # Panics
-    ///
-    /// Panics if the underlying disk or file sytem failed to initialize.
+    /// Mounts the FAT32 file system on the SD card. If the card or the file
+    /// system fails to initialize, the error is logged and the file system is
+    /// left unmounted. Only the first call tries to mount it.
     pub unsafe fn initialize(&self) {
-        unimplemented!("FileSystem::initialize()")
+        self.0.call_once(|| {
+            let sd = match Sd::new() {
+                Ok(sd) => sd,
+                Err(e) => {
+                    kprintln!("fs: failed to initialize SD card: {}", e);
+                    return None;
+                }
+            };
+
+            match VFat::<PiVFatHandle>::from(sd) {
+                Ok(handle) => Some(handle),
+                Err(e) => {
+                    kprintln!("fs: failed to mount FAT32 file system: {:?}", e);
+                    None
+                }
+            }
+        });
+    }
+
+    /// Returns `true` if the file system is mounted.
+    pub fn is_mounted(&self) -> bool {
+        self.handle().is_some()
+    }
+
+    fn handle(&self) -> Option<&PiVFatHandle> {
+        self.0.get().and_then(|handle| handle.as_ref())
     }
 }
 
-// FIXME: Implement `fat32::traits::FileSystem` for `&FileSystem`
-impl fat32::traits::FileSystem for &FileSystem {}
+impl fat32::traits::FileSystem for &FileSystem {
+    type File = File<PiVFatHandle>;
+    type Dir = Dir<PiVFatHandle>;
+    type Entry = Entry<PiVFatHandle>;
+
+    fn open<P: AsRef<Path>>(self, path: P) -> io::Result<Self::Entry> {
+        match self.handle() {
+            Some(handle) => traits::FileSystem::open(handle, path),
+            None => ioerr!(NotFound, "no file system mounted"),
+        }
+    }
+}
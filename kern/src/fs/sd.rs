@@ -29,8 +29,21 @@ extern "C" {
     fn sd_readsector(n: i32, buffer: *mut u8) -> i32;
 }
 
-// FIXME: Define a `#[no_mangle]` `wait_micros` function for use by `libsd`.
-// The `wait_micros` C signature is: `void wait_micros(unsigned int);`
+/// Sleeps for `us` microseconds. Called by `libsd` as
+/// `void wait_micros(unsigned int);`.
+#[no_mangle]
+pub extern "C" fn wait_micros(us: u32) {
+    pi::timer::spin_sleep(Duration::from_micros(us as u64));
+}
+
+/// Returns the error corresponding to the SD controller error code `code`.
+fn sd_error(code: i64) -> io::Error {
+    match code {
+        -1 => io::Error::new(io::ErrorKind::TimedOut, "SD card timed out"),
+        -2 => io::Error::new(io::ErrorKind::Other, "failed to send command to SD card"),
+        _ => io::Error::new(io::ErrorKind::Other, "SD card error"),
+    }
+}
 
 /// A handle to an SD card controller.
 #[derive(Debug)]
@@ -43,7 +56,10 @@ impl Sd {
     /// with atomic memory access, but we can't use it yet since we haven't
     /// written the memory management unit (MMU).
     pub unsafe fn new() -> Result<Sd, io::Error> {
-        unimplemented!("Sd::new()")
+        match sd_init() {
+            0 => Ok(Sd),
+            code => Err(sd_error(code as i64)),
+        }
     }
 }
 
@@ -61,7 +77,23 @@ impl BlockDevice for Sd {
     ///
     /// An error of kind `Other` is returned for all other errors.
     fn read_sector(&mut self, n: u64, buf: &mut [u8]) -> io::Result<usize> {
-        unimplemented!("Sd::read_sector()")
+        if buf.len() < 512 {
+            return ioerr!(InvalidInput, "buffer is smaller than a sector");
+        }
+        if n > i32::max_value() as u64 {
+            return ioerr!(InvalidInput, "sector number out of range");
+        }
+
+        // `sd_readsector` needs a 4-byte aligned buffer.
+        let mut sector = [0u32; 128];
+        let read = unsafe { sd_readsector(n as i32, sector.as_mut_ptr() as *mut u8) };
+        if read <= 0 {
+            return Err(sd_error(unsafe { sd_err }));
+        }
+
+        let bytes = unsafe { core::slice::from_raw_parts(sector.as_ptr() as *const u8, 512) };
+        buf[..512].copy_from_slice(bytes);
+        Ok(512)
     }
 
     fn write_sector(&mut self, _n: u64, _buf: &[u8]) -> io::Result<usize> {
@@ -75,12 +75,15 @@ switch_to_el1:
     msr     SCTLR_EL1, x2
 
     // set up exception handlers
-    // FIXME: load `_vectors` addr into appropriate register (guide: 10.4)
+    adr     x2, _vectors
+    msr     VBAR_EL1, x2
 
     // change execution level to EL1 (ref: C5.2.19)
     mov     x2, #0x3c5
     msr     SPSR_EL2, x2
-    // FIXME: Return to EL1 at `set_stack`.
+    adr     x2, set_stack
+    msr     ELR_EL2, x2
+    eret
 
 set_stack:
     // set the current stack pointer
@@ -103,24 +106,151 @@ go_kmain:
     bl      kinit
     b       halt
 
+// The layout of `TrapFrame` (traps/frame.rs): x0-x30, q0-q31, then the
+// system registers.
+.equ FRAME_Q,       256
+.equ FRAME_ELR,     768
+.equ FRAME_SPSR,    776
+.equ FRAME_ESR,     784
+.equ FRAME_FAR,     792
+.equ FRAME_SP,      800
+.equ FRAME_SIZE,    816
+
+// Saves the rest of the trap frame whose x0 and x30 the vector saved, calls
+// `handle_exception(info, esr, frame)` with the info in x0, then restores the
+// frame, less x0 and x30, and returns to the vector.
 context_save:
-    // FIXME: Save the remaining context to the stack.
+    stp     x1, x2, [sp, #8]
+    stp     x3, x4, [sp, #24]
+    stp     x5, x6, [sp, #40]
+    stp     x7, x8, [sp, #56]
+    stp     x9, x10, [sp, #72]
+    stp     x11, x12, [sp, #88]
+    stp     x13, x14, [sp, #104]
+    stp     x15, x16, [sp, #120]
+    stp     x17, x18, [sp, #136]
+    stp     x19, x20, [sp, #152]
+    stp     x21, x22, [sp, #168]
+    stp     x23, x24, [sp, #184]
+    stp     x25, x26, [sp, #200]
+    stp     x27, x28, [sp, #216]
+    str     x29, [sp, #232]
+
+    stp     q0, q1, [sp, #(FRAME_Q + 0 * 32)]
+    stp     q2, q3, [sp, #(FRAME_Q + 1 * 32)]
+    stp     q4, q5, [sp, #(FRAME_Q + 2 * 32)]
+    stp     q6, q7, [sp, #(FRAME_Q + 3 * 32)]
+    stp     q8, q9, [sp, #(FRAME_Q + 4 * 32)]
+    stp     q10, q11, [sp, #(FRAME_Q + 5 * 32)]
+    stp     q12, q13, [sp, #(FRAME_Q + 6 * 32)]
+    stp     q14, q15, [sp, #(FRAME_Q + 7 * 32)]
+    stp     q16, q17, [sp, #(FRAME_Q + 8 * 32)]
+    stp     q18, q19, [sp, #(FRAME_Q + 9 * 32)]
+    stp     q20, q21, [sp, #(FRAME_Q + 10 * 32)]
+    stp     q22, q23, [sp, #(FRAME_Q + 11 * 32)]
+    stp     q24, q25, [sp, #(FRAME_Q + 12 * 32)]
+    stp     q26, q27, [sp, #(FRAME_Q + 13 * 32)]
+    stp     q28, q29, [sp, #(FRAME_Q + 14 * 32)]
+    stp     q30, q31, [sp, #(FRAME_Q + 15 * 32)]
+
+    mrs     x1, ELR_EL1
+    str     x1, [sp, #FRAME_ELR]
+    mrs     x1, SPSR_EL1
+    str     x1, [sp, #FRAME_SPSR]
+    mrs     x1, ESR_EL1
+    str     x1, [sp, #FRAME_ESR]
+    mrs     x1, FAR_EL1
+    str     x1, [sp, #FRAME_FAR]
+    // the stack pointer before the vector pushed the frame
+    add     x1, sp, #FRAME_SIZE
+    str     x1, [sp, #FRAME_SP]
+
+    // keep the return address into the vector, then call the handler
+    str     lr, [sp, #-16]!
+    ldr     w1, [sp, #(16 + FRAME_ESR)]
+    add     x2, sp, #16
+    bl      handle_exception
+    ldr     lr, [sp], #16
 
 .global context_restore
 context_restore:
-    // FIXME: Restore the context from the stack.
+    // the handler may have changed where and how execution resumes
+    ldr     x1, [sp, #FRAME_ELR]
+    msr     ELR_EL1, x1
+    ldr     x1, [sp, #FRAME_SPSR]
+    msr     SPSR_EL1, x1
+
+    ldp     q0, q1, [sp, #(FRAME_Q + 0 * 32)]
+    ldp     q2, q3, [sp, #(FRAME_Q + 1 * 32)]
+    ldp     q4, q5, [sp, #(FRAME_Q + 2 * 32)]
+    ldp     q6, q7, [sp, #(FRAME_Q + 3 * 32)]
+    ldp     q8, q9, [sp, #(FRAME_Q + 4 * 32)]
+    ldp     q10, q11, [sp, #(FRAME_Q + 5 * 32)]
+    ldp     q12, q13, [sp, #(FRAME_Q + 6 * 32)]
+    ldp     q14, q15, [sp, #(FRAME_Q + 7 * 32)]
+    ldp     q16, q17, [sp, #(FRAME_Q + 8 * 32)]
+    ldp     q18, q19, [sp, #(FRAME_Q + 9 * 32)]
+    ldp     q20, q21, [sp, #(FRAME_Q + 10 * 32)]
+    ldp     q22, q23, [sp, #(FRAME_Q + 11 * 32)]
+    ldp     q24, q25, [sp, #(FRAME_Q + 12 * 32)]
+    ldp     q26, q27, [sp, #(FRAME_Q + 13 * 32)]
+    ldp     q28, q29, [sp, #(FRAME_Q + 14 * 32)]
+    ldp     q30, q31, [sp, #(FRAME_Q + 15 * 32)]
+
+    ldp     x1, x2, [sp, #8]
+    ldp     x3, x4, [sp, #24]
+    ldp     x5, x6, [sp, #40]
+    ldp     x7, x8, [sp, #56]
+    ldp     x9, x10, [sp, #72]
+    ldp     x11, x12, [sp, #88]
+    ldp     x13, x14, [sp, #104]
+    ldp     x15, x16, [sp, #120]
+    ldp     x17, x18, [sp, #136]
+    ldp     x19, x20, [sp, #152]
+    ldp     x21, x22, [sp, #168]
+    ldp     x23, x24, [sp, #184]
+    ldp     x25, x26, [sp, #200]
+    ldp     x27, x28, [sp, #216]
+    ldr     x29, [sp, #232]
 
     ret
 
-// #define HANDLER(source, kind) \
-//     .align 7; \
-//     stp     lr, x0, [SP, #-16]!; \
-//     mov     x0, ##source; \
-//     movk    x0, ##kind, LSL #16; \
-//     bl      context_save; \
-//     ldp     lr, x0, [SP], #16; \
-//     eret
+// An exception vector: pushes a trap frame, saving x0 and x30 in it, and
+// passes `source` and `kind` to the handler as an `Info` in x0.
+.macro HANDLER source, kind
+    .align 7
+    sub     sp, sp, #FRAME_SIZE
+    str     x0, [sp, #0]
+    str     lr, [sp, #240]
+    mov     x0, #\source
+    movk    x0, #\kind, LSL #16
+    bl      context_save
+    ldr     x0, [sp, #0]
+    ldr     lr, [sp, #240]
+    add     sp, sp, #FRAME_SIZE
+    eret
+.endm
 
+// The exception vector table: for each source (ref: D1.10.2), the
+// synchronous, IRQ, FIQ and SError vectors.
 .align 11
 _vectors:
-    // FIXME: Setup the 16 exception vectors.
+    HANDLER 0, 0
+    HANDLER 0, 1
+    HANDLER 0, 2
+    HANDLER 0, 3
+
+    HANDLER 1, 0
+    HANDLER 1, 1
+    HANDLER 1, 2
+    HANDLER 1, 3
+
+    HANDLER 2, 0
+    HANDLER 2, 1
+    HANDLER 2, 2
+    HANDLER 2, 3
+
+    HANDLER 3, 0
+    HANDLER 3, 1
+    HANDLER 3, 2
+    HANDLER 3, 3
@@ -1,15 +1,22 @@
 use core::time::Duration;
 use core::panic::PanicInfo;
 
-use crate::console::kprintln;
+use shim::io::Write;
+
+use crate::console::{kprintln, CONSOLE};
 use crate::timer;
 
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
+    // The panicking code may hold the console, and will never release it.
+    unsafe { CONSOLE.force_unlock() };
+
     kprintln!("---------- PANIC ----------");
     loop {
         kprintln!("AAAAAAAAHHHHHHHHHHHHHHHHHHHHHHHHHHHHH");
         kprintln!("{}", info);
+        // IRQs may be masked for good, so send what the console buffered.
+        let _ = CONSOLE.lock().flush();
         timer::spin_sleep(Duration::new(1,0));
     }
 }
@@ -0,0 +1,182 @@
+use alloc::boxed::Box;
+
+use pi::interrupt::{Controller, Interrupt};
+
+use crate::mutex::IrqMutex;
+use crate::traps::TrapFrame;
+
+/// A handler for an interrupt, given the state of the code it interrupted.
+pub type IrqHandler = Box<dyn FnMut(&mut TrapFrame) + Send>;
+
+/// What has happened on one IRQ line.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct IrqStats {
+    /// Whether a handler is registered.
+    pub registered: bool,
+    /// The number of times the handler ran.
+    pub count: u64,
+    /// The number of times the interrupt was pending with no handler.
+    pub unhandled: u64,
+}
+
+struct Line {
+    handler: Option<IrqHandler>,
+    stats: IrqStats,
+}
+
+impl Line {
+    const fn new() -> Line {
+        Line {
+            handler: None,
+            stats: IrqStats { registered: false, count: 0, unhandled: 0 },
+        }
+    }
+}
+
+struct Lines {
+    lines: [Line; Interrupt::MAX],
+    spurious: u64,
+}
+
+/// The kernel's IRQ handlers, one per interrupt source, dispatched from the
+/// IRQ exception vector.
+///
+/// Handlers run with IRQs masked and with the registry locked, so they must
+/// not register or unregister handlers themselves.
+pub struct Irq(IrqMutex<Lines>);
+
+impl Irq {
+    /// Returns a registry with no handlers. Its lock is named, so it must
+    /// never move once used: it is meant for a static.
+    pub const fn new() -> Irq {
+        Irq(IrqMutex::named("irq handlers", Lines {
+            lines: [
+                Line::new(),
+                Line::new(),
+                Line::new(),
+                Line::new(),
+                Line::new(),
+                Line::new(),
+                Line::new(),
+                Line::new(),
+                Line::new(),
+            ],
+            spurious: 0,
+        }))
+    }
+
+    /// Registers `handler` for the interrupt `int`, replacing the handler
+    /// registered before. The interrupt must still be enabled with
+    /// `pi::interrupt::Controller::enable()` for the handler to run.
+    pub fn register(&self, int: Interrupt, handler: IrqHandler) {
+        let line = &mut self.0.lock().lines[int.to_index()];
+        line.handler = Some(handler);
+        line.stats.registered = true;
+    }
+
+    /// Removes and returns the handler for the interrupt `int`.
+    pub fn unregister(&self, int: Interrupt) -> Option<IrqHandler> {
+        let line = &mut self.0.lock().lines[int.to_index()];
+        line.stats.registered = false;
+        line.handler.take()
+    }
+
+    /// Runs the handler for the interrupt `int` with `tf`, returning `false`
+    /// if there is none.
+    pub fn invoke(&self, int: Interrupt, tf: &mut TrapFrame) -> bool {
+        let line = &mut self.0.lock().lines[int.to_index()];
+        match line.handler {
+            Some(ref mut handler) => {
+                line.stats.count += 1;
+                handler(tf);
+                true
+            }
+            None => {
+                line.stats.unhandled += 1;
+                false
+            }
+        }
+    }
+
+    /// Runs the handlers of every pending interrupt. Interrupts without a
+    /// handler are disabled so that they cannot raise IRQs forever.
+    pub fn dispatch(&self, tf: &mut TrapFrame) {
+        let mut controller = Controller::new();
+        let mut pending = false;
+        for int in Interrupt::iter() {
+            if !controller.is_pending(int) {
+                continue;
+            }
+
+            pending = true;
+            if !self.invoke(int, tf) {
+                controller.disable(int);
+            }
+        }
+
+        if !pending {
+            self.0.lock().spurious += 1;
+        }
+    }
+
+    /// Returns what has happened on the IRQ line of the interrupt `int`.
+    pub fn stats(&self, int: Interrupt) -> IrqStats {
+        self.0.lock().lines[int.to_index()].stats
+    }
+
+    /// Returns the number of IRQs taken with no interrupt pending.
+    pub fn spurious(&self) -> u64 {
+        self.0.lock().spurious
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use pi::interrupt::Interrupt;
+
+    use super::{Irq, IrqStats};
+    use crate::traps::TrapFrame;
+
+    #[test]
+    fn invokes_registered_handlers() {
+        static IRQ: Irq = Irq::new();
+        let irq = &IRQ;
+        let mut tf = TrapFrame::default();
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        assert!(!irq.invoke(Interrupt::Timer1, &mut tf));
+        let counter = calls.clone();
+        irq.register(
+            Interrupt::Timer1,
+            Box::new(move |tf: &mut TrapFrame| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                tf.x[0] = 7;
+            }),
+        );
+        assert!(irq.invoke(Interrupt::Timer1, &mut tf));
+        assert!(irq.invoke(Interrupt::Timer1, &mut tf));
+        assert!(!irq.invoke(Interrupt::Aux, &mut tf));
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(tf.x[0], 7);
+        assert_eq!(irq.stats(Interrupt::Timer1), IrqStats { registered: true, count: 2, unhandled: 1 });
+        assert_eq!(irq.stats(Interrupt::Aux), IrqStats { registered: false, count: 0, unhandled: 1 });
+    }
+
+    #[test]
+    fn unregisters_handlers() {
+        static IRQ: Irq = Irq::new();
+        let irq = &IRQ;
+        let mut tf = TrapFrame::default();
+        irq.register(Interrupt::Uart, Box::new(|_: &mut TrapFrame| {}));
+
+        assert!(irq.unregister(Interrupt::Uart).is_some());
+        assert!(irq.unregister(Interrupt::Uart).is_none());
+        assert!(!irq.invoke(Interrupt::Uart, &mut tf));
+        assert!(!irq.stats(Interrupt::Uart).registered);
+    }
+}
@@ -5,6 +5,7 @@
 #![feature(global_asm)]
 #![feature(optin_builtin_traits)]
 #![feature(raw_vec_internals)]
+#![feature(track_caller)]
 #![cfg_attr(not(test), no_std)]
 #![cfg_attr(not(test), no_main)]
 
@@ -13,12 +14,18 @@ mod init;
 
 extern crate alloc;
 
+pub mod aarch64;
 pub mod allocator;
 pub mod console;
 pub mod fs;
+pub mod irq;
 pub mod mutex;
 pub mod shell;
+pub mod sync;
+pub mod timers;
+pub mod traps;
 
+use pi::atags::Atags;
 use pi::timer;
 use pi::gpio::{Gpio, Output};
 use console::kprintln;
@@ -27,18 +34,27 @@ use core::time::Duration;
 use core::fmt::Write; 
 use allocator::Allocator;
 use fs::FileSystem;
+use irq::Irq;
+use timers::Timers;
 
 #[cfg_attr(not(test), global_allocator)]
 pub static ALLOCATOR: Allocator = Allocator::uninitialized();
-//pub static FILESYSTEM: FileSystem = FileSystem::uninitialized();
+pub static FILESYSTEM: FileSystem = FileSystem::uninitialized();
+pub static IRQ: Irq = Irq::new();
+pub static TIMERS: Timers = Timers::uninitialized();
 
 fn kmain() -> ! {
     pi::timer::spin_sleep(Duration::from_secs(3));
 
+    allocator::reserve_boot_regions();
     unsafe {
-        //FILESYSTEM.initialize();
         ALLOCATOR.initialize();
+        FILESYSTEM.initialize();
+        TIMERS.initialize();
+        console::enable_interrupts();
+        aarch64::enable_irqs();
     }
 
-    shell::shell("> ");
+    let autorun = Atags::get().filter_map(|atag| atag.cmd()).find_map(shell::autorun_path);
+    shell::shell("> ", autorun);
 }
@@ -1,13 +1,38 @@
 use core::fmt;
-use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use core::panic::Location;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
 use core::cell::UnsafeCell;
 use core::ops::{DerefMut, Deref, Drop};
+use core::ptr;
 
+use crate::aarch64;
+use crate::sync;
+
+/// `RawMutex::lock` while the lock is free and while it is held.
+const UNLOCKED: usize = 0;
+const LOCKED: usize = 1;
+
+/// `RawMutex::owner` when no core holds the lock.
+const NO_OWNER: usize = usize::max_value();
+
+/// Waiters warn that a lock may be stuck once they have spun this many
+/// times, unless changed with `set_spin_warning()`.
+#[cfg_attr(not(debug_assertions), allow(dead_code))]
+pub const DEFAULT_SPIN_WARNING: usize = 1 << 22;
+
+/// A spinlock. Cores waiting for it sleep in `wfe` until it is released.
+///
+/// Locking a `Mutex` on the core that already holds it panics instead of
+/// spinning forever.
+///
+/// In debug builds, every `Mutex` records where its holder took it and how
+/// contended it is, and waiters spin instead of sleeping so that they can
+/// warn once they have waited `DEFAULT_SPIN_WARNING` spins. Mutexes made
+/// with `named()` are listed by `each_lock()`.
 #[repr(align(32))]
 pub struct Mutex<T> {
     data: UnsafeCell<T>,
-    lock: AtomicBool,
-    owner: AtomicUsize
+    raw: RawMutex,
 }
 
 unsafe impl<T: Send> Send for Mutex<T> { }
@@ -23,45 +48,309 @@ unsafe impl<'a, T: Sync> Sync for MutexGuard<'a, T> { }
 impl<T> Mutex<T> {
     pub const fn new(val: T) -> Mutex<T> {
         Mutex {
-            lock: AtomicBool::new(false),
-            owner: AtomicUsize::new(usize::max_value()),
+            raw: RawMutex::new(None),
+            data: UnsafeCell::new(val)
+        }
+    }
+
+    /// Returns a `Mutex` that debug builds list as `name` once it has been
+    /// locked. It must never move or be dropped after that, which holds for
+    /// the statics it is meant for.
+    pub const fn named(name: &'static str, val: T) -> Mutex<T> {
+        Mutex {
+            raw: RawMutex::new(Some(name)),
             data: UnsafeCell::new(val)
         }
     }
 }
 
 impl<T> Mutex<T> {
-    // Once MMU/cache is enabled, do the right thing here. For now, we don't
-    // need any real synchronization.
+    /// Returns a guard for the lock if it is free, or `None` if any core,
+    /// including this one, holds it.
+    #[cfg_attr(debug_assertions, track_caller)]
     pub fn try_lock(&self) -> Option<MutexGuard<T>> {
-        let this = 0;
-        if !self.lock.load(Ordering::Relaxed) || self.owner.load(Ordering::Relaxed) == this {
-            self.lock.store(true, Ordering::Relaxed);
-            self.owner.store(this, Ordering::Relaxed);
-            Some(MutexGuard { lock: &self })
-        } else {
-            None
+        match self.raw.try_lock() {
+            true => Some(MutexGuard { lock: self }),
+            false => None,
         }
     }
 
-    // Once MMU/cache is enabled, do the right thing here. For now, we don't
-    // need any real synchronization.
+    /// Waits for the lock to be free, then takes it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this core already holds the lock.
     #[inline(never)]
+    #[cfg_attr(debug_assertions, track_caller)]
     pub fn lock(&self) -> MutexGuard<T> {
-        // Wait until we can "aquire" the lock, then "acquire" it.
+        self.raw.lock();
+        MutexGuard { lock: self }
+    }
+
+    /// Releases the lock, whoever holds it.
+    ///
+    /// # Safety
+    ///
+    /// The holder must never touch the data again, as when it has panicked.
+    pub unsafe fn force_unlock(&self) {
+        self.raw.unlock()
+    }
+}
+
+/// The lock of a `Mutex`, apart from the data it protects.
+struct RawMutex {
+    lock: AtomicUsize,
+    owner: AtomicUsize,
+    #[cfg(debug_assertions)]
+    diagnostics: Diagnostics,
+}
+
+impl RawMutex {
+    const fn new(name: Option<&'static str>) -> RawMutex {
+        #[cfg(not(debug_assertions))]
+        let _ = name;
+
+        RawMutex {
+            lock: AtomicUsize::new(UNLOCKED),
+            owner: AtomicUsize::new(NO_OWNER),
+            #[cfg(debug_assertions)]
+            diagnostics: Diagnostics::new(name),
+        }
+    }
+
+    /// Takes the lock if it is free.
+    fn acquire(&self) -> bool {
+        sync::compare_exchange(&self.lock, UNLOCKED, LOCKED)
+    }
+
+    #[cfg_attr(debug_assertions, track_caller)]
+    fn try_lock(&self) -> bool {
+        if !self.acquire() {
+            return false;
+        }
+
+        self.owner.store(aarch64::affinity(), Ordering::Relaxed);
+        #[cfg(debug_assertions)]
+        self.diagnostics.acquired(self, Location::caller());
+        true
+    }
+
+    #[cfg_attr(debug_assertions, track_caller)]
+    fn lock(&self) {
+        let this = aarch64::affinity();
+        let mut spins = 0;
         loop {
-            match self.try_lock() {
-                Some(guard) => return guard,
-                None => continue
+            if self.try_lock() {
+                #[cfg(debug_assertions)]
+                self.diagnostics.waited(spins);
+                return;
             }
+
+            // Only this core stores its own ID, so seeing it means the lock
+            // is held further up this core's stack, which waiting won't fix.
+            let owner = self.owner.load(Ordering::Relaxed);
+            if owner == this {
+                panic!("Mutex locked again on core {}, which already holds it", this);
+            }
+
+            spins += 1;
+            #[cfg(debug_assertions)]
+            self.diagnostics.spinning(spins, owner, Location::caller());
+            // Sleeping until an event would stop the count, and the warning.
+            #[cfg(not(debug_assertions))]
+            aarch64::wfe();
         }
     }
 
     fn unlock(&self) {
-        self.lock.store(false, Ordering::Relaxed);
+        self.owner.store(NO_OWNER, Ordering::Relaxed);
+        self.lock.store(UNLOCKED, Ordering::Release);
+        aarch64::sev();
+    }
+}
+
+/// A snapshot of a named lock's state and contention.
+#[derive(Debug, Copy, Clone)]
+pub struct LockStats {
+    pub name: &'static str,
+    /// The core holding the lock, if any.
+    pub owner: Option<usize>,
+    /// Where the lock was taken last, which is where its holder took it if
+    /// it is held.
+    pub holder: Option<&'static Location<'static>>,
+    /// The number of times the lock was taken.
+    pub acquisitions: usize,
+    /// The number of times the lock was taken after waiting for it.
+    pub contended: usize,
+    /// The spins spent waiting for the lock, in all and at most at once.
+    pub spins: usize,
+    pub max_spins: usize,
+}
+
+/// The spins after which waiters warn, or 0 if they don't.
+#[cfg(debug_assertions)]
+static SPIN_WARNING: AtomicUsize = AtomicUsize::new(DEFAULT_SPIN_WARNING);
+
+/// The named locks that have been taken, newest first, linked through
+/// `Diagnostics::next`.
+#[cfg(debug_assertions)]
+static LOCKS: Mutex<Option<&'static RawMutex>> = Mutex::new(None);
+
+/// What debug builds record about a lock. The counters are updated with
+/// plain loads and stores, which need no exclusive accesses but may lose
+/// counts when cores race.
+#[cfg(debug_assertions)]
+struct Diagnostics {
+    name: Option<&'static str>,
+    registered: AtomicBool,
+    next: AtomicPtr<RawMutex>,
+    holder: AtomicPtr<Location<'static>>,
+    acquisitions: AtomicUsize,
+    contended: AtomicUsize,
+    spins: AtomicUsize,
+    max_spins: AtomicUsize,
+}
+
+#[cfg(debug_assertions)]
+fn add(counter: &AtomicUsize, n: usize) {
+    counter.store(counter.load(Ordering::Relaxed).wrapping_add(n), Ordering::Relaxed);
+}
+
+#[cfg(debug_assertions)]
+impl Diagnostics {
+    const fn new(name: Option<&'static str>) -> Diagnostics {
+        Diagnostics {
+            name,
+            registered: AtomicBool::new(false),
+            next: AtomicPtr::new(ptr::null_mut()),
+            holder: AtomicPtr::new(ptr::null_mut()),
+            acquisitions: AtomicUsize::new(0),
+            contended: AtomicUsize::new(0),
+            spins: AtomicUsize::new(0),
+            max_spins: AtomicUsize::new(0),
+        }
+    }
+
+    /// Records that `raw`, which this belongs to, was taken at `at`, and
+    /// lists it if it is named and not listed yet.
+    fn acquired(&self, raw: &RawMutex, at: &'static Location<'static>) {
+        self.holder.store(at as *const Location as *mut Location, Ordering::Relaxed);
+        add(&self.acquisitions, 1);
+
+        if self.name.is_some() && !self.registered.load(Ordering::Relaxed) {
+            let mut locks = LOCKS.lock();
+            if !self.registered.load(Ordering::Relaxed) {
+                let next = locks.map_or(ptr::null_mut(), |l| l as *const RawMutex as *mut RawMutex);
+                self.next.store(next, Ordering::Relaxed);
+                // Named mutexes never move or go away once taken.
+                *locks = Some(unsafe { &*(raw as *const RawMutex) });
+                self.registered.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Records that the lock was taken after `spins` spins.
+    fn waited(&self, spins: usize) {
+        if spins == 0 {
+            return;
+        }
+
+        add(&self.contended, 1);
+        add(&self.spins, spins);
+        if spins > self.max_spins.load(Ordering::Relaxed) {
+            self.max_spins.store(spins, Ordering::Relaxed);
+        }
+    }
+
+    /// Warns once a waiter at `at` has spun as long as `SPIN_WARNING` for the
+    /// lock `owner` holds.
+    fn spinning(&self, spins: usize, owner: usize, at: &'static Location<'static>) {
+        if spins != SPIN_WARNING.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let holder: &dyn fmt::Display = match unsafe { self.holder.load(Ordering::Relaxed).as_ref() } {
+            Some(location) => location,
+            None => &"<unknown>",
+        };
+        warn(format_args!(
+            "lock {} may be stuck: core {} has waited {} spins at {}; core {} took it at {}",
+            self.name.unwrap_or("<unnamed>"),
+            aarch64::affinity(),
+            spins,
+            at,
+            owner,
+            holder
+        ));
     }
 }
 
+/// Prints a warning about a lock. Goes without the console if it is the lock
+/// in question, or is held elsewhere.
+#[cfg(all(debug_assertions, not(test)))]
+fn warn(args: fmt::Arguments) {
+    use core::fmt::Write;
+
+    if let Some(mut console) = crate::console::CONSOLE.try_lock() {
+        let _ = writeln!(console, "{}", args);
+    }
+}
+
+#[cfg(all(debug_assertions, test))]
+fn warn(args: fmt::Arguments) {
+    eprintln!("{}", args);
+}
+
+/// Calls `f` with a snapshot of every named lock that has been taken, newest
+/// first. Returns `false` without calling it in release builds, which keep
+/// no diagnostics.
+#[cfg(debug_assertions)]
+pub fn each_lock<F: FnMut(&LockStats)>(mut f: F) -> bool {
+    let mut next = *LOCKS.lock();
+    while let Some(raw) = next {
+        let d = &raw.diagnostics;
+        let owner = raw.owner.load(Ordering::Relaxed);
+        let holder = d.holder.load(Ordering::Relaxed);
+        f(&LockStats {
+            name: d.name.unwrap_or("<unnamed>"),
+            owner: if owner == NO_OWNER { None } else { Some(owner) },
+            holder: if holder.is_null() { None } else { Some(unsafe { &*holder }) },
+            acquisitions: d.acquisitions.load(Ordering::Relaxed),
+            contended: d.contended.load(Ordering::Relaxed),
+            spins: d.spins.load(Ordering::Relaxed),
+            max_spins: d.max_spins.load(Ordering::Relaxed),
+        });
+        next = unsafe { d.next.load(Ordering::Relaxed).as_ref() };
+    }
+    true
+}
+
+#[cfg(not(debug_assertions))]
+pub fn each_lock<F: FnMut(&LockStats)>(_f: F) -> bool {
+    false
+}
+
+/// Makes waiters warn after `spins` spins, or never if it is 0. Returns
+/// `false` in release builds, where waiters never warn.
+pub fn set_spin_warning(spins: usize) -> bool {
+    #[cfg(debug_assertions)]
+    SPIN_WARNING.store(spins, Ordering::Relaxed);
+    #[cfg(not(debug_assertions))]
+    let _ = spins;
+
+    cfg!(debug_assertions)
+}
+
+/// Returns the spins after which waiters warn, or 0 if they never do.
+pub fn spin_warning() -> usize {
+    #[cfg(debug_assertions)]
+    let spins = SPIN_WARNING.load(Ordering::Relaxed);
+    #[cfg(not(debug_assertions))]
+    let spins = 0;
+
+    spins
+}
+
 impl<'a, T: 'a> Deref for MutexGuard<'a, T> {
     type Target = T;
 
@@ -78,7 +367,7 @@ impl<'a, T: 'a> DerefMut for MutexGuard<'a, T> {
 
 impl<'a, T: 'a> Drop for MutexGuard<'a, T> {
     fn drop(&mut self) {
-        self.lock.unlock()
+        self.lock.raw.unlock()
     }
 }
 
@@ -90,3 +379,209 @@ impl<T: fmt::Debug> fmt::Debug for Mutex<T> {
         }
     }
 }
+
+/// A `Mutex` that masks IRQs on the core holding it, so that data shared
+/// with interrupt handlers can't be locked by a handler that interrupted
+/// its holder.
+pub struct IrqMutex<T>(Mutex<T>);
+
+/// Restores the interrupt masks when dropped.
+struct IrqRestore(u64);
+
+impl Drop for IrqRestore {
+    fn drop(&mut self) {
+        unsafe { aarch64::set_daif(self.0) }
+    }
+}
+
+pub struct IrqMutexGuard<'a, T: 'a> {
+    // Fields are dropped in order: the lock is released before IRQs are
+    // unmasked.
+    guard: MutexGuard<'a, T>,
+    _irqs: IrqRestore,
+}
+
+impl<T> IrqMutex<T> {
+    pub const fn new(val: T) -> IrqMutex<T> {
+        IrqMutex(Mutex::new(val))
+    }
+
+    /// Returns an `IrqMutex` that debug builds list as `name`, as
+    /// `Mutex::named()` does.
+    pub const fn named(name: &'static str, val: T) -> IrqMutex<T> {
+        IrqMutex(Mutex::named(name, val))
+    }
+
+    /// Masks IRQs and returns a guard for the lock if it is free. IRQs are
+    /// left as they were if it is not.
+    #[cfg_attr(debug_assertions, track_caller)]
+    pub fn try_lock(&self) -> Option<IrqMutexGuard<T>> {
+        let irqs = IrqRestore(aarch64::disable_irqs());
+        self.0.try_lock().map(|guard| IrqMutexGuard { guard, _irqs: irqs })
+    }
+
+    /// Masks IRQs, then waits for the lock to be free and takes it. IRQs
+    /// stay masked until the guard is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this core already holds the lock.
+    #[cfg_attr(debug_assertions, track_caller)]
+    pub fn lock(&self) -> IrqMutexGuard<T> {
+        let irqs = IrqRestore(aarch64::disable_irqs());
+        IrqMutexGuard { guard: self.0.lock(), _irqs: irqs }
+    }
+
+    /// Releases the lock, whoever holds it. IRQs are left as they are.
+    ///
+    /// # Safety
+    ///
+    /// The holder must never touch the data again, as when it has panicked.
+    pub unsafe fn force_unlock(&self) {
+        self.0.force_unlock()
+    }
+}
+
+impl<'a, T: 'a> Deref for IrqMutexGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<'a, T: 'a> DerefMut for IrqMutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for IrqMutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.try_lock() {
+            Some(guard) => f.debug_struct("IrqMutex").field("data", &&*guard).finish(),
+            None => f.debug_struct("IrqMutex").field("data", &"<locked>").finish()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::{IrqMutex, Mutex};
+    #[cfg(debug_assertions)]
+    use super::{each_lock, LockStats};
+
+    #[test]
+    fn excludes_other_threads() {
+        let counter = Arc::new(Mutex::new(0usize));
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let counter = counter.clone();
+                thread::spawn(move || {
+                    for _ in 0..10_000 {
+                        // A read and a separate write, which would lose
+                        // updates without the lock.
+                        let mut guard = counter.lock();
+                        let value = *guard;
+                        *guard = value + 1;
+                    }
+                })
+            })
+            .collect();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(*counter.lock(), 40_000);
+    }
+
+    #[test]
+    fn try_lock_fails_while_held() {
+        let mutex = IrqMutex::new(5);
+        let guard = mutex.lock();
+        assert!(mutex.try_lock().is_none());
+        assert_eq!(format!("{:?}", mutex), "IrqMutex { data: \"<locked>\" }");
+
+        drop(guard);
+        assert_eq!(*mutex.try_lock().unwrap(), 5);
+        assert_eq!(format!("{:?}", mutex), "IrqMutex { data: 5 }");
+    }
+
+    #[test]
+    #[should_panic(expected = "which already holds it")]
+    fn panics_when_locked_twice() {
+        let mutex = Mutex::new(());
+        let _guard = mutex.lock();
+        let _again = mutex.lock();
+    }
+
+    #[test]
+    fn force_unlock_releases_a_leaked_guard() {
+        let mutex = Mutex::new(1);
+        std::mem::forget(mutex.lock());
+        assert!(mutex.try_lock().is_none());
+
+        unsafe { mutex.force_unlock() };
+        assert_eq!(*mutex.lock(), 1);
+    }
+
+    #[cfg(debug_assertions)]
+    fn stats(name: &str) -> Option<LockStats> {
+        let mut found = None;
+        each_lock(|lock| {
+            if lock.name == name {
+                found = Some(*lock);
+            }
+        });
+        found
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn lists_named_locks_once_taken() {
+        static NAMED: Mutex<()> = Mutex::named("test: listed", ());
+        static UNNAMED: Mutex<()> = Mutex::new(());
+
+        assert!(stats("test: listed").is_none());
+        drop(UNNAMED.lock());
+        let guard = NAMED.lock();
+        let line = line!() - 1;
+
+        let held = stats("test: listed").expect("listed once taken");
+        assert_eq!(held.owner, Some(super::aarch64::affinity()));
+        assert_eq!(held.acquisitions, 1);
+        let holder = held.holder.expect("holder recorded");
+        assert_eq!((holder.file(), holder.line()), (file!(), line));
+
+        drop(guard);
+        drop(NAMED.lock());
+        let free = stats("test: listed").unwrap();
+        assert_eq!(free.owner, None);
+        assert_eq!(free.acquisitions, 2);
+        assert_eq!(free.contended, 0);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn counts_contention() {
+        static CONTENDED: Mutex<()> = Mutex::named("test: contended", ());
+
+        let guard = CONTENDED.lock();
+        let waiter = thread::spawn(|| drop(CONTENDED.lock()));
+        while stats("test: contended").map_or(true, |s| s.acquisitions < 1) {
+            thread::yield_now();
+        }
+        thread::sleep(std::time::Duration::from_millis(10));
+        drop(guard);
+        waiter.join().unwrap();
+
+        let stats = stats("test: contended").unwrap();
+        assert_eq!(stats.acquisitions, 2);
+        assert_eq!(stats.contended, 1);
+        assert!(stats.spins > 0);
+        assert_eq!(stats.spins, stats.max_spins);
+    }
+}
@@ -1,127 +1,453 @@
-use shim::io;
-use shim::path::{Path, PathBuf};
+mod builtin;
+mod command;
+mod complete;
+mod filter;
+mod fs;
+mod gpio;
+mod hexdump;
+mod line;
+mod mem;
+mod parse;
+mod script;
+mod stdio;
+mod sys;
+mod tmp;
+
+#[cfg(test)]
+mod tests;
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 
-use stack_vec::StackVec;
+use core::time::Duration;
 
-use pi::atags::Atags;
+use pi::timer;
+
+use shim::io;
+use shim::path::{Path, PathBuf};
 
 use fat32::traits::FileSystem;
-use fat32::traits::{Dir, Entry};
 
-use crate::console::{kprint, kprintln, CONSOLE};
-use crate::ALLOCATOR;
-//use crate::FILESYSTEM;
+use crate::console::{self, kprint, kprintln, CONSOLE};
+use crate::FILESYSTEM;
+
+use self::command::Registry;
+use self::complete::ShellCompleter;
+use self::line::{Action, LineEditor};
+use self::parse::{Command, Connector, ParseError, Pipeline, RedirectKind, Word};
+use self::stdio::{ConsoleIn, ConsoleOut};
+use self::tmp::TmpFs;
 
 use shim::io::Read;
 
 use core::str;
-use core::default::Default;
 
-/// Error type for `Command` parse failures.
-#[derive(Debug)]
-enum Error {
-    Empty,
-    TooManyArgs,
-}
+pub use self::command::{Builtin, Error as CommandError, ShellCommand};
+pub use self::script::autorun_path;
+pub use self::stdio::Stdio;
+
+/// Every command the shell knows about. Add a module's `COMMANDS` slice here
+/// to make its commands available.
+pub static REGISTRY: Registry = Registry::new(&[
+    builtin::COMMANDS,
+    fs::COMMANDS,
+    filter::COMMANDS,
+    mem::COMMANDS,
+    gpio::COMMANDS,
+    sys::COMMANDS,
+]);
+
+/// Exit status of a command that succeeded.
+pub const SUCCESS: u8 = 0;
+
+/// Exit status of a command that failed.
+pub const FAILURE: u8 = 1;
+
+/// Exit status of a command given bad arguments.
+pub const USAGE: u8 = 2;
+
+/// Exit status when no such command exists.
+pub const NOT_FOUND: u8 = 127;
 
-/// A structure representing a single shell command.
-struct Command<'a> {
-    args: StackVec<'a, &'a str>,
+/// How long a key may be pressed to skip the autorun script.
+const AUTORUN_DELAY: Duration = Duration::from_secs(3);
+
+/// State the shell keeps between commands.
+pub struct Shell {
+    cwd: PathBuf,
+    /// Shell variables. `?` holds the exit status of the last command.
+    env: BTreeMap<String, String>,
+    tmp: TmpFs,
+    /// Set by `exit` to the status to exit with.
+    exit: Option<u8>,
+    /// The number of scripts being run by `source`.
+    depth: usize,
 }
 
-impl<'a> Command<'a> {
-    /// Parse a command from a string `s` using `buf` as storage for the
-    /// arguments.
+impl Shell {
+    /// Returns a new shell state with `/` as the working directory and no
+    /// variables set.
+    pub fn new() -> Shell {
+        let mut env = BTreeMap::new();
+        env.insert("?".to_string(), SUCCESS.to_string());
+        Shell { cwd: PathBuf::from("/"), env, tmp: TmpFs::new(), exit: None, depth: 0 }
+    }
+
+    /// Returns the working directory. It is always absolute and normalized.
+    pub fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    /// Sets the working directory to `path`, which must be absolute and
+    /// normalized.
+    pub fn set_cwd(&mut self, path: PathBuf) {
+        self.cwd = path;
+    }
+
+    /// Returns the absolute, normalized path `path` refers to when given
+    /// relative to the working directory.
+    pub fn resolve<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        fs::normalize(&self.cwd.join(path))
+    }
+
+    /// Returns the value of the variable `name`, if it is set.
+    pub fn var(&self, name: &str) -> Option<&str> {
+        self.env.get(name).map(|value| value.as_str())
+    }
+
+    /// Sets the variable `name` to `value`.
+    pub fn set_var(&mut self, name: &str, value: &str) {
+        self.env.insert(name.to_string(), value.to_string());
+    }
+
+    /// Removes the variable `name`.
+    pub fn unset_var(&mut self, name: &str) {
+        self.env.remove(name);
+    }
+
+    /// Returns an iterator over the variables set by the user, in order of
+    /// their names.
+    pub fn vars(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.env
+            .iter()
+            .filter(|(name, _)| name.as_str() != "?")
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Returns the exit status of the last command run.
+    pub fn status(&self) -> u8 {
+        self.var("?").and_then(|status| status.parse().ok()).unwrap_or(SUCCESS)
+    }
+
+    fn set_status(&mut self, status: u8) {
+        self.set_var("?", &status.to_string());
+    }
+
+    /// Asks the shell to stop once the running command returns: a script run
+    /// by `source` ends with exit status `status`, and at the prompt the
+    /// shell starts over.
+    pub fn exit(&mut self, status: u8) {
+        self.exit = Some(status);
+    }
+
+    /// Returns the in-memory files under `/tmp`.
+    pub fn tmp(&self) -> &TmpFs {
+        &self.tmp
+    }
+
+    /// Returns the in-memory files under `/tmp` for modification.
+    pub fn tmp_mut(&mut self) -> &mut TmpFs {
+        &mut self.tmp
+    }
+
+    /// Opens the file at the absolute path `path` for reading, from `/tmp` or
+    /// the mounted file system.
+    pub fn open(&self, path: &Path) -> io::Result<Box<dyn io::Read>> {
+        if let Some(data) = self.tmp.get(path) {
+            return Ok(Box::new(io::Cursor::new(data.to_vec())));
+        }
+
+        let file = (&FILESYSTEM).open_file(path)?;
+        Ok(Box::new(file))
+    }
+
+    /// Parses `line` and runs its pipelines in order, skipping those after
+    /// `&&` or `||` as the exit status of the one before dictates. Nothing
+    /// more is run once a command has called `exit`.
     ///
     /// # Errors
     ///
-    /// If `s` contains no arguments, returns `Error::Empty`. If there are more
-    /// arguments than `buf` can hold, returns `Error::TooManyArgs`.
-    fn parse(s: &'a str, buf: &'a mut [&'a str]) -> Result<Command<'a>, Error> {
-        let mut args = StackVec::new(buf);
-        for arg in s.split(' ').filter(|a| !a.is_empty()) {
-            args.push(arg).map_err(|_| Error::TooManyArgs)?;
-        }
+    /// If `line` cannot be parsed, nothing is run and the error is returned.
+    pub fn execute(&mut self, line: &str) -> Result<(), ParseError> {
+        for (connector, pipeline) in parse::parse(line)? {
+            if self.exit.is_some() {
+                break;
+            }
+
+            let run = match connector {
+                Connector::Then => true,
+                Connector::And => self.status() == SUCCESS,
+                Connector::Or => self.status() != SUCCESS,
+            };
 
-        if args.is_empty() {
-            return Err(Error::Empty);
+            if run {
+                let status = self.run_pipeline(&pipeline);
+                self.set_status(status);
+            }
         }
 
-        Ok(Command { args })
+        Ok(())
     }
 
-    /// Returns this command's path. This is equivalent to the first argument.
-    fn path(&self) -> &str {
-        self.args[0]
-    }
+    /// Runs the script at the absolute path `path` line by line, stopping
+    /// early if it runs `exit`. Returns the exit status of the last command
+    /// run, or the one given to `exit`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the script cannot be read, if scripts are nested
+    /// too deeply, or if a line cannot be parsed, in which case the error
+    /// names the file, line and column.
+    pub fn source(&mut self, path: &Path) -> Result<u8, CommandError> {
+        if self.depth >= script::MAX_DEPTH {
+            return Err(CommandError::Failed(format!("{}: scripts nested too deeply", path.display())));
+        }
 
-    fn execute(&self) {
-        let path = self.path();
-        let args = &self.args.as_slice();
-        match path {
-            "echo" => echo_cmd(args),
-            "atags" => atag_cmd(),
-            _ => kprint!("unknown command: {}", path)
+        let mut data = Vec::new();
+        self.open(path)
+            .and_then(|mut file| file.read_to_end(&mut data))
+            .map_err(|e| fs::path_error(path, e))?;
+        let text = str::from_utf8(&data)
+            .map_err(|_| CommandError::Failed(format!("{}: not a text file", path.display())))?;
+
+        self.depth += 1;
+        let mut result = Ok(SUCCESS);
+        for (number, line) in script::lines(text) {
+            if let Err(e) = self.execute(line) {
+                let location = format!("{}:{}:{}", path.display(), number, e.column + 1);
+                result = Err(CommandError::Failed(format!("{}: {}", location, e.kind)));
+                break;
+            }
+
+            if let Some(status) = self.exit.take() {
+                result = Ok(status);
+                break;
+            }
+
+            result = Ok(self.status());
         }
+        self.depth -= 1;
+
+        result
     }
-}
 
-/// Starts a shell using `prefix` as the prefix for each line. This function
-/// never returns.
-pub fn shell(prefix: &str) -> ! {
-    let mut cmd_mem: [u8; 512] = [0; 512];
-    //let mut parse_mem: [&str; 10] = Default::default();
+    /// Runs the script at `path` unless a key is pressed within
+    /// `AUTORUN_DELAY`, reporting any error to the console. The file system
+    /// must have been mounted.
+    pub fn autorun(&mut self, path: &str) {
+        if !FILESYSTEM.is_mounted() {
+            kprintln!("autorun: not running {}: no file system mounted", path);
+            self.set_status(FAILURE);
+            return;
+        }
 
-    let mut user_input: StackVec<u8> = StackVec::new(&mut cmd_mem);
+        kprintln!("running {} in {}s; press any key to skip", path, AUTORUN_DELAY.as_secs());
 
-    kprint!("{}", prefix);
-    loop {
-        let read_char = CONSOLE.lock().read_byte();
+        let deadline = timer::current_time() + AUTORUN_DELAY;
+        while timer::current_time() < deadline {
+            if CONSOLE.lock().try_read_byte().is_some() {
+                kprintln!("skipped {}", path);
+                return;
+            }
+        }
 
-        if read_char == 127 || read_char == 8 {
-            if !user_input.is_empty() {
-                user_input.pop();
-                kprint!("\u{8} \u{8}");
+        let path = self.resolve(path);
+        match self.source(&path) {
+            Ok(status) => self.set_status(status),
+            Err(e) => {
+                kprintln!("autorun: {}", e);
+                self.set_status(FAILURE);
             }
-        } else if read_char == b'\n' || read_char == b'\r' {
-            kprint!("\r\n");
-
-            let mut parsed_cmd = [""; 64]; 
-            let string = str::from_utf8(user_input.as_slice()).unwrap();
-            let cmd = Command::parse(&string, &mut parsed_cmd);
-
-            match cmd {
-                Ok(cmd) => cmd.execute(),
-                Err(err) => {
-                    match err {
-                        Error::TooManyArgs => kprintln!("error: too many arguments"),
-                        Error::Empty => (),
+        }
+    }
+
+    /// Reads and runs lines typed at the console, each shown after `prefix`,
+    /// until `exit` is run. Returns the status given to `exit`.
+    pub fn interact(&mut self, prefix: &str) -> u8 {
+        let mut editor = LineEditor::new(prefix);
+
+        loop {
+            let _ = editor.start(&mut *CONSOLE.lock());
+
+            loop {
+                let byte = console::read_byte();
+                match editor.feed(byte, &mut *CONSOLE.lock()) {
+                    Ok(Action::Submit) => break,
+                    Ok(Action::Cancel) => {
+                        let _ = editor.start(&mut *CONSOLE.lock());
                     }
+                    Ok(Action::Complete) => {
+                        let mut completer = ShellCompleter { registry: &REGISTRY, cwd: self.cwd() };
+                        let _ = editor.complete(&mut completer, &mut *CONSOLE.lock());
+                    }
+                    _ => (),
+                }
+            }
+
+            if let Err(e) = self.execute(editor.line()) {
+                kprintln!("{:1$}^", "", prefix.len() + e.column);
+                kprintln!("error: {}", e.kind);
+            }
+
+            editor.finish();
+
+            if let Some(status) = self.exit.take() {
+                return status;
+            }
+        }
+    }
+
+    /// Expands the variables in `words`, dropping unquoted words that expand
+    /// to nothing.
+    fn expand(&self, words: &[Word]) -> Vec<String> {
+        words
+            .iter()
+            .map(|word| (word.expand(&self.env), word.quoted))
+            .filter(|(arg, quoted)| *quoted || !arg.is_empty())
+            .map(|(arg, _)| arg)
+            .collect()
+    }
+
+    /// Runs the commands of `pipeline` one after the other, collecting the
+    /// output of each in memory to feed it to the next. Returns the exit
+    /// status of the last command.
+    fn run_pipeline(&mut self, pipeline: &Pipeline) -> u8 {
+        let mut piped = None;
+        let mut status = SUCCESS;
+
+        for (i, command) in pipeline.iter().enumerate() {
+            let last = i + 1 == pipeline.len();
+            status = match self.run_command(command, piped.take(), last) {
+                Ok((status, output)) => {
+                    piped = Some(output);
+                    status
+                }
+                Err(e) => {
+                    kprintln!("{}", e);
+                    piped = Some(Vec::new());
+                    FAILURE
+                }
+            };
+        }
+
+        status
+    }
+
+    /// Runs `command` with its redirections applied and returns its exit
+    /// status and collected output. Standard input is `piped`, or the console
+    /// for the first command of a pipeline. Standard output goes to the
+    /// console only for the `last` command; otherwise it is collected for the
+    /// next one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a redirection cannot be applied, in which case the
+    /// command is not run.
+    fn run_command(
+        &mut self,
+        command: &Command,
+        piped: Option<Vec<u8>>,
+        last: bool,
+    ) -> Result<(u8, Vec<u8>), CommandError> {
+        let mut stdin: Box<dyn io::Read> = match piped {
+            Some(data) => Box::new(io::Cursor::new(data)),
+            None => Box::new(ConsoleIn),
+        };
+
+        // Like other shells, `>` truncates its file before the command runs.
+        let mut redirected = None;
+        for redirect in &command.redirects {
+            let path = self.resolve(redirect.target.expand(&self.env));
+            match redirect.kind {
+                RedirectKind::Input => {
+                    stdin = self.open(&path).map_err(|e| fs::path_error(&path, e))?;
+                }
+                RedirectKind::Output | RedirectKind::Append => {
+                    let append = redirect.kind == RedirectKind::Append;
+                    self.tmp.write(&path, &[], append).map_err(|e| fs::path_error(&path, e))?;
+                    redirected = Some(path);
                 }
             }
+        }
 
-            kprint!("\r\n{}", prefix);
-            user_input.truncate(0);
+        let args = self.expand(&command.words);
+        let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+        let mut output = Vec::new();
+        let status = if last && redirected.is_none() {
+            self.run(&args, &mut Stdio { stdin: &mut *stdin, stdout: &mut ConsoleOut })
         } else {
-            if !read_char.is_ascii_graphic() && !read_char.is_ascii_whitespace() {
-                kprint!("\u{7}");
-            } else if user_input.is_full() {
-            } else {
-                kprint!("{}", read_char as char);
-                user_input.push(read_char).unwrap();
+            self.run(&args, &mut Stdio { stdin: &mut *stdin, stdout: &mut output })
+        };
+
+        if let Some(path) = redirected {
+            self.tmp.write(&path, &output, true).map_err(|e| fs::path_error(&path, e))?;
+            output.clear();
+        }
+
+        Ok((status, output))
+    }
+
+    /// Runs the command named by `args[0]` from the `REGISTRY` with `stdio`
+    /// as its input and output, printing any error to the console, and
+    /// returns its exit status.
+    pub fn run(&mut self, args: &[&str], stdio: &mut Stdio) -> u8 {
+        let name = match args.first() {
+            Some(name) => *name,
+            None => return SUCCESS,
+        };
+
+        let command = match REGISTRY.find(name) {
+            Some(command) => command,
+            None => {
+                kprint!("unknown command: {}", name);
+                match REGISTRY.suggest(name) {
+                    Some(suggestion) => kprintln!(" (did you mean `{}`?)", suggestion),
+                    None => kprintln!(),
+                }
+                return NOT_FOUND;
+            }
+        };
+
+        match command.run(args, self, stdio) {
+            Ok(()) => SUCCESS,
+            Err(CommandError::Usage) => {
+                kprintln!("usage: {}", command.usage());
+                USAGE
+            }
+            Err(CommandError::Exit(status)) => status,
+            Err(e) => {
+                kprintln!("{}: {}", name, e);
+                FAILURE
             }
         }
     }
 }
 
-fn echo_cmd(args: &[&str]) {
-    for arg in args.iter().skip(1) {
-        kprint!("{} ", arg);
+/// Starts a shell using `prefix` as the prefix for each line, first running
+/// the script at `autorun` if one is given. Running `exit` at the prompt
+/// starts over with a new shell. This function never returns.
+pub fn shell(prefix: &str, autorun: Option<&str>) -> ! {
+    let mut shell = Shell::new();
+    if let Some(path) = autorun {
+        shell.autorun(path);
     }
-}
 
-fn atag_cmd() {
-    for atag in Atags::get() {
-        kprintln!("{:#?}", atag);
+    loop {
+        let status = shell.interact(prefix);
+        kprintln!("exit {}: starting a new shell", status);
+        shell = Shell::new();
     }
 }
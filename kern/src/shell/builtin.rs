@@ -0,0 +1,146 @@
+use alloc::format;
+
+use pi::atags::Atags;
+
+use shim::io::Write;
+
+use super::command::{Builtin, Error, ShellCommand};
+use super::parse::is_var_name;
+use super::stdio::Stdio;
+use super::{Shell, REGISTRY, SUCCESS};
+
+/// Commands built into the shell.
+pub const COMMANDS: &[&dyn ShellCommand] = &[
+    &Builtin { name: "help", help: "list commands or describe one", usage: "help [command]", run: help },
+    &Builtin { name: "echo", help: "print the arguments", usage: "echo [arg]...", run: echo },
+    &Builtin { name: "atags", help: "print the ATAGS from the firmware", usage: "atags", run: atags },
+    &Builtin { name: "set", help: "set shell variables", usage: "set [NAME=VALUE]...", run: set },
+    &Builtin { name: "unset", help: "remove shell variables", usage: "unset NAME...", run: unset },
+    &Builtin { name: "env", help: "print the shell variables", usage: "env", run: env },
+    &Builtin { name: "source", help: "run the commands in a script", usage: "source <file>", run: source },
+    &Builtin { name: "exit", help: "end the script or start a new shell", usage: "exit [status]", run: exit },
+];
+
+/// Lists every registered command, or shows the usage of the one given.
+fn help(args: &[&str], _shell: &mut Shell, stdio: &mut Stdio) -> Result<(), Error> {
+    match args {
+        [_] => {
+            let width = REGISTRY.iter().map(|c| c.name().len()).max().unwrap_or(0);
+            for command in REGISTRY.iter() {
+                writeln!(stdio.stdout, "{:1$}  {2}", command.name(), width, command.help())?;
+            }
+        }
+        [_, name] => match REGISTRY.find(name) {
+            Some(command) => writeln!(stdio.stdout, "usage: {}\n{}", command.usage(), command.help())?,
+            None => return Err(Error::Failed(format!("no such command: {}", name))),
+        },
+        _ => return Err(Error::Usage),
+    }
+
+    Ok(())
+}
+
+fn echo(args: &[&str], _shell: &mut Shell, stdio: &mut Stdio) -> Result<(), Error> {
+    for (i, arg) in args.iter().skip(1).enumerate() {
+        if i > 0 {
+            stdio.stdout.write_all(b" ")?;
+        }
+        stdio.stdout.write_all(arg.as_bytes())?;
+    }
+
+    stdio.stdout.write_all(b"\n")?;
+    Ok(())
+}
+
+fn atags(args: &[&str], _shell: &mut Shell, stdio: &mut Stdio) -> Result<(), Error> {
+    if args.len() != 1 {
+        return Err(Error::Usage);
+    }
+
+    for atag in Atags::get() {
+        writeln!(stdio.stdout, "{:#?}", atag)?;
+    }
+
+    Ok(())
+}
+
+/// Sets each `NAME=VALUE` given. With no arguments, prints every variable.
+fn set(args: &[&str], shell: &mut Shell, stdio: &mut Stdio) -> Result<(), Error> {
+    if args.len() == 1 {
+        return env(args, shell, stdio);
+    }
+
+    for arg in &args[1..] {
+        let (name, value) = match arg.find('=') {
+            Some(i) => (&arg[..i], &arg[i + 1..]),
+            None => return Err(Error::Usage),
+        };
+
+        if !is_var_name(name) {
+            return Err(Error::Failed(format!("bad variable name: {}", name)));
+        }
+
+        shell.set_var(name, value);
+    }
+
+    Ok(())
+}
+
+fn unset(args: &[&str], shell: &mut Shell, _stdio: &mut Stdio) -> Result<(), Error> {
+    if args.len() < 2 {
+        return Err(Error::Usage);
+    }
+
+    for name in &args[1..] {
+        if !is_var_name(name) {
+            return Err(Error::Failed(format!("bad variable name: {}", name)));
+        }
+
+        shell.unset_var(name);
+    }
+
+    Ok(())
+}
+
+fn env(args: &[&str], shell: &mut Shell, stdio: &mut Stdio) -> Result<(), Error> {
+    if args.len() != 1 {
+        return Err(Error::Usage);
+    }
+
+    for (name, value) in shell.vars() {
+        writeln!(stdio.stdout, "{}={}", name, value)?;
+    }
+
+    Ok(())
+}
+
+/// Runs a script, exiting with the status of its last command.
+fn source(args: &[&str], shell: &mut Shell, _stdio: &mut Stdio) -> Result<(), Error> {
+    let path = match args {
+        [_, path] => shell.resolve(path),
+        _ => return Err(Error::Usage),
+    };
+
+    match shell.source(&path)? {
+        SUCCESS => Ok(()),
+        status => Err(Error::Exit(status)),
+    }
+}
+
+/// Stops the running script, or the shell at the prompt, with the status
+/// given or else that of the last command.
+fn exit(args: &[&str], shell: &mut Shell, _stdio: &mut Stdio) -> Result<(), Error> {
+    let status = match args {
+        [_] => shell.status(),
+        [_, status] => status
+            .parse()
+            .map_err(|_| Error::Failed(format!("bad exit status: {}", status)))?,
+        _ => return Err(Error::Usage),
+    };
+
+    shell.exit(status);
+    match status {
+        SUCCESS => Ok(()),
+        status => Err(Error::Exit(status)),
+    }
+}
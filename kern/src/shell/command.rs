@@ -0,0 +1,157 @@
+use alloc::string::String;
+use core::cmp::{max, min};
+use core::fmt;
+
+use shim::io;
+
+use super::stdio::Stdio;
+use super::Shell;
+
+/// Error returned by a `ShellCommand` that did not complete.
+#[derive(Debug)]
+pub enum Error {
+    /// The arguments were not understood; the shell prints the usage.
+    Usage,
+    /// An I/O operation failed.
+    Io(io::Error),
+    /// The command failed for the given reason.
+    Failed(String),
+    /// The command failed quietly with the given exit status, like `grep`
+    /// finding nothing.
+    Exit(u8),
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::Io(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Usage => f.write_str("invalid arguments"),
+            Error::Io(error) => write!(f, "{}", error),
+            Error::Failed(reason) => f.write_str(reason),
+            Error::Exit(status) => write!(f, "exit status {}", status),
+        }
+    }
+}
+
+/// A command that can be run from the shell.
+///
+/// Commands are found through a `Registry`. To add commands, implement this
+/// trait (or use `Builtin`), collect the commands in a slice and add the
+/// slice to `shell::REGISTRY`.
+pub trait ShellCommand: Sync {
+    /// The name the command is invoked by.
+    fn name(&self) -> &'static str;
+
+    /// A one-line description of the command, shown by `help`.
+    fn help(&self) -> &'static str;
+
+    /// The command's synopsis, e.g. `ls [-a] [-l] [dir]`.
+    fn usage(&self) -> &'static str;
+
+    /// Runs the command with the input and output in `stdio`. `args[0]` is
+    /// the command's name.
+    fn run(&self, args: &[&str], shell: &mut Shell, stdio: &mut Stdio) -> Result<(), Error>;
+}
+
+/// A `ShellCommand` implemented by a plain function.
+pub struct Builtin {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub usage: &'static str,
+    pub run: fn(&[&str], &mut Shell, &mut Stdio) -> Result<(), Error>,
+}
+
+impl ShellCommand for Builtin {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn help(&self) -> &'static str {
+        self.help
+    }
+
+    fn usage(&self) -> &'static str {
+        self.usage
+    }
+
+    fn run(&self, args: &[&str], shell: &mut Shell, stdio: &mut Stdio) -> Result<(), Error> {
+        (self.run)(args, shell, stdio)
+    }
+}
+
+/// The set of commands known to the shell, kept as a list of per-module
+/// slices so each module can export its own commands.
+pub struct Registry(&'static [&'static [&'static dyn ShellCommand]]);
+
+impl Registry {
+    /// Returns a registry of the commands in `groups`.
+    pub const fn new(groups: &'static [&'static [&'static dyn ShellCommand]]) -> Registry {
+        Registry(groups)
+    }
+
+    /// Returns an iterator over all registered commands.
+    pub fn iter(&self) -> impl Iterator<Item = &'static dyn ShellCommand> {
+        let groups = self.0;
+        groups.iter().flat_map(|group| group.iter()).cloned()
+    }
+
+    /// Returns the command named `name`, if any.
+    pub fn find(&self, name: &str) -> Option<&'static dyn ShellCommand> {
+        self.iter().find(|command| command.name() == name)
+    }
+
+    /// Returns the name of the registered command closest to `name`, if any
+    /// is close enough to be a likely typo.
+    pub fn suggest(&self, name: &str) -> Option<&'static str> {
+        let limit = min(2, name.len() / 2);
+        self.iter()
+            .map(|command| (edit_distance(name, command.name()), command.name()))
+            .filter(|&(distance, _)| distance <= limit)
+            .min_by_key(|&(distance, _)| distance)
+            .map(|(_, name)| name)
+    }
+}
+
+/// Returns the edit distance between `a` and `b`: the number of single-byte
+/// insertions, deletions, substitutions and swaps of adjacent bytes turning
+/// `a` into `b`. Counting swaps as one edit makes typos like `sl` for `ls`
+/// as close as a single wrong letter.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    const MAX: usize = 32;
+
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() > MAX || b.len() > MAX {
+        // Too long to be a typo of a command name; this bounds the distance.
+        return max(a.len(), b.len());
+    }
+
+    // `row[j]` is the distance between `a[..i]` and `b[..j]`; `prev` and
+    // `prev2` hold the rows for `i - 1` and `i - 2`.
+    let mut prev2 = [0; MAX + 1];
+    let mut prev = [0; MAX + 1];
+    for (j, cell) in prev.iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        let mut row = [0; MAX + 1];
+        row[0] = i;
+        for j in 1..=b.len() {
+            let cost = (a[i - 1] != b[j - 1]) as usize;
+            row[j] = min(prev[j - 1] + cost, min(prev[j], row[j - 1]) + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = min(row[j], prev2[j - 2] + 1);
+            }
+        }
+
+        prev2 = prev;
+        prev = row;
+    }
+
+    prev[b.len()]
+}
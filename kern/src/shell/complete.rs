@@ -0,0 +1,90 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use shim::path::Path;
+
+use fat32::traits::FileSystem;
+use fat32::traits::{Dir, Entry, Metadata};
+
+use crate::FILESYSTEM;
+
+use super::command::Registry;
+use super::line::Completer;
+
+/// Completes command names for the first word of each command on a line and
+/// paths on the mounted file system, if there is one, for every other word.
+pub struct ShellCompleter<'a> {
+    /// The commands the shell understands.
+    pub registry: &'a Registry,
+    /// Directory that relative paths are completed from.
+    pub cwd: &'a Path,
+}
+
+impl<'a> Completer for ShellCompleter<'a> {
+    fn complete(&mut self, before: &str, word: &str) -> Vec<String> {
+        if is_command_position(before) {
+            command_candidates(self.registry, word)
+        } else if FILESYSTEM.is_mounted() {
+            path_candidates(&FILESYSTEM, self.cwd, word)
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Returns `true` if a word following `before` names a command: it starts
+/// the line or follows a `;`, `|`, `&&` or `||`.
+pub fn is_command_position(before: &str) -> bool {
+    let before = before.trim_end();
+    before.is_empty() || before.ends_with(';') || before.ends_with('|') || before.ends_with("&&")
+}
+
+/// Returns the names of the commands in `registry` that start with `word`.
+pub fn command_candidates(registry: &Registry, word: &str) -> Vec<String> {
+    registry
+        .iter()
+        .map(|command| command.name())
+        .filter(|name| name.starts_with(word))
+        .map(String::from)
+        .collect()
+}
+
+/// Returns `true` if `name` starts with `prefix`, ignoring ASCII case as FAT
+/// does.
+fn matches_prefix(name: &str, prefix: &str) -> bool {
+    name.len() >= prefix.len()
+        && name.as_bytes()[..prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+}
+
+/// Returns the entries of the directory named by `word` up to its last `/`
+/// whose names start with the rest of `word`. Relative paths are looked up
+/// from `cwd`. Directories get a trailing `/` so completion can continue into
+/// them. Hidden entries, `.` and `..` are only offered once the name being
+/// completed starts with a `.`.
+pub fn path_candidates<F: FileSystem>(fs: F, cwd: &Path, word: &str) -> Vec<String> {
+    let (dir, prefix) = match word.rfind('/') {
+        Some(i) => word.split_at(i + 1),
+        None => ("", word),
+    };
+
+    let entries = match fs.open_dir(cwd.join(dir)).and_then(|d| d.entries()) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let show_hidden = prefix.starts_with('.');
+    entries
+        .filter(|entry| matches_prefix(entry.name(), prefix))
+        .filter(|entry| {
+            show_hidden || !(entry.metadata().hidden() || entry.name().starts_with('.'))
+        })
+        .map(|entry| {
+            let mut candidate = String::from(dir);
+            candidate.push_str(entry.name());
+            if entry.is_dir() {
+                candidate.push('/');
+            }
+            candidate
+        })
+        .collect()
+}
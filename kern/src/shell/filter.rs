@@ -0,0 +1,182 @@
+use alloc::vec::Vec;
+
+use shim::io::{self, Read, Write};
+
+use super::command::{Builtin, Error, ShellCommand};
+use super::fs::path_error;
+use super::hexdump;
+use super::mem;
+use super::stdio::Stdio;
+use super::Shell;
+
+/// Commands that read files or standard input and write what they find.
+pub const COMMANDS: &[&dyn ShellCommand] = &[
+    &Builtin { name: "grep", help: "print lines containing a pattern", usage: "grep [-i] [-v] [-c] <pattern> [file]...", run: grep },
+    &Builtin { name: "head", help: "print the first lines of the input", usage: "head [-n <lines>] [file]...", run: head },
+    &Builtin { name: "wc", help: "count lines, words and bytes", usage: "wc [file]...", run: wc },
+    &Builtin {
+        name: "hexdump",
+        help: "show the input or memory in hex and ASCII",
+        usage: "hexdump [file]... | hexdump -m <addr> <len>",
+        run: dump,
+    },
+];
+
+/// Calls `f` with the contents of each file in `files` in turn, or with all of
+/// standard input if there are none.
+fn for_each_input<F>(files: &[&str], shell: &Shell, stdio: &mut Stdio, mut f: F) -> Result<(), Error>
+where
+    F: FnMut(&[u8], &mut dyn io::Write) -> io::Result<()>,
+{
+    let mut data = Vec::new();
+    if files.is_empty() {
+        stdio.stdin.read_to_end(&mut data)?;
+        f(&data, stdio.stdout)?;
+    }
+
+    for file in files {
+        let path = shell.resolve(file);
+        data.clear();
+        shell
+            .open(&path)
+            .and_then(|mut input| input.read_to_end(&mut data))
+            .map_err(|e| path_error(&path, e))?;
+        f(&data, stdio.stdout)?;
+    }
+
+    Ok(())
+}
+
+/// Returns an iterator over the lines of `data`, each with its `\n`.
+pub fn lines(data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut rest = data;
+    core::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+
+        let len = rest.iter().position(|&b| b == b'\n').map_or(rest.len(), |i| i + 1);
+        let (line, next) = rest.split_at(len);
+        rest = next;
+        Some(line)
+    })
+}
+
+/// Returns `true` if `pattern` occurs in `line`, ignoring ASCII case if
+/// `ignore_case` is set.
+pub fn contains(line: &[u8], pattern: &[u8], ignore_case: bool) -> bool {
+    pattern.is_empty()
+        || line.windows(pattern.len()).any(|window| {
+            if ignore_case {
+                window.eq_ignore_ascii_case(pattern)
+            } else {
+                window == pattern
+            }
+        })
+}
+
+/// Returns `line` without its trailing `\n`, if any.
+fn trim_newline(line: &[u8]) -> &[u8] {
+    match line.last() {
+        Some(b'\n') => &line[..line.len() - 1],
+        _ => line,
+    }
+}
+
+/// Prints the lines that contain the pattern. Fails without a message if no
+/// line matches, so `grep` can be used with `&&` and `||`.
+fn grep(args: &[&str], shell: &mut Shell, stdio: &mut Stdio) -> Result<(), Error> {
+    let mut ignore_case = false;
+    let mut invert = false;
+    let mut count = false;
+
+    let mut args = args[1..].iter();
+    let pattern = loop {
+        match args.next() {
+            Some(&"-i") => ignore_case = true,
+            Some(&"-v") => invert = true,
+            Some(&"-c") => count = true,
+            Some(pattern) => break pattern.as_bytes(),
+            None => return Err(Error::Usage),
+        }
+    };
+
+    let files: Vec<&str> = args.cloned().collect();
+    let mut matches = 0;
+    for_each_input(&files, shell, stdio, |data, out| {
+        for line in lines(data) {
+            let text = trim_newline(line);
+            if contains(text, pattern, ignore_case) != invert {
+                matches += 1;
+                if !count {
+                    out.write_all(text)?;
+                    out.write_all(b"\n")?;
+                }
+            }
+        }
+        Ok(())
+    })?;
+
+    if count {
+        writeln!(stdio.stdout, "{}", matches)?;
+    }
+
+    match matches {
+        0 => Err(Error::Exit(super::FAILURE)),
+        _ => Ok(()),
+    }
+}
+
+/// Prints the first lines of the input, ten unless `-n` says otherwise.
+fn head(args: &[&str], shell: &mut Shell, stdio: &mut Stdio) -> Result<(), Error> {
+    let (mut remaining, files) = if args.len() >= 3 && args[1] == "-n" {
+        (args[2].parse::<usize>().map_err(|_| Error::Usage)?, &args[3..])
+    } else {
+        (10, &args[1..])
+    };
+
+    for_each_input(files, shell, stdio, |data, out| {
+        for line in lines(data).take(remaining) {
+            out.write_all(line)?;
+            remaining -= 1;
+        }
+        Ok(())
+    })
+}
+
+/// Prints the number of lines, words and bytes of each input.
+fn wc(args: &[&str], shell: &mut Shell, stdio: &mut Stdio) -> Result<(), Error> {
+    let files = &args[1..];
+    let mut names = files.iter();
+    for_each_input(files, shell, stdio, |data, out| {
+        let lines = data.iter().filter(|&&b| b == b'\n').count();
+        let words = data
+            .split(|b| b.is_ascii_whitespace())
+            .filter(|word| !word.is_empty())
+            .count();
+
+        write!(out, "{:>7} {:>7} {:>7}", lines, words, data.len())?;
+        match names.next() {
+            Some(name) => writeln!(out, " {}", name),
+            None => writeln!(out),
+        }
+    })
+}
+
+/// Shows the input as a hexdump, numbering each input from offset zero, or
+/// with `-m` a range of physical memory.
+fn dump(args: &[&str], shell: &mut Shell, stdio: &mut Stdio) -> Result<(), Error> {
+    if args.get(1) == Some(&"-m") {
+        return match args {
+            [_, _, addr, len] => mem::dump(addr, len, stdio),
+            _ => Err(Error::Usage),
+        };
+    }
+
+    for_each_input(&args[1..], shell, stdio, |data, out| {
+        for line in hexdump::lines(0, data) {
+            writeln!(out, "{}", line)?;
+        }
+        Ok(())
+    })
+}
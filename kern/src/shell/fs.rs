@@ -0,0 +1,266 @@
+use alloc::format;
+use core::fmt;
+
+use shim::io::{self, Read, Write};
+use shim::path::{Component, Path, PathBuf};
+
+use fat32::traits::FileSystem;
+use fat32::traits::{Dir, Entry, File, Metadata, Timestamp};
+
+use crate::FILESYSTEM;
+
+use super::command::{Builtin, Error, ShellCommand};
+use super::hexdump;
+use super::stdio::Stdio;
+use super::tmp::TmpFs;
+use super::Shell;
+
+/// Returns `path` with every `.` and `..` component resolved. `..` at the
+/// root stays at the root. `path` must be absolute.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::from("/");
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => normalized.push(name),
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            _ => (),
+        }
+    }
+
+    normalized
+}
+
+/// Returns `true` if `bytes` look like text: anything but control characters
+/// other than whitespace and escape sequences.
+pub fn is_text(bytes: &[u8]) -> bool {
+    bytes.iter().all(|&b| match b {
+        b'\t' | b'\n' | b'\r' | 0x0c | 0x1b => true,
+        0x00..=0x1f | 0x7f => false,
+        _ => true,
+    })
+}
+
+/// Formats a `Timestamp` as `YYYY-MM-DD HH:MM:SS`.
+struct DateTime<T: Timestamp>(T);
+
+impl<T: Timestamp> fmt::Display for DateTime<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let t = &self.0;
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            t.year(),
+            t.month(),
+            t.day(),
+            t.hour(),
+            t.minute(),
+            t.second()
+        )
+    }
+}
+
+/// The file system commands.
+pub const COMMANDS: &[&dyn ShellCommand] = &[
+    &Builtin { name: "pwd", help: "print the working directory", usage: "pwd", run: pwd },
+    &Builtin { name: "cd", help: "change the working directory", usage: "cd [dir]", run: cd },
+    &Builtin { name: "ls", help: "list a directory", usage: "ls [-a] [-l] [dir]", run: ls },
+    &Builtin { name: "cat", help: "print files or standard input", usage: "cat [file]...", run: cat },
+    &Builtin { name: "rm", help: "remove files from /tmp", usage: "rm <file>...", run: rm },
+];
+
+/// Returns an error naming `path` for the I/O error `error`.
+pub fn path_error(path: &Path, error: io::Error) -> Error {
+    Error::Failed(format!("{}: {}", path.display(), error))
+}
+
+/// Prints the working directory.
+fn pwd(args: &[&str], shell: &mut Shell, stdio: &mut Stdio) -> Result<(), Error> {
+    if args.len() != 1 {
+        return Err(Error::Usage);
+    }
+
+    writeln!(stdio.stdout, "{}", shell.cwd().display())?;
+    Ok(())
+}
+
+/// Changes the working directory to the given directory, or to `/` if none is
+/// given.
+fn cd(args: &[&str], shell: &mut Shell, _stdio: &mut Stdio) -> Result<(), Error> {
+    let path = match args {
+        [_] => PathBuf::from("/"),
+        [_, dir] => shell.resolve(dir),
+        _ => return Err(Error::Usage),
+    };
+
+    if !TmpFs::is_dir(&path) {
+        (&FILESYSTEM).open_dir(&path).map_err(|e| path_error(&path, e))?;
+    }
+
+    shell.set_cwd(path);
+    Ok(())
+}
+
+/// Lists a directory, or the working directory if none is given. `-a` also
+/// shows hidden entries and `-l` shows attributes, sizes and modification
+/// times.
+fn ls(args: &[&str], shell: &mut Shell, stdio: &mut Stdio) -> Result<(), Error> {
+    let mut all = false;
+    let mut long = false;
+    let mut dir = None;
+
+    for arg in &args[1..] {
+        if arg.starts_with('-') && arg.len() > 1 {
+            for flag in arg[1..].chars() {
+                match flag {
+                    'a' => all = true,
+                    'l' => long = true,
+                    _ => return Err(Error::Usage),
+                }
+            }
+        } else if dir.is_none() {
+            dir = Some(*arg);
+        } else {
+            return Err(Error::Usage);
+        }
+    }
+
+    let path = shell.resolve(dir.unwrap_or("."));
+    let out = &mut *stdio.stdout;
+
+    if TmpFs::is_dir(&path) {
+        for (name, size) in shell.tmp().files() {
+            if long {
+                write!(out, "-w- {:>10} {:19}  ", size, "")?;
+            }
+            writeln!(out, "{}", name)?;
+        }
+        return Ok(());
+    }
+
+    if let Some(data) = shell.tmp().get(&path) {
+        if long {
+            write!(out, "-w- {:>10} {:19}  ", data.len(), "")?;
+        }
+        writeln!(out, "{}", path.display())?;
+        return Ok(());
+    }
+
+    let entry = (&FILESYSTEM).open(&path).map_err(|e| path_error(&path, e))?;
+    let entries = match entry.as_dir() {
+        Some(dir) => dir.entries().map_err(|e| path_error(&path, e))?,
+        None => {
+            print_entry(out, &entry, long)?;
+            return Ok(());
+        }
+    };
+
+    for entry in entries {
+        let hidden = entry.metadata().hidden() || entry.name().starts_with('.');
+        if all || !hidden {
+            print_entry(out, &entry, long)?;
+        }
+    }
+
+    // `/tmp` lives in memory, not on the SD card.
+    if path == Path::new("/") {
+        if long {
+            write!(out, "dw- {:>10} {:19}  ", 0, "")?;
+        }
+        writeln!(out, "tmp/")?;
+    }
+
+    Ok(())
+}
+
+fn print_entry<E: Entry>(out: &mut dyn io::Write, entry: &E, long: bool) -> io::Result<()> {
+    if long {
+        let metadata = entry.metadata();
+        write!(
+            out,
+            "{}{}{} {:>10} {}  ",
+            if entry.is_dir() { 'd' } else { '-' },
+            if metadata.read_only() { 'r' } else { 'w' },
+            if metadata.hidden() { 'h' } else { '-' },
+            entry.as_file().map(|f| f.size()).unwrap_or(0),
+            DateTime(metadata.modified())
+        )?;
+    }
+
+    writeln!(out, "{}{}", entry.name(), if entry.is_dir() { "/" } else { "" })
+}
+
+/// Prints the contents of each file given, or standard input if none is.
+/// Input that does not look like text is shown as a hexdump instead.
+fn cat(args: &[&str], shell: &mut Shell, stdio: &mut Stdio) -> Result<(), Error> {
+    if args.len() == 1 {
+        cat_file(stdio.stdin, stdio.stdout)?;
+        return Ok(());
+    }
+
+    for arg in &args[1..] {
+        let path = shell.resolve(arg);
+        let mut file = shell.open(&path).map_err(|e| path_error(&path, e))?;
+        cat_file(&mut *file, stdio.stdout).map_err(|e| path_error(&path, e))?;
+    }
+
+    Ok(())
+}
+
+/// Removes files from `/tmp`.
+fn rm(args: &[&str], shell: &mut Shell, _stdio: &mut Stdio) -> Result<(), Error> {
+    if args.len() < 2 {
+        return Err(Error::Usage);
+    }
+
+    for arg in &args[1..] {
+        let path = shell.resolve(arg);
+        if !TmpFs::is_file(&path) {
+            return Err(Error::Failed(format!("{}: read-only file system", path.display())));
+        }
+        if !shell.tmp_mut().remove(&path) {
+            return Err(Error::Failed(format!("{}: no such file", path.display())));
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads from `file` until `buf` is full or the file ends. Returns the number
+/// of bytes read.
+pub fn fill(file: &mut dyn Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match file.read(&mut buf[len..])? {
+            0 => break,
+            n => len += n,
+        }
+    }
+
+    Ok(len)
+}
+
+fn cat_file(file: &mut dyn Read, out: &mut dyn io::Write) -> io::Result<()> {
+    let mut buf = [0u8; 512];
+    let mut offset = 0;
+    let mut text = None;
+
+    loop {
+        let n = fill(file, &mut buf)?;
+        if n == 0 {
+            return Ok(());
+        }
+
+        // Decide from the first block so a file is never shown half as text.
+        if *text.get_or_insert_with(|| is_text(&buf[..n])) {
+            out.write_all(&buf[..n])?;
+        } else {
+            for line in hexdump::lines(offset, &buf[..n]) {
+                writeln!(out, "{}", line)?;
+            }
+        }
+
+        offset += n;
+    }
+}
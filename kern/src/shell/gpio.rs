@@ -0,0 +1,129 @@
+use alloc::format;
+use core::time::Duration;
+
+use pi::gpio::{Function, Gpio, Pull, PIN_COUNT};
+
+use shim::io::Write;
+
+use crate::TIMERS;
+
+use super::command::{Builtin, Error, ShellCommand};
+use super::mem::parse_number;
+use super::stdio::Stdio;
+use super::Shell;
+
+/// Commands that drive the GPIO pins.
+pub const COMMANDS: &[&dyn ShellCommand] = &[&Builtin {
+    name: "gpio",
+    help: "show or change GPIO pins",
+    usage: "gpio <pin> [in|out|alt0..alt5|set|clear|toggle|read|pull up|down|off] \
+            | gpio blink <pin> <ms-on-and-off> <count>",
+    run: gpio,
+}];
+
+/// Pins the kernel itself depends on, which the shell refuses to change.
+const RESERVED: &[(u8, &str)] = &[
+    (14, "the console's TX line"),
+    (15, "the console's RX line"),
+    (48, "the SD card"),
+    (49, "the SD card"),
+    (50, "the SD card"),
+    (51, "the SD card"),
+    (52, "the SD card"),
+    (53, "the SD card"),
+];
+
+/// Something the `gpio` command can do to a pin.
+#[derive(Debug, PartialEq)]
+pub enum Op {
+    Show,
+    Function(Function),
+    Set,
+    Clear,
+    Toggle,
+    Read,
+    Pull(Pull),
+    Blink { ms: u64, count: u64 },
+}
+
+/// The names of the pin functions, in the form the `gpio` command takes.
+const FUNCTIONS: &[(&str, Function)] = &[
+    ("in", Function::Input),
+    ("out", Function::Output),
+    ("alt0", Function::Alt0),
+    ("alt1", Function::Alt1),
+    ("alt2", Function::Alt2),
+    ("alt3", Function::Alt3),
+    ("alt4", Function::Alt4),
+    ("alt5", Function::Alt5),
+];
+
+/// Returns the name `gpio` uses for `function`.
+pub fn function_name(function: Function) -> &'static str {
+    FUNCTIONS.iter().find(|(_, f)| *f == function).map_or("?", |&(name, _)| name)
+}
+
+/// Parses the arguments of `gpio` after its name into a pin number and what
+/// to do with it. Returns `None` if they are not understood.
+pub fn parse(args: &[&str]) -> Option<(u8, Op)> {
+    let pin = |arg: &str| parse_number(arg).filter(|&pin| pin < PIN_COUNT as u64).map(|pin| pin as u8);
+
+    let (pin, op) = match args {
+        ["blink", p, ms, count] => {
+            (pin(p)?, Op::Blink { ms: parse_number(ms)?, count: parse_number(count)? })
+        }
+        [p] => (pin(p)?, Op::Show),
+        [p, "set"] => (pin(p)?, Op::Set),
+        [p, "clear"] => (pin(p)?, Op::Clear),
+        [p, "toggle"] => (pin(p)?, Op::Toggle),
+        [p, "read"] => (pin(p)?, Op::Read),
+        [p, "pull", "up"] => (pin(p)?, Op::Pull(Pull::Up)),
+        [p, "pull", "down"] => (pin(p)?, Op::Pull(Pull::Down)),
+        [p, "pull", "off"] => (pin(p)?, Op::Pull(Pull::Off)),
+        [p, function] => {
+            let (_, function) = FUNCTIONS.iter().find(|(name, _)| name == function)?;
+            (pin(p)?, Op::Function(*function))
+        }
+        _ => return None,
+    };
+
+    Some((pin, op))
+}
+
+/// Shows or changes a GPIO pin. `blink` turns the pin on and then off
+/// `count` times, keeping it on for `ms` milliseconds and then off for as
+/// long.
+fn gpio(args: &[&str], _shell: &mut Shell, stdio: &mut Stdio) -> Result<(), Error> {
+    let (pin, op) = parse(&args[1..]).ok_or(Error::Usage)?;
+    if op != Op::Show && op != Op::Read {
+        if let Some((_, user)) = RESERVED.iter().find(|(reserved, _)| *reserved == pin) {
+            return Err(Error::Failed(format!("pin {} is used by {}", pin, user)));
+        }
+    }
+
+    let mut gpio = Gpio::new(pin).into_dynamic();
+    match op {
+        Op::Show => {
+            let level = if gpio.level() { "high" } else { "low" };
+            writeln!(stdio.stdout, "pin {}: {}, {}", pin, function_name(gpio.function()), level)?;
+        }
+        Op::Function(function) => gpio.set_function(function),
+        Op::Set => gpio.set(),
+        Op::Clear => gpio.clear(),
+        Op::Toggle => gpio.toggle(),
+        Op::Read => writeln!(stdio.stdout, "{}", gpio.level() as u8)?,
+        Op::Pull(pull) => gpio.set_pull(pull),
+        Op::Blink { ms, count } => {
+            let phase = Duration::from_millis(ms);
+            gpio.set_function(Function::Output);
+            for _ in 0..count {
+                gpio.set();
+                TIMERS.sleep(phase);
+                gpio.clear();
+                TIMERS.sleep(phase);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use core::fmt;
+
+/// Number of bytes shown on each line of a hexdump.
+pub const LINE_BYTES: usize = 16;
+
+/// One line of a canonical hexdump: the offset, up to `LINE_BYTES` bytes in
+/// hex and the same bytes as ASCII, with `.` for anything unprintable.
+///
+/// ```text
+/// 00000010  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a        |Hello, world!.|
+/// ```
+pub struct HexLine<'a> {
+    pub offset: usize,
+    pub bytes: &'a [u8],
+}
+
+impl<'a> fmt::Display for HexLine<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:08x} ", self.offset)?;
+        for i in 0..LINE_BYTES {
+            if i % 8 == 0 {
+                f.write_str(" ")?;
+            }
+            match self.bytes.get(i) {
+                Some(byte) => write!(f, "{:02x} ", byte)?,
+                None => f.write_str("   ")?,
+            }
+        }
+
+        f.write_str(" |")?;
+        for &byte in self.bytes.iter().take(LINE_BYTES) {
+            let c = match byte {
+                0x20..=0x7e => byte as char,
+                _ => '.',
+            };
+            write!(f, "{}", c)?;
+        }
+        f.write_str("|")
+    }
+}
+
+/// Returns an iterator over the hexdump lines of `bytes`, numbering them from
+/// `offset`.
+pub fn lines<'a>(offset: usize, bytes: &'a [u8]) -> impl Iterator<Item = HexLine<'a>> + 'a {
+    bytes
+        .chunks(LINE_BYTES)
+        .enumerate()
+        .map(move |(i, bytes)| HexLine { offset: offset + i * LINE_BYTES, bytes })
+}
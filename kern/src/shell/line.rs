@@ -0,0 +1,608 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::str;
+
+use shim::io;
+
+/// Maximum length of a line in bytes.
+pub const LINE_LEN: usize = 512;
+
+/// Number of previous lines kept in the history.
+pub const HISTORY_LEN: usize = 16;
+
+/// ASCII bell, written when a key cannot be handled.
+const BELL: &[u8] = b"\x07";
+
+/// Width of the terminal assumed when listing completions.
+const TERM_WIDTH: usize = 80;
+
+/// A key decoded from the console's byte stream.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Key {
+    /// A printable ASCII character.
+    Char(u8),
+    Enter,
+    Backspace,
+    Delete,
+    Tab,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    /// A control character, given as the lowercase letter: `Ctrl(b'a')` is
+    /// Ctrl-A.
+    Ctrl(u8),
+    /// An escape sequence we do not understand.
+    Unknown,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum DecoderState {
+    Ground,
+    /// Saw `ESC`.
+    Escape,
+    /// Saw `ESC [` followed by the digits accumulated in `param`.
+    Csi,
+    /// Saw `ESC O`.
+    Ss3,
+}
+
+/// Turns the bytes of ANSI escape sequences sent by terminals into `Key`s.
+#[derive(Debug)]
+pub struct Decoder {
+    state: DecoderState,
+    param: u8,
+    last_was_cr: bool,
+}
+
+impl Decoder {
+    /// Returns a new decoder waiting for the start of a key.
+    pub const fn new() -> Decoder {
+        Decoder { state: DecoderState::Ground, param: 0, last_was_cr: false }
+    }
+
+    /// Feeds `byte` into the decoder. Returns `Some(key)` once `byte`
+    /// completes a key and `None` if more bytes are needed.
+    pub fn feed(&mut self, byte: u8) -> Option<Key> {
+        let last_was_cr = self.last_was_cr;
+        self.last_was_cr = false;
+
+        match self.state {
+            DecoderState::Ground => match byte {
+                0x1b => {
+                    self.state = DecoderState::Escape;
+                    None
+                }
+                b'\r' => {
+                    self.last_was_cr = true;
+                    Some(Key::Enter)
+                }
+                // Terminals configured to send CRLF would otherwise submit
+                // every line twice.
+                b'\n' if last_was_cr => None,
+                b'\n' => Some(Key::Enter),
+                b'\t' => Some(Key::Tab),
+                0x08 | 0x7f => Some(Key::Backspace),
+                0x01..=0x1a => Some(Key::Ctrl(byte - 1 + b'a')),
+                0x20..=0x7e => Some(Key::Char(byte)),
+                _ => Some(Key::Unknown),
+            },
+            DecoderState::Escape => match byte {
+                b'[' => {
+                    self.state = DecoderState::Csi;
+                    self.param = 0;
+                    None
+                }
+                b'O' => {
+                    self.state = DecoderState::Ss3;
+                    None
+                }
+                _ => {
+                    self.state = DecoderState::Ground;
+                    Some(Key::Unknown)
+                }
+            },
+            DecoderState::Csi => match byte {
+                b'0'..=b'9' => {
+                    self.param = self.param.saturating_mul(10).saturating_add(byte - b'0');
+                    None
+                }
+                // Modifier parameters (`ESC [ 1 ; 5 C`) are ignored.
+                b';' => None,
+                0x40..=0x7e => {
+                    self.state = DecoderState::Ground;
+                    Some(match (byte, self.param) {
+                        (b'A', _) => Key::Up,
+                        (b'B', _) => Key::Down,
+                        (b'C', _) => Key::Right,
+                        (b'D', _) => Key::Left,
+                        (b'H', _) => Key::Home,
+                        (b'F', _) => Key::End,
+                        (b'~', 1) | (b'~', 7) => Key::Home,
+                        (b'~', 4) | (b'~', 8) => Key::End,
+                        (b'~', 3) => Key::Delete,
+                        _ => Key::Unknown,
+                    })
+                }
+                _ => {
+                    self.state = DecoderState::Ground;
+                    Some(Key::Unknown)
+                }
+            },
+            DecoderState::Ss3 => {
+                self.state = DecoderState::Ground;
+                Some(match byte {
+                    b'A' => Key::Up,
+                    b'B' => Key::Down,
+                    b'C' => Key::Right,
+                    b'D' => Key::Left,
+                    b'H' => Key::Home,
+                    b'F' => Key::End,
+                    _ => Key::Unknown,
+                })
+            }
+        }
+    }
+}
+
+/// A ring buffer of the last `HISTORY_LEN` lines entered.
+pub struct History {
+    lines: [[u8; LINE_LEN]; HISTORY_LEN],
+    lens: [usize; HISTORY_LEN],
+    next: usize,
+    count: usize,
+}
+
+impl History {
+    /// Returns an empty history.
+    pub const fn new() -> History {
+        History {
+            lines: [[0; LINE_LEN]; HISTORY_LEN],
+            lens: [0; HISTORY_LEN],
+            next: 0,
+            count: 0,
+        }
+    }
+
+    /// Returns the number of lines in the history.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns `true` if the history is empty.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Adds `line` as the most recent entry, dropping the oldest entry if the
+    /// history is full. Empty lines and repeats of the most recent entry are
+    /// not recorded.
+    pub fn push(&mut self, line: &[u8]) {
+        if line.is_empty() || self.get(0) == Some(line) {
+            return;
+        }
+
+        let len = core::cmp::min(line.len(), LINE_LEN);
+        self.lines[self.next][..len].copy_from_slice(&line[..len]);
+        self.lens[self.next] = len;
+        self.next = (self.next + 1) % HISTORY_LEN;
+        self.count = core::cmp::min(self.count + 1, HISTORY_LEN);
+    }
+
+    /// Returns the entry `age` lines back: `get(0)` is the most recent line.
+    pub fn get(&self, age: usize) -> Option<&[u8]> {
+        if age >= self.count {
+            return None;
+        }
+
+        let index = (self.next + HISTORY_LEN - 1 - age) % HISTORY_LEN;
+        Some(&self.lines[index][..self.lens[index]])
+    }
+}
+
+/// What the caller of `LineEditor::feed()` should do next.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Action {
+    /// Keep feeding bytes.
+    Continue,
+    /// The user pressed Enter; the line is available from `line()`.
+    Submit,
+    /// The user pressed Ctrl-C; the line was discarded.
+    Cancel,
+    /// The user pressed Tab; call `complete()` to complete the word before
+    /// the cursor.
+    Complete,
+}
+
+/// A source of completions for the word before the cursor.
+pub trait Completer {
+    /// Returns the candidates for `word`, given the part of the line `before`
+    /// it. Each candidate replaces `word` in full. A candidate ending in `/`
+    /// names a directory and is not followed by a space once inserted.
+    fn complete(&mut self, before: &str, word: &str) -> Vec<String>;
+}
+
+/// An interactive line editor for the console.
+///
+/// The editor keeps the line being edited, the cursor position and the
+/// history in fixed-size buffers, so it never allocates. Output is written to
+/// any `io::Write`, which keeps the editing logic testable on the host.
+pub struct LineEditor<'a> {
+    prompt: &'a str,
+    buf: [u8; LINE_LEN],
+    len: usize,
+    cursor: usize,
+    decoder: Decoder,
+    history: History,
+    /// How far back in the history we are, if browsing it.
+    browsing: Option<usize>,
+    /// The line that was being edited before browsing started.
+    saved: [u8; LINE_LEN],
+    saved_len: usize,
+    /// Number of Tabs pressed in a row without completing anything.
+    tabs: usize,
+}
+
+impl<'a> LineEditor<'a> {
+    /// Returns a new line editor that shows `prompt` before every line.
+    pub fn new(prompt: &'a str) -> LineEditor<'a> {
+        LineEditor {
+            prompt,
+            buf: [0; LINE_LEN],
+            len: 0,
+            cursor: 0,
+            decoder: Decoder::new(),
+            history: History::new(),
+            browsing: None,
+            saved: [0; LINE_LEN],
+            saved_len: 0,
+            tabs: 0,
+        }
+    }
+
+    /// Returns the line being edited.
+    pub fn line(&self) -> &str {
+        // Only printable ASCII is ever inserted into `buf`.
+        str::from_utf8(&self.buf[..self.len]).unwrap_or("")
+    }
+
+    /// Returns the cursor's position as a byte offset into `line()`.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Returns the history of submitted lines.
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Clears the line and writes the prompt for a new one.
+    pub fn start<W: io::Write>(&mut self, out: &mut W) -> io::Result<()> {
+        self.len = 0;
+        self.cursor = 0;
+        self.browsing = None;
+        self.tabs = 0;
+        out.write_all(self.prompt.as_bytes())
+    }
+
+    /// Records the submitted line in the history. Call this once the line
+    /// returned by a `Submit` action has been handled.
+    pub fn finish(&mut self) {
+        self.history.push(&self.buf[..self.len]);
+        self.len = 0;
+        self.cursor = 0;
+        self.browsing = None;
+        self.tabs = 0;
+    }
+
+    /// Feeds a byte read from the console into the editor, echoing any changes
+    /// to `out`.
+    pub fn feed<W: io::Write>(&mut self, byte: u8, out: &mut W) -> io::Result<Action> {
+        let key = match self.decoder.feed(byte) {
+            Some(key) => key,
+            None => return Ok(Action::Continue),
+        };
+
+        self.tabs = match key {
+            Key::Tab => self.tabs + 1,
+            _ => 0,
+        };
+
+        match key {
+            Key::Char(c) => self.insert(&[c], out)?,
+            Key::Tab => return Ok(Action::Complete),
+            Key::Enter => {
+                out.write_all(b"\r\n")?;
+                return Ok(Action::Submit);
+            }
+            Key::Ctrl(b'c') => {
+                out.write_all(b"^C\r\n")?;
+                self.len = 0;
+                self.cursor = 0;
+                self.browsing = None;
+                return Ok(Action::Cancel);
+            }
+            Key::Backspace => {
+                if self.cursor == 0 {
+                    out.write_all(BELL)?;
+                } else {
+                    self.move_left(1, out)?;
+                    self.delete(self.cursor, self.cursor + 1, out)?;
+                }
+            }
+            Key::Delete | Key::Ctrl(b'd') => {
+                if self.cursor == self.len {
+                    out.write_all(BELL)?;
+                } else {
+                    self.delete(self.cursor, self.cursor + 1, out)?;
+                }
+            }
+            Key::Left | Key::Ctrl(b'b') => {
+                if self.cursor == 0 {
+                    out.write_all(BELL)?;
+                } else {
+                    self.move_left(1, out)?;
+                }
+            }
+            Key::Right | Key::Ctrl(b'f') => {
+                if self.cursor == self.len {
+                    out.write_all(BELL)?;
+                } else {
+                    self.move_right(1, out)?;
+                }
+            }
+            Key::Home | Key::Ctrl(b'a') => self.move_left(self.cursor, out)?,
+            Key::End | Key::Ctrl(b'e') => self.move_right(self.len - self.cursor, out)?,
+            Key::Ctrl(b'u') => {
+                let end = self.cursor;
+                self.move_left(end, out)?;
+                self.delete(0, end, out)?;
+            }
+            Key::Ctrl(b'k') => self.delete(self.cursor, self.len, out)?,
+            Key::Ctrl(b'w') => {
+                let end = self.cursor;
+                let start = self.word_start(end);
+                self.move_left(end - start, out)?;
+                self.delete(start, end, out)?;
+            }
+            Key::Ctrl(b'l') => {
+                out.write_all(b"\x1b[2J\x1b[H")?;
+                self.redraw(out)?;
+            }
+            Key::Up | Key::Ctrl(b'p') => self.history_prev(out)?,
+            Key::Down | Key::Ctrl(b'n') => self.history_next(out)?,
+            _ => out.write_all(BELL)?,
+        }
+
+        Ok(Action::Continue)
+    }
+
+    /// Inserts `bytes` at the cursor and moves the cursor past them.
+    pub fn insert<W: io::Write>(&mut self, bytes: &[u8], out: &mut W) -> io::Result<()> {
+        if self.len + bytes.len() > LINE_LEN {
+            return out.write_all(BELL);
+        }
+
+        let at = self.cursor;
+        self.buf.copy_within(at..self.len, at + bytes.len());
+        self.buf[at..at + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+        self.cursor += bytes.len();
+
+        out.write_all(&self.buf[at..self.len])?;
+        self.move_back(self.len - self.cursor, out)
+    }
+
+    /// Completes the word before the cursor with the candidates from
+    /// `completer`.
+    ///
+    /// A single candidate replaces the word. With several candidates, the
+    /// word is extended to their longest common prefix; if that adds nothing,
+    /// a second Tab in a row lists all of them below the line.
+    pub fn complete<C, W>(&mut self, completer: &mut C, out: &mut W) -> io::Result<()>
+    where
+        C: Completer,
+        W: io::Write,
+    {
+        let end = self.cursor;
+        let mut start = end;
+        while start > 0 && self.buf[start - 1] != b' ' {
+            start -= 1;
+        }
+
+        let mut candidates = {
+            let line = self.line();
+            completer.complete(&line[..start], &line[start..end])
+        };
+        candidates.retain(|c| !c.is_empty() && c.bytes().all(|b| (0x20..0x7f).contains(&b)));
+        candidates.sort();
+        candidates.dedup();
+
+        if candidates.len() == 1 {
+            let mut candidate = candidates.remove(0);
+            if !candidate.ends_with('/') {
+                candidate.push(' ');
+            }
+            self.tabs = 0;
+            return self.replace_word(start, candidate.as_bytes(), out);
+        }
+
+        let prefix = common_prefix(&candidates);
+        if prefix.len() > end - start {
+            self.tabs = 0;
+            self.replace_word(start, prefix.as_bytes(), out)
+        } else if self.tabs >= 2 && !candidates.is_empty() {
+            self.list(&candidates, out)
+        } else {
+            out.write_all(BELL)
+        }
+    }
+
+    /// Replaces the word from `start` to the cursor with `word`.
+    fn replace_word<W: io::Write>(&mut self, start: usize, word: &[u8], out: &mut W) -> io::Result<()> {
+        let end = self.cursor;
+        if self.len - (end - start) + word.len() > LINE_LEN {
+            return out.write_all(BELL);
+        }
+
+        self.move_left(end - start, out)?;
+        self.delete(start, end, out)?;
+        self.insert(word, out)
+    }
+
+    /// Lists `candidates` in columns below the line, then redraws the line.
+    /// Only the last path component of each candidate is shown.
+    fn list<W: io::Write>(&self, candidates: &[String], out: &mut W) -> io::Result<()> {
+        let names: Vec<&str> = candidates
+            .iter()
+            .map(|c| {
+                let name = c.trim_end_matches('/');
+                match name.rfind('/') {
+                    Some(i) => &c[i + 1..],
+                    None => &c[..],
+                }
+            })
+            .collect();
+
+        let width = names.iter().map(|n| n.len()).max().unwrap_or(0) + 2;
+        let columns = core::cmp::max(1, TERM_WIDTH / width);
+
+        out.write_all(b"\r\n")?;
+        for (i, name) in names.iter().enumerate() {
+            out.write_all(name.as_bytes())?;
+            if (i + 1) % columns == 0 || i + 1 == names.len() {
+                out.write_all(b"\r\n")?;
+            } else {
+                write!(out, "{:1$}", "", width - name.len())?;
+            }
+        }
+
+        self.redraw(out)
+    }
+
+    /// Returns the start of the word ending at `end`, skipping any spaces
+    /// right before `end` first.
+    fn word_start(&self, end: usize) -> usize {
+        let mut start = end;
+        while start > 0 && self.buf[start - 1] == b' ' {
+            start -= 1;
+        }
+        while start > 0 && self.buf[start - 1] != b' ' {
+            start -= 1;
+        }
+        start
+    }
+
+    /// Removes `buf[start..end]`. The cursor must already be at `start`.
+    fn delete<W: io::Write>(&mut self, start: usize, end: usize, out: &mut W) -> io::Result<()> {
+        if start == end {
+            return Ok(());
+        }
+
+        self.buf.copy_within(end..self.len, start);
+        self.len -= end - start;
+
+        out.write_all(&self.buf[start..self.len])?;
+        out.write_all(b"\x1b[K")?;
+        self.move_back(self.len - start, out)
+    }
+
+    fn move_left<W: io::Write>(&mut self, n: usize, out: &mut W) -> io::Result<()> {
+        self.cursor -= n;
+        self.move_back(n, out)
+    }
+
+    fn move_right<W: io::Write>(&mut self, n: usize, out: &mut W) -> io::Result<()> {
+        self.cursor += n;
+        match n {
+            0 => Ok(()),
+            n => write!(out, "\x1b[{}C", n),
+        }
+    }
+
+    /// Moves the terminal's cursor `n` columns left without touching
+    /// `self.cursor`.
+    fn move_back<W: io::Write>(&self, n: usize, out: &mut W) -> io::Result<()> {
+        match n {
+            0 => Ok(()),
+            n => write!(out, "\x1b[{}D", n),
+        }
+    }
+
+    /// Writes the prompt and the line, then puts the cursor back in place.
+    fn redraw<W: io::Write>(&self, out: &mut W) -> io::Result<()> {
+        out.write_all(self.prompt.as_bytes())?;
+        out.write_all(&self.buf[..self.len])?;
+        out.write_all(b"\x1b[K")?;
+        self.move_back(self.len - self.cursor, out)
+    }
+
+    /// Replaces the whole line with `line` and moves the cursor to its end.
+    fn replace<W: io::Write>(&mut self, line: &[u8], out: &mut W) -> io::Result<()> {
+        let cursor = self.cursor;
+        self.move_left(cursor, out)?;
+        self.buf[..line.len()].copy_from_slice(line);
+        self.len = line.len();
+        self.cursor = line.len();
+        out.write_all(line)?;
+        out.write_all(b"\x1b[K")
+    }
+
+    fn history_prev<W: io::Write>(&mut self, out: &mut W) -> io::Result<()> {
+        let age = self.browsing.map(|age| age + 1).unwrap_or(0);
+        if age >= self.history.len() {
+            return out.write_all(BELL);
+        }
+
+        if self.browsing.is_none() {
+            self.saved[..self.len].copy_from_slice(&self.buf[..self.len]);
+            self.saved_len = self.len;
+        }
+
+        self.browsing = Some(age);
+        let mut line = [0; LINE_LEN];
+        let len = {
+            let entry = self.history.get(age).unwrap_or(&[]);
+            line[..entry.len()].copy_from_slice(entry);
+            entry.len()
+        };
+
+        self.replace(&line[..len], out)
+    }
+
+    fn history_next<W: io::Write>(&mut self, out: &mut W) -> io::Result<()> {
+        let mut line = [0; LINE_LEN];
+        let len = match self.browsing {
+            None => return out.write_all(BELL),
+            Some(0) => {
+                self.browsing = None;
+                line[..self.saved_len].copy_from_slice(&self.saved[..self.saved_len]);
+                self.saved_len
+            }
+            Some(age) => {
+                self.browsing = Some(age - 1);
+                let entry = self.history.get(age - 1).unwrap_or(&[]);
+                line[..entry.len()].copy_from_slice(entry);
+                entry.len()
+            }
+        };
+
+        self.replace(&line[..len], out)
+    }
+}
+
+/// Returns the longest prefix shared by all of `candidates`.
+fn common_prefix(candidates: &[String]) -> &str {
+    let first = match candidates.first() {
+        Some(first) => first,
+        None => return "",
+    };
+
+    let len = candidates[1..].iter().fold(first.len(), |len, c| {
+        first.bytes().zip(c.bytes()).take(len).take_while(|(a, b)| a == b).count()
+    });
+
+    &first[..len]
+}
@@ -0,0 +1,327 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+use core::ptr;
+
+use pi::atags::Atags;
+use pi::common::{IO_BASE, IO_BASE_END};
+
+use shim::io::Write;
+
+use crate::allocator;
+use crate::ALLOCATOR;
+
+use super::command::{Builtin, Error, ShellCommand};
+use super::hexdump;
+use super::stdio::Stdio;
+use super::Shell;
+
+/// Commands that read and write physical memory.
+pub const COMMANDS: &[&dyn ShellCommand] = &[
+    &Builtin {
+        name: "peek",
+        help: "read a value from physical memory",
+        usage: "peek [-8|-16|-32|-64] <addr>",
+        run: peek,
+    },
+    &Builtin {
+        name: "poke",
+        help: "write a value to physical memory",
+        usage: "poke [-8|-16|-32|-64] <addr> <value>",
+        run: poke,
+    },
+    &Builtin { name: "memmap", help: "print the physical memory map", usage: "memmap", run: memmap },
+    &Builtin { name: "meminfo", help: "print heap usage", usage: "meminfo", run: meminfo },
+    &Builtin { name: "leaks", help: "list live heap allocations", usage: "leaks", run: leaks },
+];
+
+/// The most bytes `hexdump -m` shows at once.
+pub const MAX_DUMP: usize = 64 * 1024;
+
+/// The most allocations `leaks` lists.
+const MAX_LEAKS: usize = 64;
+
+/// Peripheral registers are all this wide and must be accessed whole.
+const MMIO_WIDTH: usize = 4;
+
+/// The kind of memory an address range lies in.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Region {
+    Ram,
+    Mmio,
+}
+
+/// The reason a memory access was refused.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AccessError {
+    /// The address is not a multiple of the access width.
+    Unaligned,
+    /// The range is not entirely within RAM or entirely within the
+    /// peripherals.
+    OutOfRange,
+    /// Peripheral registers must be accessed 32 bits at a time.
+    MmioWidth,
+}
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AccessError::Unaligned => f.write_str("address is not aligned to the access width"),
+            AccessError::OutOfRange => f.write_str("not in RAM or the peripheral range"),
+            AccessError::MmioWidth => f.write_str("peripherals must be accessed 32 bits at a time"),
+        }
+    }
+}
+
+/// Checks that `len` bytes at `addr`, accessed `width` bytes at a time, lie
+/// within one of the `ram` ranges or the peripheral range and can be accessed
+/// safely, returning which of the two it is. `width` must be 1, 2, 4 or 8.
+pub fn check(addr: usize, len: usize, width: usize, ram: &[Range<usize>]) -> Result<Region, AccessError> {
+    let end = addr.checked_add(len).ok_or(AccessError::OutOfRange)?;
+    let region = if ram.iter().any(|ram| ram.start <= addr && end <= ram.end) {
+        Region::Ram
+    } else if IO_BASE <= addr && end <= IO_BASE_END {
+        Region::Mmio
+    } else {
+        return Err(AccessError::OutOfRange);
+    };
+
+    if region == Region::Mmio && width != MMIO_WIDTH {
+        return Err(AccessError::MmioWidth);
+    }
+
+    // With the MMU off all memory is device memory, which faults on
+    // unaligned accesses.
+    if addr % width != 0 || len % width != 0 {
+        return Err(AccessError::Unaligned);
+    }
+
+    Ok(region)
+}
+
+/// Parses an address or value, in hex if it starts with `0x` and in decimal
+/// otherwise. `_` may separate digits.
+pub fn parse_number(s: &str) -> Option<u64> {
+    let (digits, radix) = if s.starts_with("0x") || s.starts_with("0X") {
+        (&s[2..], 16)
+    } else {
+        (s, 10)
+    };
+
+    let digits: String = digits.chars().filter(|&c| c != '_').collect();
+    u64::from_str_radix(&digits, radix).ok()
+}
+
+/// Returns RAM as reported by the firmware's `Mem` ATAGs, in the order the
+/// firmware lists them.
+pub fn ram() -> Vec<Range<usize>> {
+    Atags::get()
+        .filter_map(|atag| atag.mem())
+        .map(|mem| mem.start as usize..mem.start as usize + mem.size as usize)
+        .collect()
+}
+
+fn ram_or_error() -> Result<Vec<Range<usize>>, Error> {
+    let ram = ram();
+    if ram.is_empty() {
+        return Err(Error::Failed(String::from("no memory ATAG")));
+    }
+    Ok(ram)
+}
+
+/// Splits a leading `-8`, `-16`, `-32` or `-64` off `args`, returning the
+/// access width in bytes (4 if there is none) and the remaining arguments.
+fn width<'a, 'b>(args: &'a [&'b str]) -> Result<(usize, &'a [&'b str]), Error> {
+    match args.first() {
+        Some(&"-8") => Ok((1, &args[1..])),
+        Some(&"-16") => Ok((2, &args[1..])),
+        Some(&"-32") => Ok((4, &args[1..])),
+        Some(&"-64") => Ok((8, &args[1..])),
+        Some(arg) if arg.starts_with('-') => Err(Error::Usage),
+        _ => Ok((4, args)),
+    }
+}
+
+/// Parses `arg` as an address and checks that `len` bytes there can be
+/// accessed `width` bytes at a time.
+fn address(arg: &str, len: usize, width: usize) -> Result<usize, Error> {
+    let addr = parse_number(arg).ok_or(Error::Usage)? as usize;
+    let ram = ram_or_error()?;
+    check(addr, len, width, &ram)
+        .map(|_| addr)
+        .map_err(|e| Error::Failed(format!("{:#x}: {}", addr, e)))
+}
+
+/// Reads `width` bytes at `addr` with a single volatile access.
+///
+/// # Safety
+///
+/// `addr` must have passed `check()` for `width`.
+unsafe fn read(addr: usize, width: usize) -> u64 {
+    match width {
+        1 => ptr::read_volatile(addr as *const u8) as u64,
+        2 => ptr::read_volatile(addr as *const u16) as u64,
+        4 => ptr::read_volatile(addr as *const u32) as u64,
+        _ => ptr::read_volatile(addr as *const u64),
+    }
+}
+
+/// Writes the low `width` bytes of `value` to `addr` with a single volatile
+/// access.
+///
+/// # Safety
+///
+/// `addr` must have passed `check()` for `width`.
+unsafe fn write(addr: usize, width: usize, value: u64) {
+    match width {
+        1 => ptr::write_volatile(addr as *mut u8, value as u8),
+        2 => ptr::write_volatile(addr as *mut u16, value as u16),
+        4 => ptr::write_volatile(addr as *mut u32, value as u32),
+        _ => ptr::write_volatile(addr as *mut u64, value),
+    }
+}
+
+/// Prints the value at an address.
+fn peek(args: &[&str], _shell: &mut Shell, stdio: &mut Stdio) -> Result<(), Error> {
+    let (width, args) = width(&args[1..])?;
+    let addr = match args {
+        [addr] => address(addr, width, width)?,
+        _ => return Err(Error::Usage),
+    };
+
+    let value = unsafe { read(addr, width) };
+    writeln!(stdio.stdout, "{:#010x}: {:#02$x}", addr, value, width * 2 + 2)?;
+    Ok(())
+}
+
+/// Writes a value to an address.
+fn poke(args: &[&str], _shell: &mut Shell, _stdio: &mut Stdio) -> Result<(), Error> {
+    let (width, args) = width(&args[1..])?;
+    let (addr, value) = match args {
+        [addr, value] => (address(addr, width, width)?, parse_number(value).ok_or(Error::Usage)?),
+        _ => return Err(Error::Usage),
+    };
+
+    if width < 8 && value >> (width * 8) != 0 {
+        return Err(Error::Failed(format!("{:#x} does not fit in {} bits", value, width * 8)));
+    }
+
+    unsafe { write(addr, width, value) };
+    Ok(())
+}
+
+/// Shows `len` bytes of memory at `addr` as a hexdump. Peripheral registers
+/// are read 32 bits at a time.
+pub fn dump(addr: &str, len: &str, stdio: &mut Stdio) -> Result<(), Error> {
+    let len = parse_number(len).ok_or(Error::Usage)? as usize;
+    if len > MAX_DUMP {
+        return Err(Error::Failed(format!("at most {} bytes can be shown at once", MAX_DUMP)));
+    }
+
+    let ram = ram_or_error()?;
+    let start = parse_number(addr).ok_or(Error::Usage)? as usize;
+    let width = match check(start, len, 1, &ram) {
+        Err(AccessError::MmioWidth) => MMIO_WIDTH,
+        _ => 1,
+    };
+    check(start, len, width, &ram).map_err(|e| Error::Failed(format!("{:#x}: {}", start, e)))?;
+
+    let mut bytes = Vec::with_capacity(len);
+    for addr in (start..start + len).step_by(width) {
+        let value = unsafe { read(addr, width) };
+        bytes.extend_from_slice(&value.to_le_bytes()[..width]);
+    }
+
+    for line in hexdump::lines(start, &bytes) {
+        writeln!(stdio.stdout, "{}", line)?;
+    }
+
+    Ok(())
+}
+
+/// Prints RAM, the part of it the kernel takes (the firmware's data, the
+/// kernel and its stack), the heap's regions, the reserved regions (among
+/// them the VideoCore's memory) and the peripherals.
+fn memmap(args: &[&str], _shell: &mut Shell, stdio: &mut Stdio) -> Result<(), Error> {
+    if args.len() != 1 {
+        return Err(Error::Usage);
+    }
+
+    let ram = ram();
+    let mut regions = Vec::new();
+    for range in ram.iter() {
+        regions.push(("ram", range.clone()));
+    }
+    if let Some(first) = ram.first() {
+        regions.push(("kernel", first.start..allocator::kernel_end()));
+    }
+    for region in ALLOCATOR.regions().iter() {
+        regions.push(("heap", region.start..region.end));
+    }
+    allocator::reservations(|r| regions.push((r.name, r.region.start..r.region.end)));
+    regions.push(("mmio", IO_BASE..IO_BASE_END));
+
+    for (name, range) in regions {
+        let size = range.end.saturating_sub(range.start);
+        writeln!(
+            stdio.stdout,
+            "{:<8}{:#010x}-{:#010x}  {:>6} KiB",
+            name,
+            range.start,
+            range.end,
+            size / 1024
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Prints the heap's regions and the allocator's statistics.
+fn meminfo(args: &[&str], _shell: &mut Shell, stdio: &mut Stdio) -> Result<(), Error> {
+    if args.len() != 1 {
+        return Err(Error::Usage);
+    }
+
+    let stats = ALLOCATOR
+        .stats()
+        .ok_or_else(|| Error::Failed(String::from("allocator uninitialized")))?;
+    for region in ALLOCATOR.regions().iter() {
+        writeln!(stdio.stdout, "heap:       {}", region)?;
+    }
+    writeln!(stdio.stdout, "{}", stats)?;
+    Ok(())
+}
+
+/// Lists the newest live allocations and who made them. Needs a kernel built
+/// with the `debug-alloc` feature.
+fn leaks(args: &[&str], _shell: &mut Shell, stdio: &mut Stdio) -> Result<(), Error> {
+    if args.len() != 1 {
+        return Err(Error::Usage);
+    }
+
+    let mut records = vec![allocator::Record::default(); MAX_LEAKS];
+    let count = ALLOCATOR.live(&mut records).ok_or_else(|| {
+        Error::Failed(String::from("allocations are only recorded with the debug-alloc feature"))
+    })?;
+
+    // `records` itself is the newest allocation.
+    let own = records.as_ptr() as usize;
+    let mut bytes = 0;
+    for record in records.iter().take(count).filter(|record| record.ptr != own) {
+        writeln!(
+            stdio.stdout,
+            "{:#010x}  {:>8} bytes  align {:<4}  from {:#010x}",
+            record.ptr, record.size, record.align, record.caller
+        )?;
+        bytes += record.size;
+    }
+
+    if count > MAX_LEAKS {
+        writeln!(stdio.stdout, "... and {} more", count - MAX_LEAKS)?;
+    }
+    writeln!(stdio.stdout, "{} live allocations, {} bytes listed", count.saturating_sub(1), bytes)?;
+    Ok(())
+}
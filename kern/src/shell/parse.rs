@@ -0,0 +1,419 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::iter::Peekable;
+use core::str::CharIndices;
+
+/// A piece of a word: literal text or a variable expanded when the command
+/// runs.
+#[derive(Debug, PartialEq)]
+pub enum Part {
+    Literal(String),
+    Var(String),
+}
+
+/// A single argument of a command.
+#[derive(Debug, Default, PartialEq)]
+pub struct Word {
+    pub parts: Vec<Part>,
+    /// Whether any of the word was quoted. An unquoted word that expands to
+    /// nothing is dropped from the arguments; a quoted one is kept as `""`.
+    pub quoted: bool,
+}
+
+impl Word {
+    /// Returns the word with its variables replaced by their values in `env`.
+    /// Unset variables expand to nothing.
+    pub fn expand(&self, env: &BTreeMap<String, String>) -> String {
+        let mut expanded = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => expanded.push_str(text),
+                Part::Var(name) => expanded.push_str(env.get(name).map_or("", |v| v.as_str())),
+            }
+        }
+
+        expanded
+    }
+
+    fn push_char(&mut self, c: char) {
+        match self.parts.last_mut() {
+            Some(Part::Literal(text)) => text.push(c),
+            _ => {
+                let mut text = String::new();
+                text.push(c);
+                self.parts.push(Part::Literal(text));
+            }
+        }
+    }
+}
+
+/// Where a redirection sends a command's input or output.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RedirectKind {
+    /// `< file`: read standard input from the file.
+    Input,
+    /// `> file`: replace the file with standard output.
+    Output,
+    /// `>> file`: append standard output to the file.
+    Append,
+}
+
+/// A redirection of a command's input or output to a file.
+#[derive(Debug, PartialEq)]
+pub struct Redirect {
+    pub kind: RedirectKind,
+    pub target: Word,
+}
+
+/// A command, its arguments and its redirections.
+#[derive(Debug, Default, PartialEq)]
+pub struct Command {
+    pub words: Vec<Word>,
+    pub redirects: Vec<Redirect>,
+}
+
+/// Commands joined by `|`, each reading the output of the one before.
+pub type Pipeline = Vec<Command>;
+
+/// How a command is joined to the one before it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Connector {
+    /// `;` (or the start of the line): always run.
+    Then,
+    /// `&&`: run if the previous command succeeded.
+    And,
+    /// `||`: run if the previous command failed.
+    Or,
+}
+
+/// The reason a line could not be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    /// A quote was opened but never closed.
+    UnterminatedQuote(char),
+    /// The line ends with a `\`.
+    TrailingBackslash,
+    /// A `${` was never closed.
+    UnterminatedBrace,
+    /// A `${...}` holds something other than a variable name.
+    BadVariableName,
+    /// A `;`, `&&`, `||` or `|` has no command on one side.
+    MissingCommand,
+    /// A `<`, `>` or `>>` is not followed by a file name.
+    MissingTarget,
+    /// A character that cannot appear here.
+    Unexpected(char),
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::UnterminatedQuote(quote) => write!(f, "missing closing {}", quote),
+            ErrorKind::TrailingBackslash => f.write_str("nothing to escape after \\"),
+            ErrorKind::UnterminatedBrace => f.write_str("missing closing }"),
+            ErrorKind::BadVariableName => f.write_str("bad variable name"),
+            ErrorKind::MissingCommand => f.write_str("expected a command"),
+            ErrorKind::MissingTarget => f.write_str("expected a file name"),
+            ErrorKind::Unexpected(c) => write!(f, "unexpected {}", c),
+        }
+    }
+}
+
+/// Error returned by `parse()`, pointing at the offending byte of the line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub column: usize,
+    pub kind: ErrorKind,
+}
+
+/// Returns `true` if `name` can be used as a variable name.
+pub fn is_var_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c == '_' || c.is_ascii_alphabetic() => {
+            chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+        }
+        _ => false,
+    }
+}
+
+/// Splits `line` into pipelines joined by `;`, `&&` and `||`.
+///
+/// Words are separated by whitespace. Text in single quotes is taken as is.
+/// Text in double quotes may contain `$` expansions and `\` escapes of `"`,
+/// `\` and `$`. Outside of quotes, `\` escapes any character and a `#` that
+/// starts a word begins a comment running to the end of the line. `$NAME`,
+/// `${NAME}` and `$?` name variables. `<`, `>` and `>>` followed by a file
+/// name redirect a command's input and output.
+pub fn parse(line: &str) -> Result<Vec<(Connector, Pipeline)>, ParseError> {
+    let mut parser = Parser { chars: line.char_indices().peekable(), end: line.len() };
+    let mut pipelines = Vec::new();
+    let mut connector = Connector::Then;
+
+    loop {
+        let pipeline = parser.pipeline()?;
+        let column = parser.column();
+        let next = parser.connector()?;
+
+        if pipeline.is_empty() {
+            // An empty command is only allowed at the end of the line, after
+            // a `;` or on its own.
+            if next.is_some() || connector != Connector::Then {
+                return Err(ParseError { column, kind: ErrorKind::MissingCommand });
+            }
+        } else {
+            pipelines.push((connector, pipeline));
+        }
+
+        match next {
+            Some(next) => connector = next,
+            None => return Ok(pipelines),
+        }
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<CharIndices<'a>>,
+    end: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Returns the column of the next character.
+    fn column(&mut self) -> usize {
+        let end = self.end;
+        self.chars.peek().map_or(end, |&(i, _)| i)
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|&(_, c)| c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().map_or(false, char::is_whitespace) {
+            self.chars.next();
+        }
+    }
+
+    fn error<T>(&mut self, kind: ErrorKind) -> Result<T, ParseError> {
+        Err(ParseError { column: self.column(), kind })
+    }
+
+    /// Returns `true` if the next character is a `|` that is not part of a
+    /// `||`.
+    fn at_pipe(&mut self) -> bool {
+        let mut ahead = self.chars.clone();
+        match (ahead.next(), ahead.peek()) {
+            (Some((_, '|')), Some(&(_, '|'))) => false,
+            (Some((_, '|')), _) => true,
+            _ => false,
+        }
+    }
+
+    /// Parses commands joined by `|`. Returns an empty pipeline if there is no
+    /// command at all.
+    fn pipeline(&mut self) -> Result<Pipeline, ParseError> {
+        let mut pipeline = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let start = self.column();
+            let command = self.command()?;
+            let piped = self.at_pipe();
+
+            if command.words.is_empty() {
+                if pipeline.is_empty() && !piped && command.redirects.is_empty() {
+                    return Ok(pipeline);
+                }
+
+                // Point at the redirection missing its command, or at
+                // whatever follows a `|` instead of a command.
+                let column = if command.redirects.is_empty() { self.column() } else { start };
+                return Err(ParseError { column, kind: ErrorKind::MissingCommand });
+            }
+
+            pipeline.push(command);
+            if !piped {
+                return Ok(pipeline);
+            }
+            self.chars.next();
+        }
+    }
+
+    /// Parses the words and redirections of one command, stopping at a
+    /// connector, a pipe, a comment or the end of the line.
+    fn command(&mut self) -> Result<Command, ParseError> {
+        let mut command = Command::default();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None | Some(';') | Some('&') | Some('|') => return Ok(command),
+                Some('#') => {
+                    while self.chars.next().is_some() {}
+                    return Ok(command);
+                }
+                Some('<') | Some('>') => command.redirects.push(self.redirect()?),
+                Some(_) => command.words.push(self.word()?),
+            }
+        }
+    }
+
+    fn redirect(&mut self) -> Result<Redirect, ParseError> {
+        let kind = match self.chars.next() {
+            Some((_, '<')) => RedirectKind::Input,
+            _ if self.peek() == Some('>') => {
+                self.chars.next();
+                RedirectKind::Append
+            }
+            _ => RedirectKind::Output,
+        };
+
+        self.skip_whitespace();
+        match self.peek() {
+            None | Some(';') | Some('&') | Some('|') | Some('<') | Some('>') | Some('#') => {
+                self.error(ErrorKind::MissingTarget)
+            }
+            Some(_) => Ok(Redirect { kind, target: self.word()? }),
+        }
+    }
+
+    /// Parses the connector after a command, if any.
+    fn connector(&mut self) -> Result<Option<Connector>, ParseError> {
+        let (doubled, connector) = match self.peek() {
+            None => return Ok(None),
+            Some(';') => {
+                self.chars.next();
+                return Ok(Some(Connector::Then));
+            }
+            Some('&') => ('&', Connector::And),
+            Some('|') => ('|', Connector::Or),
+            Some(c) => return self.error(ErrorKind::Unexpected(c)),
+        };
+
+        let column = self.column();
+        self.chars.next();
+        match self.peek() {
+            Some(c) if c == doubled => {
+                self.chars.next();
+                Ok(Some(connector))
+            }
+            _ => Err(ParseError { column, kind: ErrorKind::Unexpected(doubled) }),
+        }
+    }
+
+    fn word(&mut self) -> Result<Word, ParseError> {
+        let mut word = Word::default();
+        loop {
+            match self.peek() {
+                None | Some(';') | Some('&') | Some('|') | Some('<') | Some('>') => return Ok(word),
+                Some(c) if c.is_whitespace() => return Ok(word),
+                Some('\'') => {
+                    word.quoted = true;
+                    self.single_quoted(&mut word)?;
+                }
+                Some('"') => {
+                    word.quoted = true;
+                    self.double_quoted(&mut word)?;
+                }
+                Some('\\') => {
+                    self.chars.next();
+                    match self.chars.next() {
+                        Some((_, c)) => word.push_char(c),
+                        None => return self.error(ErrorKind::TrailingBackslash),
+                    }
+                }
+                Some('$') => self.variable(&mut word)?,
+                Some(c) => {
+                    self.chars.next();
+                    word.push_char(c);
+                }
+            }
+        }
+    }
+
+    fn single_quoted(&mut self, word: &mut Word) -> Result<(), ParseError> {
+        let column = self.column();
+        self.chars.next();
+        loop {
+            match self.chars.next() {
+                Some((_, '\'')) => return Ok(()),
+                Some((_, c)) => word.push_char(c),
+                None => return Err(ParseError { column, kind: ErrorKind::UnterminatedQuote('\'') }),
+            }
+        }
+    }
+
+    fn double_quoted(&mut self, word: &mut Word) -> Result<(), ParseError> {
+        let column = self.column();
+        self.chars.next();
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.chars.next();
+                    return Ok(());
+                }
+                Some('\\') => {
+                    self.chars.next();
+                    match self.peek() {
+                        Some(c) if c == '"' || c == '\\' || c == '$' => {
+                            self.chars.next();
+                            word.push_char(c);
+                        }
+                        _ => word.push_char('\\'),
+                    }
+                }
+                Some('$') => self.variable(word)?,
+                Some(c) => {
+                    self.chars.next();
+                    word.push_char(c);
+                }
+                None => return Err(ParseError { column, kind: ErrorKind::UnterminatedQuote('"') }),
+            }
+        }
+    }
+
+    /// Parses a `$` expansion. A `$` not followed by a variable name is taken
+    /// literally.
+    fn variable(&mut self, word: &mut Word) -> Result<(), ParseError> {
+        let column = self.column();
+        self.chars.next();
+
+        let mut name = String::new();
+        match self.peek() {
+            Some('?') => {
+                self.chars.next();
+                name.push('?');
+            }
+            Some('{') => {
+                self.chars.next();
+                loop {
+                    match self.chars.next() {
+                        Some((_, '}')) => break,
+                        Some((_, c)) => name.push(c),
+                        None => return Err(ParseError { column, kind: ErrorKind::UnterminatedBrace }),
+                    }
+                }
+
+                if !is_var_name(&name) && name != "?" {
+                    return Err(ParseError { column, kind: ErrorKind::BadVariableName });
+                }
+            }
+            Some(c) if c == '_' || c.is_ascii_alphabetic() => {
+                while let Some(c) = self.peek() {
+                    if c != '_' && !c.is_ascii_alphanumeric() {
+                        break;
+                    }
+                    self.chars.next();
+                    name.push(c);
+                }
+            }
+            _ => {
+                word.push_char('$');
+                return Ok(());
+            }
+        }
+
+        word.parts.push(Part::Var(name));
+        Ok(())
+    }
+}
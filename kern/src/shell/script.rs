@@ -0,0 +1,27 @@
+/// The kernel command line option naming the script to run at boot, as in
+/// `autorun=/boot/init.sh`.
+const AUTORUN_OPTION: &str = "autorun=";
+
+/// Scripts may `source` other scripts up to this depth, which stops a script
+/// that sources itself.
+pub const MAX_DEPTH: usize = 8;
+
+/// Returns the path given by the `autorun=` option of the kernel command line
+/// `cmdline`, if any. The last such option wins.
+pub fn autorun_path(cmdline: &str) -> Option<&str> {
+    cmdline
+        .split_whitespace()
+        .rev()
+        .filter(|option| option.starts_with(AUTORUN_OPTION))
+        .map(|option| &option[AUTORUN_OPTION.len()..])
+        .find(|path| !path.is_empty())
+}
+
+/// Returns an iterator over the lines of the script `text` with their line
+/// numbers, starting from 1. Both `\n` and `\r\n` end a line.
+pub fn lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+}
@@ -0,0 +1,66 @@
+use shim::io;
+
+use crate::console::{self, CONSOLE};
+
+/// The input and output a command is connected to: the console, a file or
+/// the previous or next command of a pipeline.
+pub struct Stdio<'a> {
+    pub stdin: &'a mut dyn io::Read,
+    pub stdout: &'a mut dyn io::Write,
+}
+
+/// End-of-file key for console input: Ctrl-D.
+const EOF: u8 = 0x04;
+
+/// Reads standard input from the console, echoing what is typed, until
+/// Ctrl-D is pressed at the start of a read.
+pub struct ConsoleIn;
+
+impl io::Read for ConsoleIn {
+    /// Reads up to a line. Enter is read as `\n`.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut len = 0;
+        while len < buf.len() {
+            let byte = match console::read_byte() {
+                EOF => break,
+                b'\r' => b'\n',
+                byte => byte,
+            };
+
+            let mut console = CONSOLE.lock();
+            if byte == b'\n' {
+                console.write_byte(b'\r');
+            }
+            console.write_byte(byte);
+
+            buf[len] = byte;
+            len += 1;
+            if byte == b'\n' {
+                break;
+            }
+        }
+
+        Ok(len)
+    }
+}
+
+/// Writes standard output to the console, turning `\n` into `\r\n`.
+pub struct ConsoleOut;
+
+impl io::Write for ConsoleOut {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut console = CONSOLE.lock();
+        for &byte in buf {
+            if byte == b'\n' {
+                console.write_byte(b'\r');
+            }
+            console.write_byte(byte);
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
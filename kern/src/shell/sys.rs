@@ -0,0 +1,141 @@
+use alloc::format;
+use alloc::string::String;
+use core::time::Duration;
+
+use shim::io::Write;
+
+use pi::interrupt::{Controller, Interrupt};
+
+use crate::console::CONSOLE;
+use crate::{aarch64, mutex, IRQ, TIMERS};
+
+use super::command::{Builtin, Error, ShellCommand};
+use super::mem::parse_number;
+use super::stdio::Stdio;
+use super::Shell;
+
+/// Commands that inspect the kernel itself.
+pub const COMMANDS: &[&dyn ShellCommand] = &[
+    &Builtin {
+        name: "brk",
+        help: "stop in the debugger, resuming when it exits",
+        usage: "brk",
+        run: brk,
+    },
+    &Builtin {
+        name: "irqs",
+        help: "show the interrupt sources and their handlers",
+        usage: "irqs",
+        run: irqs,
+    },
+    &Builtin {
+        name: "locks",
+        help: "show the named kernel locks and their contention",
+        usage: "locks [-w <spins>]",
+        run: locks,
+    },
+    &Builtin {
+        name: "sleep",
+        help: "wait for a number of milliseconds",
+        usage: "sleep <ms>",
+        run: sleep,
+    },
+];
+
+fn brk(args: &[&str], _shell: &mut Shell, _stdio: &mut Stdio) -> Result<(), Error> {
+    if args.len() != 1 {
+        return Err(Error::Usage);
+    }
+
+    aarch64::brk();
+    Ok(())
+}
+
+/// Lists every interrupt source with how often its handler ran, and how many
+/// received bytes the console lost.
+fn irqs(args: &[&str], _shell: &mut Shell, stdio: &mut Stdio) -> Result<(), Error> {
+    if args.len() != 1 {
+        return Err(Error::Usage);
+    }
+
+    let controller = Controller::new();
+    writeln!(stdio.stdout, "{:<8}{:<10}{:<10}{:>12}{:>12}", "irq", "state", "handler", "count", "unhandled")?;
+    for int in Interrupt::iter() {
+        let stats = IRQ.stats(int);
+        let state = match controller.is_enabled(int) {
+            true => "enabled",
+            false => "disabled",
+        };
+        let handler = match stats.registered {
+            true => "yes",
+            false => "no",
+        };
+        let name = format!("{:?}", int);
+        writeln!(stdio.stdout, "{:<8}{:<10}{:<10}{:>12}{:>12}", name, state, handler, stats.count, stats.unhandled)?;
+    }
+    writeln!(stdio.stdout, "{} spurious", IRQ.spurious())?;
+
+    let overruns = CONSOLE.lock().overruns();
+    writeln!(stdio.stdout, "console lost {} bytes to fifo overruns, {} to a full buffer", overruns.fifo, overruns.buffer)?;
+    Ok(())
+}
+
+/// Lists every named lock that has been taken, or with `-w`, sets the spins
+/// after which waiters warn (0 for never).
+fn locks(args: &[&str], _shell: &mut Shell, stdio: &mut Stdio) -> Result<(), Error> {
+    let unavailable = || Error::Failed(String::from("lock diagnostics are only kept in debug builds"));
+
+    match args {
+        [_] => {}
+        [_, "-w", spins] => {
+            let spins = parse_number(spins).ok_or(Error::Usage)? as usize;
+            return match mutex::set_spin_warning(spins) {
+                true => Ok(()),
+                false => Err(unavailable()),
+            };
+        }
+        _ => return Err(Error::Usage),
+    }
+
+    writeln!(
+        stdio.stdout,
+        "{:<14}{:<10}{:>10}{:>11}{:>11}{:>11}  last taken at",
+        "name", "state", "taken", "contended", "spins", "max spins"
+    )?;
+
+    let mut result = Ok(());
+    let kept = mutex::each_lock(|lock| {
+        let state = match lock.owner {
+            Some(core) => format!("core {}", core),
+            None => String::from("free"),
+        };
+        let holder = lock.holder.map_or(String::from("-"), |at| format!("{}", at));
+        if result.is_ok() {
+            result = writeln!(
+                stdio.stdout,
+                "{:<14}{:<10}{:>10}{:>11}{:>11}{:>11}  {}",
+                lock.name, state, lock.acquisitions, lock.contended, lock.spins, lock.max_spins, holder
+            );
+        }
+    });
+    if !kept {
+        return Err(unavailable());
+    }
+    result?;
+
+    match mutex::spin_warning() {
+        0 => writeln!(stdio.stdout, "waiters never warn")?,
+        spins => writeln!(stdio.stdout, "waiters warn after {} spins", spins)?,
+    }
+    Ok(())
+}
+
+fn sleep(args: &[&str], _shell: &mut Shell, _stdio: &mut Stdio) -> Result<(), Error> {
+    let ms = match args {
+        [_, ms] => parse_number(ms).ok_or(Error::Usage)?,
+        _ => return Err(Error::Usage),
+    };
+
+    TIMERS.sleep(Duration::from_millis(ms));
+    Ok(())
+}
@@ -0,0 +1,738 @@
+mod line_editor {
+    use crate::shell::line::{Action, Decoder, History, Key, LineEditor, HISTORY_LEN, LINE_LEN};
+
+    const UP: &[u8] = b"\x1b[A";
+    const DOWN: &[u8] = b"\x1b[B";
+    const RIGHT: &[u8] = b"\x1b[C";
+    const LEFT: &[u8] = b"\x1b[D";
+    const HOME: &[u8] = b"\x1b[H";
+    const END: &[u8] = b"\x1b[F";
+    const DELETE: &[u8] = b"\x1b[3~";
+
+    fn feed(editor: &mut LineEditor, bytes: &[u8]) -> Vec<Action> {
+        let mut out = vec![];
+        bytes
+            .iter()
+            .map(|&b| editor.feed(b, &mut out).unwrap())
+            .filter(|&action| action != Action::Continue)
+            .collect()
+    }
+
+    fn submit(editor: &mut LineEditor, line: &[u8]) {
+        assert_eq!(feed(editor, line), vec![]);
+        assert_eq!(feed(editor, b"\r"), vec![Action::Submit]);
+        editor.finish();
+    }
+
+    #[test]
+    fn decodes_keys() {
+        let mut decoder = Decoder::new();
+        let mut keys = vec![];
+        for &b in b"a\x1b[A\x1b[B\x1b[C\x1b[D\x1bOH\x1b[4~\x1b[3~\x01\x7f\t\x1b[1;5C\r\n" {
+            keys.extend(decoder.feed(b));
+        }
+
+        assert_eq!(
+            keys,
+            vec![
+                Key::Char(b'a'),
+                Key::Up,
+                Key::Down,
+                Key::Right,
+                Key::Left,
+                Key::Home,
+                Key::End,
+                Key::Delete,
+                Key::Ctrl(b'a'),
+                Key::Backspace,
+                Key::Tab,
+                Key::Right,
+                Key::Enter,
+            ]
+        );
+    }
+
+    #[test]
+    fn append_and_backspace() {
+        let mut editor = LineEditor::new("> ");
+        feed(&mut editor, b"echoo\x7f hi");
+        assert_eq!(editor.line(), "echo hi");
+        assert_eq!(editor.cursor(), 7);
+    }
+
+    #[test]
+    fn insert_in_middle() {
+        let mut editor = LineEditor::new("> ");
+        feed(&mut editor, b"ech hi");
+        feed(&mut editor, LEFT);
+        feed(&mut editor, LEFT);
+        feed(&mut editor, LEFT);
+        feed(&mut editor, b"o");
+        assert_eq!(editor.line(), "echo hi");
+        assert_eq!(editor.cursor(), 4);
+
+        feed(&mut editor, RIGHT);
+        feed(&mut editor, b"there ");
+        assert_eq!(editor.line(), "echo there hi");
+    }
+
+    #[test]
+    fn home_end_and_delete() {
+        let mut editor = LineEditor::new("> ");
+        feed(&mut editor, b"xecho");
+        feed(&mut editor, HOME);
+        assert_eq!(editor.cursor(), 0);
+        feed(&mut editor, DELETE);
+        assert_eq!(editor.line(), "echo");
+        feed(&mut editor, END);
+        assert_eq!(editor.cursor(), 4);
+        feed(&mut editor, b" 1");
+
+        feed(&mut editor, b"\x01");
+        assert_eq!(editor.cursor(), 0);
+        feed(&mut editor, b"\x05");
+        assert_eq!(editor.cursor(), 6);
+        assert_eq!(editor.line(), "echo 1");
+    }
+
+    #[test]
+    fn kill_line_and_word() {
+        let mut editor = LineEditor::new("> ");
+        feed(&mut editor, b"echo hello  world");
+        feed(&mut editor, b"\x17");
+        assert_eq!(editor.line(), "echo hello  ");
+        feed(&mut editor, b"\x17");
+        assert_eq!(editor.line(), "echo ");
+
+        feed(&mut editor, b"a b c");
+        feed(&mut editor, LEFT);
+        feed(&mut editor, LEFT);
+        feed(&mut editor, b"\x15");
+        assert_eq!(editor.line(), " c");
+        assert_eq!(editor.cursor(), 0);
+    }
+
+    #[test]
+    fn cancel_discards_line() {
+        let mut editor = LineEditor::new("> ");
+        assert_eq!(feed(&mut editor, b"echo\x03"), vec![Action::Cancel]);
+        assert_eq!(editor.line(), "");
+        submit(&mut editor, b"atags");
+        assert_eq!(editor.history().len(), 1);
+    }
+
+    #[test]
+    fn crlf_submits_once() {
+        let mut editor = LineEditor::new("> ");
+        assert_eq!(feed(&mut editor, b"echo\r\n"), vec![Action::Submit]);
+        editor.finish();
+        assert_eq!(feed(&mut editor, b"\n"), vec![Action::Submit]);
+    }
+
+    #[test]
+    fn history_navigation() {
+        let mut editor = LineEditor::new("> ");
+        submit(&mut editor, b"one");
+        submit(&mut editor, b"two");
+        submit(&mut editor, b"three");
+
+        feed(&mut editor, b"draft");
+        feed(&mut editor, UP);
+        assert_eq!(editor.line(), "three");
+        feed(&mut editor, UP);
+        feed(&mut editor, UP);
+        assert_eq!(editor.line(), "one");
+        assert_eq!(editor.cursor(), 3);
+
+        // Stays on the oldest entry.
+        feed(&mut editor, UP);
+        assert_eq!(editor.line(), "one");
+
+        feed(&mut editor, DOWN);
+        assert_eq!(editor.line(), "two");
+        feed(&mut editor, DOWN);
+        feed(&mut editor, DOWN);
+        assert_eq!(editor.line(), "draft");
+
+        feed(&mut editor, UP);
+        feed(&mut editor, b"!");
+        assert_eq!(feed(&mut editor, b"\r"), vec![Action::Submit]);
+        assert_eq!(editor.line(), "three!");
+    }
+
+    #[test]
+    fn history_ring_drops_oldest() {
+        let mut history = History::new();
+        for i in 0..(HISTORY_LEN + 3) {
+            history.push(format!("cmd {}", i).as_bytes());
+        }
+
+        assert_eq!(history.len(), HISTORY_LEN);
+        assert_eq!(history.get(0), Some(format!("cmd {}", HISTORY_LEN + 2).as_bytes()));
+        assert_eq!(history.get(HISTORY_LEN - 1), Some(&b"cmd 3"[..]));
+        assert_eq!(history.get(HISTORY_LEN), None);
+
+        history.push(b"");
+        history.push(format!("cmd {}", HISTORY_LEN + 2).as_bytes());
+        assert_eq!(history.get(1), Some(format!("cmd {}", HISTORY_LEN + 1).as_bytes()));
+    }
+
+    #[test]
+    fn full_line_rings_bell() {
+        let mut editor = LineEditor::new("> ");
+        let line = vec![b'x'; LINE_LEN];
+        feed(&mut editor, &line);
+        assert_eq!(editor.line().len(), LINE_LEN);
+
+        let mut out = vec![];
+        editor.feed(b'y', &mut out).unwrap();
+        assert_eq!(out, b"\x07");
+        assert_eq!(editor.line().len(), LINE_LEN);
+    }
+
+    #[test]
+    fn echoes_edits() {
+        let mut editor = LineEditor::new("> ");
+        let mut out = vec![];
+        editor.start(&mut out).unwrap();
+        for &b in b"ac\x1b[Db" {
+            editor.feed(b, &mut out).unwrap();
+        }
+
+        assert_eq!(out, b"> ac\x1b[1Dbc\x1b[1D");
+    }
+}
+
+mod completion {
+    use crate::shell::line::{Action, Completer, LineEditor};
+
+    /// Completes every word from a fixed list of names, like paths from a
+    /// single directory.
+    struct Words(&'static [&'static str]);
+
+    impl Completer for Words {
+        fn complete(&mut self, _before: &str, word: &str) -> Vec<String> {
+            self.0
+                .iter()
+                .filter(|w| w.starts_with(word))
+                .map(|w| w.to_string())
+                .collect()
+        }
+    }
+
+    const NAMES: &[&str] = &["atags", "cat", "cd", "/boot/", "/bootcode.bin", "/config.txt"];
+
+    fn tab(editor: &mut LineEditor) -> Vec<u8> {
+        let mut out = vec![];
+        assert_eq!(editor.feed(b'\t', &mut out).unwrap(), Action::Complete);
+        editor.complete(&mut Words(NAMES), &mut out).unwrap();
+        out
+    }
+
+    fn typed(line: &[u8]) -> LineEditor<'static> {
+        let mut editor = LineEditor::new("> ");
+        for &b in line {
+            editor.feed(b, &mut vec![]).unwrap();
+        }
+        editor
+    }
+
+    #[test]
+    fn unique_candidate_adds_space() {
+        let mut editor = typed(b"at");
+        tab(&mut editor);
+        assert_eq!(editor.line(), "atags ");
+        assert_eq!(editor.cursor(), 6);
+    }
+
+    #[test]
+    fn directory_has_no_space() {
+        let mut editor = typed(b"cat /boot");
+        tab(&mut editor);
+        assert_eq!(editor.line(), "cat /boot");
+
+        let mut editor = typed(b"cat /bo");
+        tab(&mut editor);
+        assert_eq!(editor.line(), "cat /boot");
+
+        let mut editor = typed(b"cat /boot/");
+        tab(&mut editor);
+        assert_eq!(editor.line(), "cat /boot/");
+    }
+
+    #[test]
+    fn common_prefix_then_list() {
+        let mut editor = typed(b"c");
+        let out = tab(&mut editor);
+        assert_eq!(editor.line(), "c");
+        assert_eq!(out, b"\x07");
+
+        let out = tab(&mut editor);
+        assert_eq!(out, b"\r\ncat  cd\r\n> c\x1b[K");
+        assert_eq!(editor.line(), "c");
+    }
+
+    #[test]
+    fn list_shows_last_component() {
+        let mut editor = typed(b"ls /b");
+        tab(&mut editor);
+        assert_eq!(editor.line(), "ls /boot");
+
+        assert_eq!(tab(&mut editor), b"\x07");
+        let out = tab(&mut editor);
+        assert_eq!(out, b"\r\nboot/         bootcode.bin\r\n> ls /boot\x1b[K");
+    }
+
+    #[test]
+    fn other_keys_reset_double_tab() {
+        let mut editor = typed(b"c");
+        tab(&mut editor);
+        editor.feed(b'x', &mut vec![]).unwrap();
+        editor.feed(0x7f, &mut vec![]).unwrap();
+        assert_eq!(tab(&mut editor), b"\x07");
+    }
+
+    #[test]
+    fn completes_in_middle_of_line() {
+        let mut editor = typed(b"ca /config.txt\x1b[H\x1b[C\x1b[C");
+        tab(&mut editor);
+        assert_eq!(editor.line(), "cat  /config.txt");
+        assert_eq!(editor.cursor(), 4);
+    }
+
+    #[test]
+    fn command_positions() {
+        use crate::shell::complete::is_command_position;
+
+        assert!(is_command_position(""));
+        assert!(is_command_position("  "));
+        assert!(is_command_position("ls; "));
+        assert!(is_command_position("ls &&"));
+        assert!(is_command_position("false || "));
+        assert!(is_command_position("ls | "));
+        assert!(!is_command_position("ls "));
+        assert!(!is_command_position("echo a;b "));
+    }
+
+    #[test]
+    fn no_candidates_rings_bell() {
+        let mut editor = typed(b"xyz");
+        assert_eq!(tab(&mut editor), b"\x07");
+        assert_eq!(tab(&mut editor), b"\x07");
+        assert_eq!(editor.line(), "xyz");
+    }
+}
+
+mod fs {
+    use crate::shell::fs::{is_text, normalize};
+    use shim::path::Path;
+
+    #[test]
+    fn normalizes_dots() {
+        let cases = [
+            ("/", "/"),
+            ("/a/b/c", "/a/b/c"),
+            ("/a/./b/", "/a/b"),
+            ("/a/b/../c", "/a/c"),
+            ("/a/b/../../..", "/"),
+            ("/../a", "/a"),
+            ("/a/.././b/./../c/d/..", "/c"),
+        ];
+
+        for &(path, expected) in &cases {
+            assert_eq!(normalize(Path::new(path)), Path::new(expected), "{}", path);
+        }
+    }
+
+    #[test]
+    fn detects_binary() {
+        assert!(is_text(b""));
+        assert!(is_text(b"kernel=kernel8.img\r\n\tarm_64bit=1\n"));
+        assert!(is_text("caf\u{e9}\n".as_bytes()));
+        assert!(!is_text(b"ELF\x7f\x02\x01"));
+        assert!(!is_text(b"text\0with nul"));
+    }
+}
+
+mod hexdump {
+    use crate::shell::hexdump::{lines, HexLine};
+
+    #[test]
+    fn full_line() {
+        let line = HexLine { offset: 0x10, bytes: b"Hello, world!\n\x00\xff" };
+        assert_eq!(
+            line.to_string(),
+            "00000010  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a 00 ff  |Hello, world!...|"
+        );
+    }
+
+    #[test]
+    fn partial_line_is_padded() {
+        let all: Vec<String> = lines(0x200, b"0123456789abcdefXYZ").map(|l| l.to_string()).collect();
+        assert_eq!(
+            all,
+            vec![
+                "00000200  30 31 32 33 34 35 36 37  38 39 61 62 63 64 65 66  |0123456789abcdef|",
+                "00000210  58 59 5a                                          |XYZ|",
+            ]
+        );
+    }
+}
+
+mod command {
+    use crate::shell::command::{edit_distance, Builtin, Error, Registry, ShellCommand};
+    use crate::shell::{Shell, Stdio};
+
+    fn ok(_args: &[&str], _shell: &mut Shell, _stdio: &mut Stdio) -> Result<(), Error> {
+        Ok(())
+    }
+
+    const FIRST: &[&dyn ShellCommand] = &[
+        &Builtin { name: "help", help: "", usage: "help", run: ok },
+        &Builtin { name: "echo", help: "", usage: "echo", run: ok },
+    ];
+
+    const SECOND: &[&dyn ShellCommand] = &[
+        &Builtin { name: "ls", help: "", usage: "ls", run: ok },
+        &Builtin { name: "cat", help: "", usage: "cat", run: ok },
+        &Builtin { name: "meminfo", help: "", usage: "meminfo", run: ok },
+    ];
+
+    static REGISTRY: Registry = Registry::new(&[FIRST, SECOND]);
+
+    #[test]
+    fn finds_commands_in_every_group() {
+        let names: Vec<&str> = REGISTRY.iter().map(|c| c.name()).collect();
+        assert_eq!(names, vec!["help", "echo", "ls", "cat", "meminfo"]);
+
+        assert_eq!(REGISTRY.find("cat").map(|c| c.usage()), Some("cat"));
+        assert!(REGISTRY.find("ca").is_none());
+    }
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("cat", "cat"), 0);
+        assert_eq!(edit_distance("", "cat"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("ehco", "echo"), 1);
+        assert_eq!(edit_distance("abc", "ca"), 3);
+        assert_eq!(edit_distance("ls", "lsl"), 1);
+    }
+
+    #[test]
+    fn suggests_close_names() {
+        assert_eq!(REGISTRY.suggest("ehco"), Some("echo"));
+        assert_eq!(REGISTRY.suggest("hepl"), Some("help"));
+        assert_eq!(REGISTRY.suggest("sl"), Some("ls"));
+        assert_eq!(REGISTRY.suggest("meminf"), Some("meminfo"));
+        assert_eq!(REGISTRY.suggest("c"), None);
+        assert_eq!(REGISTRY.suggest("reboot"), None);
+    }
+}
+
+mod parse {
+    use std::collections::BTreeMap;
+
+    use crate::shell::parse::{parse, Command, Connector, ErrorKind, ParseError, RedirectKind};
+
+    fn env() -> BTreeMap<String, String> {
+        let mut env = BTreeMap::new();
+        env.insert("?".to_string(), "1".to_string());
+        env.insert("DIR".to_string(), "/boot".to_string());
+        env.insert("N".to_string(), "two words".to_string());
+        env
+    }
+
+    /// Returns the expanded words of each command on `line`, none of which
+    /// may be piped.
+    fn expand(line: &str) -> Vec<(Connector, Vec<String>)> {
+        let env = env();
+        parse(line)
+            .unwrap()
+            .into_iter()
+            .map(|(connector, pipeline)| {
+                assert_eq!(pipeline.len(), 1, "{}", line);
+                (connector, pipeline[0].words.iter().map(|w| w.expand(&env)).collect())
+            })
+            .collect()
+    }
+
+    fn words(line: &str) -> Vec<String> {
+        let mut commands = expand(line);
+        assert_eq!(commands.len(), 1, "{}", line);
+        commands.remove(0).1
+    }
+
+    fn error(line: &str) -> ParseError {
+        parse(line).unwrap_err()
+    }
+
+    #[test]
+    fn splits_on_whitespace() {
+        assert_eq!(words("echo  a\tb "), vec!["echo", "a", "b"]);
+        assert!(parse("").unwrap().is_empty());
+        assert!(parse("   ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn quotes_and_escapes() {
+        assert_eq!(words("echo 'a b' \"c  d\""), vec!["echo", "a b", "c  d"]);
+        assert_eq!(words("echo a\\ b \\'"), vec!["echo", "a b", "'"]);
+        assert_eq!(words("echo '$DIR' \"$DIR\""), vec!["echo", "$DIR", "/boot"]);
+        assert_eq!(words(r#"echo "a\"b\\c\$d\e""#), vec!["echo", r#"a"b\c$d\e"#]);
+        assert_eq!(words("echo x'y'\"z\"w"), vec!["echo", "xyzw"]);
+        assert_eq!(words("echo '' \"\""), vec!["echo", "", ""]);
+        assert_eq!(words("echo 'a;b' a\\;b"), vec!["echo", "a;b", "a;b"]);
+    }
+
+    #[test]
+    fn variables() {
+        assert_eq!(words("ls $DIR/x ${DIR}x $DIRx"), vec!["ls", "/boot/x", "/bootx", ""]);
+        assert_eq!(words("echo $? $N"), vec!["echo", "1", "two words"]);
+        assert_eq!(words("echo $ a$ $1 ${?}"), vec!["echo", "$", "a$", "$1", "1"]);
+
+        let (_, pipeline) = parse("echo $UNSET \"$UNSET\"").unwrap().remove(0);
+        assert!(!pipeline[0].words[1].quoted);
+        assert!(pipeline[0].words[2].quoted);
+    }
+
+    #[test]
+    fn comments() {
+        assert!(parse("# nothing here").unwrap().is_empty());
+        assert_eq!(words("echo a#b # c ; d"), vec!["echo", "a#b"]);
+        assert_eq!(words("echo '#' \\#"), vec!["echo", "#", "#"]);
+    }
+
+    #[test]
+    fn sequences() {
+        let commands = expand("a; b && c || d ;e;");
+        let connectors: Vec<Connector> = commands.iter().map(|(c, _)| *c).collect();
+        let names: Vec<&str> = commands.iter().map(|(_, w)| w[0].as_str()).collect();
+        assert_eq!(
+            connectors,
+            vec![Connector::Then, Connector::Then, Connector::And, Connector::Or, Connector::Then]
+        );
+        assert_eq!(names, vec!["a", "b", "c", "d", "e"]);
+
+        assert_eq!(expand("a&&b").len(), 2);
+    }
+
+    #[test]
+    fn pipes() {
+        let env = env();
+        let (_, pipeline) = parse("cat a | grep -i x|wc").unwrap().remove(0);
+        let names: Vec<String> = pipeline.iter().map(|c| c.words[0].expand(&env)).collect();
+        assert_eq!(names, vec!["cat", "grep", "wc"]);
+
+        let pipelines = parse("a | b || c").unwrap();
+        assert_eq!(pipelines.len(), 2);
+        assert_eq!(pipelines[0].1.len(), 2);
+        assert_eq!(pipelines[1].0, Connector::Or);
+        assert_eq!(words("echo '|' \\|"), vec!["echo", "|", "|"]);
+    }
+
+    #[test]
+    fn redirects() {
+        let env = env();
+        let (_, mut pipeline) = parse("ls $DIR >/tmp/out < in >> '/tmp/a b'").unwrap().remove(0);
+        let Command { words: args, redirects } = pipeline.remove(0);
+        assert_eq!(args.len(), 2);
+
+        let targets: Vec<(RedirectKind, String)> =
+            redirects.iter().map(|r| (r.kind, r.target.expand(&env))).collect();
+        assert_eq!(
+            targets,
+            vec![
+                (RedirectKind::Output, "/tmp/out".to_string()),
+                (RedirectKind::Input, "in".to_string()),
+                (RedirectKind::Append, "/tmp/a b".to_string()),
+            ]
+        );
+
+        assert_eq!(words("echo a>b"), vec!["echo", "a"]);
+        assert_eq!(words("echo '>' \\<"), vec!["echo", ">", "<"]);
+    }
+
+    #[test]
+    fn error_columns() {
+        let cases = [
+            ("echo 'abc", 5, ErrorKind::UnterminatedQuote('\'')),
+            ("echo a \"b", 7, ErrorKind::UnterminatedQuote('"')),
+            ("echo \\", 6, ErrorKind::TrailingBackslash),
+            ("echo ${DIR", 5, ErrorKind::UnterminatedBrace),
+            ("echo ${1x}", 5, ErrorKind::BadVariableName),
+            ("; ls", 0, ErrorKind::MissingCommand),
+            ("ls ;; ls", 4, ErrorKind::MissingCommand),
+            ("ls &&", 5, ErrorKind::MissingCommand),
+            ("ls || # comment", 15, ErrorKind::MissingCommand),
+            ("ls & ls", 3, ErrorKind::Unexpected('&')),
+            ("ls |", 4, ErrorKind::MissingCommand),
+            ("| ls", 0, ErrorKind::MissingCommand),
+            ("ls | | wc", 5, ErrorKind::MissingCommand),
+            ("> /tmp/x", 0, ErrorKind::MissingCommand),
+            ("ls >", 4, ErrorKind::MissingTarget),
+            ("ls > ; ls", 5, ErrorKind::MissingTarget),
+            ("ls >> >x", 6, ErrorKind::MissingTarget),
+        ];
+
+        for (line, column, kind) in cases.iter().cloned() {
+            assert_eq!(error(line), ParseError { column, kind }, "{}", line);
+        }
+    }
+}
+
+mod filter {
+    use crate::shell::filter::{contains, lines};
+
+    #[test]
+    fn splits_lines() {
+        let split: Vec<&[u8]> = lines(b"a\nbc\n\nd").collect();
+        assert_eq!(split, vec![&b"a\n"[..], b"bc\n", b"\n", b"d"]);
+        assert_eq!(lines(b"").count(), 0);
+    }
+
+    #[test]
+    fn matches_substrings() {
+        assert!(contains(b"hello world", b"o w", false));
+        assert!(contains(b"anything", b"", false));
+        assert!(!contains(b"Hello", b"hello", false));
+        assert!(contains(b"Hello", b"hELLO", true));
+        assert!(!contains(b"he", b"hello", true));
+    }
+}
+
+mod tmp {
+    use crate::shell::tmp::TmpFs;
+    use shim::io;
+    use shim::path::Path;
+
+    #[test]
+    fn writes_and_appends() {
+        let mut tmp = TmpFs::new();
+        let path = Path::new("/tmp/out");
+        tmp.write(path, b"one\n", false).unwrap();
+        tmp.write(path, b"two\n", true).unwrap();
+        assert_eq!(tmp.get(path), Some(&b"one\ntwo\n"[..]));
+
+        tmp.write(path, b"three\n", false).unwrap();
+        assert_eq!(tmp.get(path), Some(&b"three\n"[..]));
+        assert_eq!(tmp.files().collect::<Vec<_>>(), vec![("out", 6)]);
+
+        assert!(tmp.remove(path));
+        assert!(!tmp.remove(path));
+        assert_eq!(tmp.get(path), None);
+    }
+
+    #[test]
+    fn only_tmp_is_writable() {
+        assert!(TmpFs::is_dir(Path::new("/tmp")));
+        assert!(TmpFs::is_file(Path::new("/tmp/x")));
+        assert!(!TmpFs::is_file(Path::new("/tmp")));
+        assert!(!TmpFs::is_file(Path::new("/tmp/a/b")));
+
+        let mut tmp = TmpFs::new();
+        let error = tmp.write(Path::new("/boot/x"), b"", false).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(tmp.files().count(), 0);
+    }
+}
+
+mod script {
+    use crate::shell::script::{autorun_path, lines};
+
+    #[test]
+    fn finds_autorun_option() {
+        assert_eq!(autorun_path("autorun=/boot/init.sh"), Some("/boot/init.sh"));
+        assert_eq!(autorun_path("quiet autorun=a.sh  debug"), Some("a.sh"));
+        assert_eq!(autorun_path("autorun=a.sh autorun=b.sh"), Some("b.sh"));
+        assert_eq!(autorun_path("autorun= quiet"), None);
+        assert_eq!(autorun_path("noautorun=a.sh"), None);
+        assert_eq!(autorun_path(""), None);
+    }
+
+    #[test]
+    fn numbers_lines() {
+        let numbered: Vec<(usize, &str)> = lines("# setup\r\necho a\n\nexit 3").collect();
+        assert_eq!(numbered, vec![(1, "# setup"), (2, "echo a"), (3, ""), (4, "exit 3")]);
+    }
+}
+
+mod mem {
+    use crate::shell::mem::{check, parse_number, AccessError, Region};
+    use pi::common::{IO_BASE, IO_BASE_END};
+
+    const RAM: &[core::ops::Range<usize>] = &[0..0x3b40_0000];
+
+    #[test]
+    fn parses_numbers() {
+        assert_eq!(parse_number("42"), Some(42));
+        assert_eq!(parse_number("0x3F20_0000"), Some(0x3f20_0000));
+        assert_eq!(parse_number("0xffffffffffffffff"), Some(u64::max_value()));
+        assert_eq!(parse_number("0x"), None);
+        assert_eq!(parse_number("12a"), None);
+        assert_eq!(parse_number("-1"), None);
+        assert_eq!(parse_number("0x1_0000_0000_0000_0000"), None);
+    }
+
+    #[test]
+    fn accepts_ram_and_mmio() {
+        assert_eq!(check(0x80000, 8, 8, RAM), Ok(Region::Ram));
+        assert_eq!(check(0x3b3f_ffff, 1, 1, RAM), Ok(Region::Ram));
+        assert_eq!(check(IO_BASE + 0x200000, 4, 4, RAM), Ok(Region::Mmio));
+        assert_eq!(check(IO_BASE_END - 16, 16, 4, RAM), Ok(Region::Mmio));
+    }
+
+    #[test]
+    fn accepts_every_ram_range() {
+        let ram = [0..0x3b40_0000, 0x4000_0000..0x4010_0000];
+        assert_eq!(check(0x4000_0000, 8, 8, &ram), Ok(Region::Ram));
+        assert_eq!(check(0x400f_fffc, 4, 4, &ram), Ok(Region::Ram));
+        assert_eq!(check(0x400f_fffc, 8, 4, &ram), Err(AccessError::OutOfRange));
+        assert_eq!(check(0x3b3f_fffc, 8, 4, &ram), Err(AccessError::OutOfRange));
+    }
+
+    #[test]
+    fn refuses_bad_accesses() {
+        assert_eq!(check(0x3b3f_fffe, 4, 4, RAM), Err(AccessError::OutOfRange));
+        assert_eq!(check(0x3c00_0000, 4, 4, RAM), Err(AccessError::OutOfRange));
+        assert_eq!(check(IO_BASE_END, 4, 4, RAM), Err(AccessError::OutOfRange));
+        assert_eq!(check(usize::max_value() - 1, 4, 4, RAM), Err(AccessError::OutOfRange));
+        assert_eq!(check(0x80001, 2, 2, RAM), Err(AccessError::Unaligned));
+        assert_eq!(check(0x80000, 6, 4, RAM), Err(AccessError::Unaligned));
+        assert_eq!(check(IO_BASE + 2, 4, 4, RAM), Err(AccessError::Unaligned));
+        assert_eq!(check(IO_BASE, 1, 1, RAM), Err(AccessError::MmioWidth));
+        assert_eq!(check(IO_BASE, 8, 8, RAM), Err(AccessError::MmioWidth));
+    }
+}
+
+mod gpio {
+    use crate::shell::gpio::{function_name, parse, Op};
+    use pi::gpio::{Function, Pull};
+
+    #[test]
+    fn parses_operations() {
+        assert_eq!(parse(&["16"]), Some((16, Op::Show)));
+        assert_eq!(parse(&["0x10", "out"]), Some((16, Op::Function(Function::Output))));
+        assert_eq!(parse(&["4", "alt5"]), Some((4, Op::Function(Function::Alt5))));
+        assert_eq!(parse(&["21", "toggle"]), Some((21, Op::Toggle)));
+        assert_eq!(parse(&["21", "pull", "down"]), Some((21, Op::Pull(Pull::Down))));
+        assert_eq!(parse(&["blink", "16", "250", "3"]), Some((16, Op::Blink { ms: 250, count: 3 })));
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert_eq!(parse(&[]), None);
+        assert_eq!(parse(&["54"]), None);
+        assert_eq!(parse(&["16", "alt6"]), None);
+        assert_eq!(parse(&["16", "pull", "sideways"]), None);
+        assert_eq!(parse(&["16", "set", "now"]), None);
+        assert_eq!(parse(&["blink", "16", "fast", "3"]), None);
+    }
+
+    #[test]
+    fn names_functions() {
+        assert_eq!(function_name(Function::Input), "in");
+        assert_eq!(function_name(Function::Alt3), "alt3");
+    }
+}
@@ -0,0 +1,73 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use shim::io;
+use shim::ioerr;
+use shim::path::{Path, PathBuf};
+
+/// The directory holding in-memory files.
+pub const TMP_DIR: &str = "/tmp";
+
+/// In-memory files under `/tmp`, kept by the shell so that output can be
+/// redirected while the SD card file system is read only. The files last
+/// until the shell exits.
+#[derive(Default)]
+pub struct TmpFs {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+}
+
+impl TmpFs {
+    /// Returns an empty `/tmp`.
+    pub fn new() -> TmpFs {
+        TmpFs { files: BTreeMap::new() }
+    }
+
+    /// Returns `true` if `path` is the `/tmp` directory itself. `path` must
+    /// be absolute and normalized.
+    pub fn is_dir(path: &Path) -> bool {
+        path == Path::new(TMP_DIR)
+    }
+
+    /// Returns `true` if `path` names a file in `/tmp`, whether it exists or
+    /// not. `path` must be absolute and normalized.
+    pub fn is_file(path: &Path) -> bool {
+        path.parent() == Some(Path::new(TMP_DIR))
+    }
+
+    /// Returns the contents of the file at `path`, if it exists.
+    pub fn get(&self, path: &Path) -> Option<&[u8]> {
+        self.files.get(path).map(|data| data.as_slice())
+    }
+
+    /// Writes `data` to the file at `path`, creating it if needed. The file is
+    /// replaced unless `append` is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind `PermissionDenied` if `path` is not in `/tmp`.
+    pub fn write(&mut self, path: &Path, data: &[u8], append: bool) -> io::Result<()> {
+        if !TmpFs::is_file(path) {
+            return ioerr!(PermissionDenied, "read-only file system: only files in /tmp can be written");
+        }
+
+        let file = self.files.entry(path.to_path_buf()).or_default();
+        if !append {
+            file.clear();
+        }
+        file.extend_from_slice(data);
+        Ok(())
+    }
+
+    /// Removes the file at `path`. Returns `true` if it existed.
+    pub fn remove(&mut self, path: &Path) -> bool {
+        self.files.remove(path).is_some()
+    }
+
+    /// Returns an iterator over the names and sizes of the files in `/tmp`.
+    pub fn files(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.files.iter().map(|(path, data)| {
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+            (name, data.len())
+        })
+    }
+}
@@ -0,0 +1,47 @@
+mod once;
+
+// Not used by the kernel yet.
+#[allow(dead_code)]
+pub mod lazy;
+#[allow(dead_code)]
+pub mod rwlock;
+#[allow(dead_code)]
+pub mod semaphore;
+
+#[cfg(test)]
+mod tests;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::aarch64;
+
+pub use self::once::Once;
+
+/// Replaces the value of `atomic` with `new` if it is `current`, returning
+/// `true` if it was.
+///
+/// Exclusive accesses do not work until the caches are on, and until then
+/// only core 0 runs: a plain load and store will do.
+pub fn compare_exchange(atomic: &AtomicUsize, current: usize, new: usize) -> bool {
+    if aarch64::caches_enabled() {
+        return atomic
+            .compare_exchange(current, new, Ordering::AcqRel, Ordering::Relaxed)
+            .is_ok();
+    }
+
+    if atomic.load(Ordering::Relaxed) != current {
+        return false;
+    }
+    atomic.store(new, Ordering::Release);
+    true
+}
+
+/// Replaces the value of `atomic` with `f` of it, returning the old value.
+fn update<F: Fn(usize) -> usize>(atomic: &AtomicUsize, f: F) -> usize {
+    loop {
+        let current = atomic.load(Ordering::Relaxed);
+        if compare_exchange(atomic, current, f(current)) {
+            return current;
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use core::cell::Cell;
+use core::fmt;
+use core::ops::Deref;
+
+use crate::sync::Once;
+
+/// A value initialized by `F` the first time it is dereferenced. Lets
+/// globals that need code to initialize be used without an `initialize()`
+/// call:
+///
+/// ```ignore
+/// static TABLE: Lazy<Table> = Lazy::new(Table::build);
+/// ```
+pub struct Lazy<T, F = fn() -> T> {
+    once: Once<T>,
+    init: Cell<Option<F>>,
+}
+
+unsafe impl<T: Send + Sync, F: Send> Sync for Lazy<T, F> {}
+
+impl<T, F> Lazy<T, F> {
+    /// Returns a `Lazy` that will be initialized by `init`.
+    pub const fn new(init: F) -> Lazy<T, F> {
+        Lazy { once: Once::new(), init: Cell::new(Some(init)) }
+    }
+}
+
+impl<T, F: FnOnce() -> T> Lazy<T, F> {
+    /// Returns the value, initializing it if it has not been yet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the initializer dereferences the value it is initializing.
+    pub fn force(this: &Lazy<T, F>) -> &T {
+        this.once.call_once(|| match this.init.take() {
+            Some(init) => init(),
+            None => unreachable!("Lazy initialized twice"),
+        })
+    }
+}
+
+impl<T, F: FnOnce() -> T> Deref for Lazy<T, F> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        Lazy::force(self)
+    }
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for Lazy<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.once.get() {
+            Some(value) => f.debug_struct("Lazy").field("data", value).finish(),
+            None => f.debug_struct("Lazy").field("data", &"<uninit>").finish(),
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::aarch64;
+use crate::sync::compare_exchange;
+
+/// `Once::state` before the value is initialized, while it is and after.
+const INCOMPLETE: usize = 0;
+const RUNNING: usize = 1;
+const COMPLETE: usize = 2;
+
+/// `Once::runner` until a core starts initializing the value.
+const NO_RUNNER: usize = usize::max_value();
+
+/// A value initialized once, by the first core to ask for it. Cores asking
+/// for it while it is initialized wait for it.
+pub struct Once<T> {
+    state: AtomicUsize,
+    /// The core initializing the value, while it is.
+    runner: AtomicUsize,
+    data: UnsafeCell<Option<T>>,
+}
+
+unsafe impl<T: Send> Send for Once<T> {}
+unsafe impl<T: Send + Sync> Sync for Once<T> {}
+
+impl<T> Once<T> {
+    /// Returns a `Once` with no value yet.
+    pub const fn new() -> Once<T> {
+        Once {
+            state: AtomicUsize::new(INCOMPLETE),
+            runner: AtomicUsize::new(NO_RUNNER),
+            data: UnsafeCell::new(None),
+        }
+    }
+
+    /// Returns the value, initializing it with `f` if no core has yet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` asks for the value it is initializing.
+    pub fn call_once<F: FnOnce() -> T>(&self, f: F) -> &T {
+        if let Some(value) = self.get() {
+            return value;
+        }
+
+        let this = aarch64::affinity();
+        if compare_exchange(&self.state, INCOMPLETE, RUNNING) {
+            self.runner.store(this, Ordering::Relaxed);
+            unsafe { *self.data.get() = Some(f()) };
+            self.state.store(COMPLETE, Ordering::Release);
+            aarch64::sev();
+        }
+
+        loop {
+            if let Some(value) = self.get() {
+                return value;
+            }
+
+            if self.runner.load(Ordering::Relaxed) == this {
+                panic!("Once initialized from its own initializer on core {}", this);
+            }
+            aarch64::wfe();
+        }
+    }
+
+    /// Returns the value if it has been initialized.
+    pub fn get(&self) -> Option<&T> {
+        match self.state.load(Ordering::Acquire) {
+            COMPLETE => unsafe { (*self.data.get()).as_ref() },
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the value has been initialized.
+    #[allow(dead_code)]
+    pub fn is_completed(&self) -> bool {
+        self.state.load(Ordering::Acquire) == COMPLETE
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Once<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.get() {
+            Some(value) => f.debug_struct("Once").field("data", value).finish(),
+            None => f.debug_struct("Once").field("data", &"<uninit>").finish(),
+        }
+    }
+}
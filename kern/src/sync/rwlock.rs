@@ -0,0 +1,168 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::aarch64;
+use crate::sync::{compare_exchange, update};
+
+/// Set in `RwLock::state` while a writer holds the lock.
+const WRITER: usize = 1;
+
+/// Set in `RwLock::state` while a writer waits for the lock, which keeps new
+/// readers out so that writers are not starved.
+const WAITING: usize = 1 << 1;
+
+/// Added to `RwLock::state` for every reader holding the lock.
+const READER: usize = 1 << 2;
+
+/// `RwLock::writer` when no writer holds the lock.
+const NO_WRITER: usize = usize::max_value();
+
+/// A reader-writer spinlock: any number of readers or a single writer may
+/// hold it. Meant for data that is read much more often than written.
+///
+/// Taking the lock on the core whose writer already holds it panics instead
+/// of spinning forever. Readers are not tracked, so a reader must not read
+/// again while it holds the lock: a writer that started waiting in between
+/// would keep it waiting forever.
+pub struct RwLock<T> {
+    state: AtomicUsize,
+    writer: AtomicUsize,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for RwLock<T> {}
+unsafe impl<T: Send + Sync> Sync for RwLock<T> {}
+
+pub struct RwLockReadGuard<'a, T: 'a> {
+    lock: &'a RwLock<T>,
+}
+
+pub struct RwLockWriteGuard<'a, T: 'a> {
+    lock: &'a RwLock<T>,
+}
+
+impl<T> RwLock<T> {
+    pub const fn new(val: T) -> RwLock<T> {
+        RwLock {
+            state: AtomicUsize::new(0),
+            writer: AtomicUsize::new(NO_WRITER),
+            data: UnsafeCell::new(val),
+        }
+    }
+
+    /// Returns a read guard if no writer holds or waits for the lock.
+    pub fn try_read(&self) -> Option<RwLockReadGuard<T>> {
+        let state = self.state.load(Ordering::Relaxed);
+        match state & (WRITER | WAITING) == 0 && compare_exchange(&self.state, state, state + READER) {
+            true => Some(RwLockReadGuard { lock: self }),
+            false => None,
+        }
+    }
+
+    /// Returns a write guard if no one holds the lock.
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<T>> {
+        let state = self.state.load(Ordering::Relaxed);
+        if state & !WAITING != 0 || !compare_exchange(&self.state, state, WRITER) {
+            return None;
+        }
+
+        self.writer.store(aarch64::affinity(), Ordering::Relaxed);
+        Some(RwLockWriteGuard { lock: self })
+    }
+
+    /// Waits for writers to be done, then takes the lock for reading.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this core holds the lock for writing.
+    pub fn read(&self) -> RwLockReadGuard<T> {
+        loop {
+            if let Some(guard) = self.try_read() {
+                return guard;
+            }
+
+            self.check_writer();
+            aarch64::wfe();
+        }
+    }
+
+    /// Waits for readers and writers to be done, then takes the lock for
+    /// writing. New readers wait until it is released.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this core holds the lock for writing.
+    pub fn write(&self) -> RwLockWriteGuard<T> {
+        loop {
+            if let Some(guard) = self.try_write() {
+                return guard;
+            }
+
+            self.check_writer();
+            let state = self.state.load(Ordering::Relaxed);
+            if state & WAITING == 0 {
+                compare_exchange(&self.state, state, state | WAITING);
+            }
+            aarch64::wfe();
+        }
+    }
+
+    /// Panics if this core holds the lock for writing.
+    fn check_writer(&self) {
+        // Only this core stores its own ID, so seeing it means the lock is
+        // held further up this core's stack.
+        let this = aarch64::affinity();
+        if self.state.load(Ordering::Relaxed) & WRITER != 0 && self.writer.load(Ordering::Relaxed) == this {
+            panic!("RwLock locked again on core {}, which holds it for writing", this);
+        }
+    }
+}
+
+impl<'a, T: 'a> Deref for RwLockReadGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T: 'a> Drop for RwLockReadGuard<'a, T> {
+    fn drop(&mut self) {
+        update(&self.lock.state, |state| state - READER);
+        aarch64::sev();
+    }
+}
+
+impl<'a, T: 'a> Deref for RwLockWriteGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T: 'a> DerefMut for RwLockWriteGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<'a, T: 'a> Drop for RwLockWriteGuard<'a, T> {
+    fn drop(&mut self) {
+        // Writers still waiting set `WAITING` again.
+        self.lock.writer.store(NO_WRITER, Ordering::Relaxed);
+        self.lock.state.store(0, Ordering::Release);
+        aarch64::sev();
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.try_read() {
+            Some(guard) => f.debug_struct("RwLock").field("data", &&*guard).finish(),
+            None => f.debug_struct("RwLock").field("data", &"<locked>").finish(),
+        }
+    }
+}
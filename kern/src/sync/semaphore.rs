@@ -0,0 +1,83 @@
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::aarch64;
+use crate::sync::{compare_exchange, update};
+
+/// A counting semaphore: a count of available permits that `acquire()` takes
+/// from, waiting while there are none, and `release()` gives back to.
+pub struct Semaphore {
+    permits: AtomicUsize,
+}
+
+/// A permit, given back to its `Semaphore` when dropped.
+pub struct SemaphoreGuard<'a> {
+    semaphore: &'a Semaphore,
+}
+
+impl Semaphore {
+    /// Returns a semaphore with `permits` permits available.
+    pub const fn new(permits: usize) -> Semaphore {
+        Semaphore { permits: AtomicUsize::new(permits) }
+    }
+
+    /// Returns the number of permits available.
+    pub fn available(&self) -> usize {
+        self.permits.load(Ordering::Relaxed)
+    }
+
+    /// Takes a permit if one is available, returning `true` if one was.
+    pub fn try_acquire(&self) -> bool {
+        // Losing a race to another core does not mean the permits ran out.
+        loop {
+            let permits = self.permits.load(Ordering::Relaxed);
+            if permits == 0 {
+                return false;
+            }
+            if compare_exchange(&self.permits, permits, permits - 1) {
+                return true;
+            }
+        }
+    }
+
+    /// Waits for a permit to be available, then takes it. Sleeps in `wfe`
+    /// only while there are none, since `release()` wakes sleepers with `sev`.
+    pub fn acquire(&self) {
+        while !self.try_acquire() {
+            aarch64::wfe();
+        }
+    }
+
+    /// Gives back a permit.
+    pub fn release(&self) {
+        update(&self.permits, |permits| permits + 1);
+        aarch64::sev();
+    }
+
+    /// Waits for a permit, then returns a guard that gives it back when
+    /// dropped.
+    pub fn access(&self) -> SemaphoreGuard {
+        self.acquire();
+        SemaphoreGuard { semaphore: self }
+    }
+
+    /// Returns a guard holding a permit if one is available.
+    pub fn try_access(&self) -> Option<SemaphoreGuard> {
+        match self.try_acquire() {
+            true => Some(SemaphoreGuard { semaphore: self }),
+            false => None,
+        }
+    }
+}
+
+impl<'a> Drop for SemaphoreGuard<'a> {
+    fn drop(&mut self) {
+        self.semaphore.release()
+    }
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Semaphore").field("available", &self.available()).finish()
+    }
+}
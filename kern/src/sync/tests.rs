@@ -0,0 +1,221 @@
+mod once {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    use crate::sync::Once;
+
+    #[test]
+    fn initializes_once() {
+        let once = Once::new();
+        assert!(!once.is_completed());
+        assert_eq!(once.get(), None);
+        assert_eq!(format!("{:?}", once), "Once { data: \"<uninit>\" }");
+
+        assert_eq!(*once.call_once(|| 1), 1);
+        assert_eq!(*once.call_once(|| 2), 1);
+        assert!(once.is_completed());
+        assert_eq!(once.get(), Some(&1));
+        assert_eq!(format!("{:?}", once), "Once { data: 1 }");
+    }
+
+    #[test]
+    #[should_panic(expected = "from its own initializer")]
+    fn panics_when_initialized_from_itself() {
+        let once = Once::new();
+        once.call_once(|| *once.call_once(|| 1) + 1);
+    }
+
+    #[test]
+    fn racing_threads_see_one_value() {
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+        let once = Arc::new(Once::new());
+        let barrier = Arc::new(Barrier::new(8));
+
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let (once, barrier) = (once.clone(), barrier.clone());
+                thread::spawn(move || {
+                    barrier.wait();
+                    *once.call_once(|| {
+                        RUNS.fetch_add(1, Ordering::SeqCst);
+                        thread::yield_now();
+                        i
+                    })
+                })
+            })
+            .collect();
+
+        let values: Vec<usize> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+        assert_eq!(RUNS.load(Ordering::SeqCst), 1);
+        assert!(values.iter().all(|&v| v == values[0]));
+    }
+}
+
+mod lazy {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    use crate::sync::lazy::Lazy;
+
+    static BUILDS: AtomicUsize = AtomicUsize::new(0);
+
+    fn build() -> Vec<usize> {
+        BUILDS.fetch_add(1, Ordering::SeqCst);
+        (0..100).collect()
+    }
+
+    static TABLE: Lazy<Vec<usize>> = Lazy::new(build);
+
+    #[test]
+    fn initializes_on_first_use() {
+        let lazy: Lazy<usize, _> = Lazy::new(|| 7);
+        assert_eq!(format!("{:?}", lazy), "Lazy { data: \"<uninit>\" }");
+        assert_eq!(*lazy + 1, 8);
+        assert_eq!(*Lazy::force(&lazy), 7);
+        assert_eq!(format!("{:?}", lazy), "Lazy { data: 7 }");
+    }
+
+    #[test]
+    fn statics_build_once() {
+        let threads: Vec<_> = (0..8).map(|_| thread::spawn(|| TABLE.iter().sum::<usize>())).collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), 4950);
+        }
+        assert_eq!(BUILDS.load(Ordering::SeqCst), 1);
+    }
+}
+
+mod rwlock {
+    use std::sync::Arc;
+    use std::thread;
+
+    use crate::sync::rwlock::RwLock;
+
+    #[test]
+    fn readers_share_writers_exclude() {
+        let lock = RwLock::new(5);
+        {
+            let a = lock.read();
+            let b = lock.try_read().expect("readers share the lock");
+            assert_eq!(*a + *b, 10);
+            assert!(lock.try_write().is_none());
+        }
+
+        {
+            let mut w = lock.write();
+            *w += 1;
+            assert!(lock.try_read().is_none());
+            assert!(lock.try_write().is_none());
+            assert_eq!(format!("{:?}", lock), "RwLock { data: \"<locked>\" }");
+        }
+
+        assert_eq!(*lock.read(), 6);
+        assert_eq!(format!("{:?}", lock), "RwLock { data: 6 }");
+    }
+
+    #[test]
+    #[should_panic(expected = "holds it for writing")]
+    fn panics_when_read_by_its_writer() {
+        let lock = RwLock::new(());
+        let _w = lock.write();
+        let _r = lock.read();
+    }
+
+    #[test]
+    fn waiting_writer_keeps_new_readers_out() {
+        let lock = Arc::new(RwLock::new(0));
+        let reader = lock.read();
+
+        let writer = {
+            let lock = lock.clone();
+            thread::spawn(move || *lock.write() += 1)
+        };
+        while lock.try_read().map(drop).is_some() {
+            thread::yield_now();
+        }
+
+        drop(reader);
+        writer.join().unwrap();
+        assert_eq!(*lock.read(), 1);
+    }
+
+    #[test]
+    fn stress() {
+        // Writers keep both halves equal; readers must never see them differ.
+        let lock = Arc::new(RwLock::new((0usize, 0usize)));
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let lock = lock.clone();
+                thread::spawn(move || {
+                    for _ in 0..2_000 {
+                        if i % 4 == 0 {
+                            let mut w = lock.write();
+                            w.0 += 1;
+                            thread::yield_now();
+                            w.1 += 1;
+                        } else {
+                            let r = lock.read();
+                            assert_eq!(r.0, r.1);
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(*lock.read(), (4_000, 4_000));
+    }
+}
+
+mod semaphore {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    use crate::sync::semaphore::Semaphore;
+
+    #[test]
+    fn counts_permits() {
+        let semaphore = Semaphore::new(2);
+        assert!(semaphore.try_acquire());
+        {
+            let _guard = semaphore.try_access().expect("one permit left");
+            assert_eq!(semaphore.available(), 0);
+            assert!(!semaphore.try_acquire());
+            assert!(semaphore.try_access().is_none());
+        }
+
+        assert_eq!(semaphore.available(), 1);
+        semaphore.release();
+        assert_eq!(format!("{:?}", semaphore), "Semaphore { available: 2 }");
+    }
+
+    #[test]
+    fn stress() {
+        // No more than the semaphore's permits are ever held at once.
+        static HELD: AtomicUsize = AtomicUsize::new(0);
+
+        let semaphore = Arc::new(Semaphore::new(3));
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let semaphore = semaphore.clone();
+                thread::spawn(move || {
+                    for _ in 0..2_000 {
+                        let _guard = semaphore.access();
+                        assert!(HELD.fetch_add(1, Ordering::SeqCst) < 3);
+                        thread::yield_now();
+                        HELD.fetch_sub(1, Ordering::SeqCst);
+                    }
+                })
+            })
+            .collect();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(semaphore.available(), 3);
+    }
+}
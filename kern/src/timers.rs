@@ -0,0 +1,352 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use pi::interrupt::{Controller, Interrupt};
+use pi::timer;
+
+use crate::aarch64;
+use crate::mutex::IrqMutex;
+use crate::traps::TrapFrame;
+use crate::IRQ;
+
+/// The resolution of software timers: they fire on the first tick at or
+/// after their deadline.
+pub const TICK: Duration = Duration::from_millis(10);
+
+/// `TICK` in microseconds.
+const TICK_US: u64 = TICK.as_micros() as u64;
+
+/// The number of slots in the wheel. Timers further than this many ticks
+/// away wait in their slot for later turns.
+const SLOTS: usize = 64;
+
+/// Identifies a scheduled timer, to cancel it with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TimerId(u64);
+
+/// A function run when a timer fires, in the timer IRQ handler. It runs with
+/// IRQs masked, so it must not take a lock that code outside IRQ handlers
+/// holds with IRQs unmasked: only `IrqMutex`es, such as the heap's, are safe.
+pub type Callback = Box<dyn FnMut() + Send>;
+
+struct Entry {
+    id: TimerId,
+    /// The tick the timer fires on.
+    deadline: u64,
+    /// The ticks between firings of a periodic timer.
+    period: Option<u64>,
+    callback: Callback,
+}
+
+/// A hashed timing wheel: each timer sits in the slot its deadline falls in,
+/// so advancing a tick only looks at the timers in one slot.
+struct Wheel {
+    slots: Vec<Vec<Entry>>,
+    /// The last tick advanced to.
+    now: u64,
+    next_id: u64,
+    len: usize,
+    /// The periodic timers taken out by the last `advance()`, which
+    /// `reschedule()` puts back unless they were cancelled in between.
+    firing: Vec<TimerId>,
+}
+
+impl Wheel {
+    fn new(now: u64) -> Wheel {
+        Wheel {
+            slots: (0..SLOTS).map(|_| Vec::new()).collect(),
+            now,
+            next_id: 0,
+            len: 0,
+            firing: Vec::new(),
+        }
+    }
+
+    fn insert(&mut self, entry: Entry) {
+        self.slots[entry.deadline as usize % SLOTS].push(entry);
+        self.len += 1;
+    }
+
+    /// Schedules `callback` for the tick `deadline`, which must be after the
+    /// last tick advanced to, and then every `period` ticks if given.
+    fn schedule(&mut self, deadline: u64, period: Option<u64>, callback: Callback) -> TimerId {
+        let id = TimerId(self.next_id);
+        self.next_id += 1;
+        let deadline = deadline.max(self.now + 1);
+        self.insert(Entry { id, deadline, period: period.map(|p| p.max(1)), callback });
+        id
+    }
+
+    /// Removes the timer `id`, returning `false` if it already fired for the
+    /// last time or was cancelled.
+    fn cancel(&mut self, id: TimerId) -> bool {
+        if let Some(i) = self.firing.iter().position(|&firing| firing == id) {
+            self.firing.swap_remove(i);
+            return true;
+        }
+
+        for slot in self.slots.iter_mut() {
+            if let Some(i) = slot.iter().position(|entry| entry.id == id) {
+                slot.swap_remove(i);
+                self.len -= 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Advances to the tick `to`, returning the timers that are due, soonest
+    /// first.
+    fn advance(&mut self, to: u64) -> Vec<Entry> {
+        let mut due = Vec::new();
+        if to <= self.now {
+            return due;
+        }
+
+        let turns = (to - self.now).min(SLOTS as u64);
+        for tick in self.now + 1..=self.now + turns {
+            let slot = &mut self.slots[tick as usize % SLOTS];
+            let mut i = 0;
+            while i < slot.len() {
+                match slot[i].deadline <= to {
+                    true => due.push(slot.swap_remove(i)),
+                    false => i += 1,
+                }
+            }
+        }
+
+        self.now = to;
+        self.len -= due.len();
+        due.sort_by_key(|entry| entry.deadline);
+        self.firing = due.iter().filter(|entry| entry.period.is_some()).map(|entry| entry.id).collect();
+        due
+    }
+
+    /// Puts back the periodic timers among `fired` that were not cancelled
+    /// while they ran, due a period after they last were.
+    fn reschedule(&mut self, fired: Vec<Entry>) {
+        for mut entry in fired {
+            if let (Some(period), Some(i)) = (entry.period, self.firing.iter().position(|&id| id == entry.id)) {
+                self.firing.swap_remove(i);
+                entry.deadline = (entry.deadline + period).max(self.now + 1);
+                self.insert(entry);
+            }
+        }
+        self.firing.clear();
+    }
+
+    /// Returns the earliest deadline of any timer.
+    fn next_deadline(&self) -> Option<u64> {
+        self.slots.iter().flat_map(|slot| slot.iter()).map(|entry| entry.deadline).min()
+    }
+}
+
+/// Returns the number of whole ticks since the system timer started.
+fn now_ticks() -> u64 {
+    timer::current_time().as_micros() as u64 / TICK_US
+}
+
+/// Returns the number of ticks in `t`, rounded up, and at least one.
+fn ticks(t: Duration) -> u64 {
+    let us = t.as_micros() as u64;
+    ((us + TICK_US - 1) / TICK_US).max(1)
+}
+
+/// The kernel's software timers: callbacks run once, or periodically, from
+/// the system timer's IRQ. The timer is only armed for the next deadline,
+/// so nothing runs while no timers are scheduled.
+pub struct Timers(IrqMutex<Option<Wheel>>);
+
+impl Timers {
+    /// Returns an uninitialized `Timers`.
+    ///
+    /// The timers must be initialized by calling `initialize()` before any
+    /// are scheduled.
+    pub const fn uninitialized() -> Timers {
+        Timers(IrqMutex::named("timers", None))
+    }
+
+    /// Starts the timers, handling the system timer's IRQ. Their callbacks
+    /// only run while IRQs are unmasked.
+    pub fn initialize(&'static self) {
+        *self.0.lock() = Some(Wheel::new(now_ticks()));
+        IRQ.register(Interrupt::Timer1, Box::new(move |_: &mut TrapFrame| self.tick()));
+        Controller::new().enable(Interrupt::Timer1);
+    }
+
+    /// Returns `true` once `initialize()` has been called.
+    pub fn is_initialized(&self) -> bool {
+        self.0.lock().is_some()
+    }
+
+    /// Runs `callback` once `t` has passed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the timers are uninitialized.
+    pub fn after<F: FnMut() + Send + 'static>(&self, t: Duration, callback: F) -> TimerId {
+        self.schedule(t, None, Box::new(callback))
+    }
+
+    /// Runs `callback` every time `period` passes, until the timer is
+    /// cancelled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the timers are uninitialized.
+    pub fn every<F: FnMut() + Send + 'static>(&self, period: Duration, callback: F) -> TimerId {
+        self.schedule(period, Some(ticks(period)), Box::new(callback))
+    }
+
+    /// Cancels the timer `id`, returning `false` if it already fired for the
+    /// last time or was cancelled.
+    pub fn cancel(&self, id: TimerId) -> bool {
+        self.0.lock().as_mut().map_or(false, |wheel| wheel.cancel(id))
+    }
+
+    /// Waits for `t` to pass, sleeping in `wfe` between IRQs. Spins instead
+    /// if the timers cannot wake this core: before they are initialized, or
+    /// while IRQs are masked.
+    pub fn sleep(&self, t: Duration) {
+        if !self.is_initialized() || aarch64::daif() & aarch64::DAIF_I != 0 {
+            return timer::spin_sleep(t);
+        }
+
+        // Other IRQs wake this core early too, so check the time on waking.
+        let end = timer::current_time() + t;
+        self.after(t, aarch64::sev);
+        while timer::current_time() < end {
+            aarch64::wfe();
+        }
+    }
+
+    fn schedule(&self, t: Duration, period: Option<u64>, callback: Callback) -> TimerId {
+        let mut guard = self.0.lock();
+        let wheel = guard.as_mut().expect("timers uninitialized");
+        // Round the deadline itself up, so that the timer never fires early.
+        let id = wheel.schedule(ticks(timer::current_time() + t), period, callback);
+        arm(wheel);
+        id
+    }
+
+    /// Runs the timers that are due, then arms the system timer for the
+    /// next one. Called from the system timer's IRQ.
+    fn tick(&self) {
+        timer::Timer::new().acknowledge();
+
+        let mut due = match self.0.lock().as_mut() {
+            Some(wheel) => wheel.advance(now_ticks()),
+            None => return,
+        };
+
+        // Callbacks may schedule or cancel timers themselves.
+        for entry in due.iter_mut() {
+            (entry.callback)();
+        }
+
+        if let Some(wheel) = self.0.lock().as_mut() {
+            wheel.reschedule(due);
+            arm(wheel);
+        }
+    }
+}
+
+/// Arms the system timer for the wheel's next deadline, if it has one.
+fn arm(wheel: &Wheel) {
+    if let Some(deadline) = wheel.next_deadline() {
+        let now = timer::current_time().as_micros() as u64;
+        timer::tick_in(Duration::from_micros((deadline * TICK_US).saturating_sub(now)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::vec::Vec;
+    use core::time::Duration;
+    use std::sync::{Arc, Mutex};
+
+    use super::{ticks, Wheel, SLOTS, TICK};
+
+    /// Returns a callback that appends `n` to `log`.
+    fn log(log: &Arc<Mutex<Vec<u64>>>, n: u64) -> Box<dyn FnMut() + Send> {
+        let log = log.clone();
+        Box::new(move || log.lock().unwrap().push(n))
+    }
+
+    /// Advances `wheel` to `to`, running and rescheduling what is due.
+    fn run(wheel: &mut Wheel, to: u64) {
+        let mut due = wheel.advance(to);
+        for entry in due.iter_mut() {
+            (entry.callback)();
+        }
+        wheel.reschedule(due);
+    }
+
+    #[test]
+    fn rounds_durations_up_to_ticks() {
+        assert_eq!(ticks(TICK), 1);
+        assert_eq!(ticks(TICK / 3), 1);
+        assert_eq!(ticks(TICK * 2 + TICK / 10), 3);
+        assert_eq!(ticks(Duration::from_secs(0)), 1);
+    }
+
+    #[test]
+    fn fires_one_shots_in_deadline_order() {
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let mut wheel = Wheel::new(100);
+        wheel.schedule(105, None, log(&fired, 5));
+        wheel.schedule(102, None, log(&fired, 2));
+        wheel.schedule(100 + SLOTS as u64 + 2, None, log(&fired, 66));
+        assert_eq!(wheel.next_deadline(), Some(102));
+
+        run(&mut wheel, 101);
+        assert!(fired.lock().unwrap().is_empty());
+        run(&mut wheel, 110);
+        assert_eq!(*fired.lock().unwrap(), [2, 5]);
+
+        // The far timer shares a slot with tick 102, but not its turn.
+        run(&mut wheel, 100 + SLOTS as u64 + 1);
+        assert_eq!(wheel.len, 1);
+        run(&mut wheel, 1_000);
+        assert_eq!(*fired.lock().unwrap(), [2, 5, 66]);
+        assert_eq!(wheel.len, 0);
+        assert_eq!(wheel.next_deadline(), None);
+    }
+
+    #[test]
+    fn repeats_periodic_timers_until_cancelled() {
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let mut wheel = Wheel::new(0);
+        let id = wheel.schedule(3, Some(3), log(&fired, 3));
+
+        for tick in 1..=10 {
+            run(&mut wheel, tick);
+        }
+        assert_eq!(fired.lock().unwrap().len(), 3);
+        assert_eq!(wheel.next_deadline(), Some(12));
+
+        // Missed deadlines fire once, not once per period missed.
+        run(&mut wheel, 40);
+        assert_eq!(fired.lock().unwrap().len(), 4);
+        assert_eq!(wheel.next_deadline(), Some(41));
+
+        assert!(wheel.cancel(id));
+        assert!(!wheel.cancel(id));
+        run(&mut wheel, 100);
+        assert_eq!(fired.lock().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn cancels_periodic_timers_while_they_fire() {
+        let mut wheel = Wheel::new(0);
+        let id = wheel.schedule(1, Some(1), Box::new(|| {}));
+
+        let due = wheel.advance(1);
+        assert!(wheel.cancel(id));
+        wheel.reschedule(due);
+        assert_eq!(wheel.len, 0);
+        assert_eq!(wheel.next_deadline(), None);
+    }
+}
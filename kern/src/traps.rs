@@ -0,0 +1,118 @@
+mod frame;
+mod syndrome;
+
+#[cfg(test)]
+mod tests;
+
+use core::fmt;
+
+use crate::console::kprintln;
+use crate::shell::Shell;
+use crate::IRQ;
+
+pub use self::frame::TrapFrame;
+pub use self::syndrome::{Fault, Syndrome};
+
+#[repr(u16)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Kind {
+    Synchronous = 0,
+    Irq = 1,
+    Fiq = 2,
+    SError = 3,
+}
+
+#[repr(u16)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Source {
+    CurrentSpEl0 = 0,
+    CurrentSpElx = 1,
+    LowerAArch64 = 2,
+    LowerAArch32 = 3,
+}
+
+/// Which vector took the exception, as passed by `init.s`.
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Info {
+    pub source: Source,
+    pub kind: Kind,
+}
+
+impl fmt::Display for Info {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            Kind::Synchronous => "synchronous exception",
+            Kind::Irq => "IRQ",
+            Kind::Fiq => "FIQ",
+            Kind::SError => "SError",
+        };
+        let source = match self.source {
+            Source::CurrentSpEl0 => "the current EL using SP_EL0",
+            Source::CurrentSpElx => "the current EL using SP_ELx",
+            Source::LowerAArch64 => "a lower EL in AArch64",
+            Source::LowerAArch32 => "a lower EL in AArch32",
+        };
+        write!(f, "{} from {}", kind, source)
+    }
+}
+
+/// A readable account of an exception: what was taken, why, and the state
+/// of the interrupted code.
+pub struct Report<'a> {
+    pub info: Info,
+    pub syndrome: Option<Syndrome>,
+    pub frame: &'a TrapFrame,
+}
+
+impl<'a> fmt::Display for Report<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.info)?;
+        if let Some(syndrome) = self.syndrome {
+            write!(f, ": {}", syndrome)?;
+            match syndrome {
+                Syndrome::DataAbort { .. } | Syndrome::InstructionAbort { .. } => {
+                    write!(f, " at {:#x}", self.frame.far)?
+                }
+                _ => {}
+            }
+        }
+        writeln!(f)?;
+        write!(f, "{}", self.frame)
+    }
+}
+
+/// Handles every exception taken through the vector table in `init.s`.
+///
+/// IRQs go to the handlers registered with `IRQ`. A `brk` instruction stops
+/// in a debugger shell showing the report, and execution resumes after it
+/// once the shell exits. Anything else is unexpected and panics with the
+/// report.
+#[no_mangle]
+pub extern "C" fn handle_exception(info: Info, esr: u32, tf: &mut TrapFrame) {
+    if info.kind == Kind::Irq {
+        IRQ.dispatch(tf);
+        return;
+    }
+
+    let syndrome = match info.kind {
+        Kind::Synchronous => Some(Syndrome::from(esr)),
+        _ => None,
+    };
+
+    if let Some(Syndrome::Brk(_)) = syndrome {
+        debug(Report { info, syndrome, frame: tf });
+        tf.elr += 4;
+        return;
+    }
+
+    panic!("unhandled {}", Report { info, syndrome, frame: tf });
+}
+
+/// Runs a shell on the console until it exits.
+fn debug(report: Report) {
+    kprintln!("---------- BREAKPOINT ----------");
+    kprintln!("{}", report);
+    kprintln!("`exit` resumes execution");
+    Shell::new().interact("debug> ");
+}
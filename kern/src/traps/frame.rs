@@ -0,0 +1,44 @@
+use core::fmt;
+
+use shim::const_assert_size;
+
+/// The state of the interrupted code, saved by `context_save` on the stack
+/// of the exception level that took the exception. Changes made to it take
+/// effect when the handler returns.
+///
+/// The layout must match the offsets `init.s` uses.
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug)]
+pub struct TrapFrame {
+    /// `x0` through `x30`; `x30` is the link register.
+    pub x: [u64; 31],
+    __reserved: u64,
+    /// The SIMD/FP registers `q0` through `q31`.
+    pub q: [u128; 32],
+    /// Where execution resumes: the faulting instruction for aborts and
+    /// `brk`, the one after it for `svc`.
+    pub elr: u64,
+    pub spsr: u64,
+    pub esr: u64,
+    /// The faulting address, for aborts.
+    pub far: u64,
+    /// The stack pointer before the exception; changing it has no effect.
+    pub sp: u64,
+    __reserved2: u64,
+}
+
+const_assert_size!(TrapFrame, 816);
+
+impl fmt::Display for TrapFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "  elr  {:#018x}   spsr {:#018x}   sp  {:#018x}", self.elr, self.spsr, self.sp)?;
+        writeln!(f, "  esr  {:#018x}   far  {:#018x}", self.esr, self.far)?;
+        for (row, regs) in self.x.chunks(4).enumerate() {
+            for (col, reg) in regs.iter().enumerate() {
+                write!(f, "  x{:<3} {:#018x} ", row * 4 + col, reg)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
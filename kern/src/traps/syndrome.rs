@@ -0,0 +1,135 @@
+use core::fmt;
+
+/// The cause of an instruction or data abort, from the fault status code in
+/// the low six bits of the ESR's ISS (ref: D12.2.36).
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Fault {
+    AddressSize,
+    Translation,
+    AccessFlag,
+    Permission,
+    Alignment,
+    TlbConflict,
+    Other(u8),
+}
+
+impl From<u32> for Fault {
+    fn from(esr: u32) -> Fault {
+        use self::Fault::*;
+
+        let code = (esr & 0b111111) as u8;
+        match code >> 2 {
+            0b0000 => AddressSize,
+            0b0001 => Translation,
+            0b0010 => AccessFlag,
+            0b0011 => Permission,
+            _ => match code {
+                0b100001 => Alignment,
+                0b110000 => TlbConflict,
+                _ => Other(code),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Fault::*;
+
+        match *self {
+            AddressSize => write!(f, "address size fault"),
+            Translation => write!(f, "translation fault"),
+            AccessFlag => write!(f, "access flag fault"),
+            Permission => write!(f, "permission fault"),
+            Alignment => write!(f, "alignment fault"),
+            TlbConflict => write!(f, "TLB conflict"),
+            Other(code) => write!(f, "fault status {:#08b}", code),
+        }
+    }
+}
+
+/// What caused a synchronous exception, decoded from the exception class in
+/// bits 26 to 31 of the ESR (ref: D12.2.36).
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Syndrome {
+    /// An undefined instruction, or an exception with no other class.
+    Unknown,
+    WfiWfe,
+    SimdFp,
+    IllegalExecutionState,
+    Svc(u16),
+    Hvc(u16),
+    Smc(u16),
+    MsrMrsSystem,
+    InstructionAbort { kind: Fault, level: u8 },
+    PCAlignmentFault,
+    DataAbort { kind: Fault, level: u8, write: bool },
+    SpAlignmentFault,
+    TrappedFpu,
+    SError,
+    Breakpoint,
+    Step,
+    Watchpoint,
+    Brk(u16),
+    Other(u32),
+}
+
+impl From<u32> for Syndrome {
+    fn from(esr: u32) -> Syndrome {
+        use self::Syndrome::*;
+
+        let iss = esr & ((1 << 25) - 1);
+        let imm = iss as u16;
+        let level = (iss & 0b11) as u8;
+        match esr >> 26 {
+            0b000000 => Unknown,
+            0b000001 => WfiWfe,
+            0b000111 => SimdFp,
+            0b001110 => IllegalExecutionState,
+            0b010001 | 0b010101 => Svc(imm),
+            0b010010 | 0b010110 => Hvc(imm),
+            0b010011 | 0b010111 => Smc(imm),
+            0b011000 => MsrMrsSystem,
+            0b100000 | 0b100001 => InstructionAbort { kind: Fault::from(iss), level },
+            0b100010 => PCAlignmentFault,
+            0b100100 | 0b100101 => DataAbort { kind: Fault::from(iss), level, write: iss & (1 << 6) != 0 },
+            0b100110 => SpAlignmentFault,
+            0b101000 | 0b101100 => TrappedFpu,
+            0b101111 => SError,
+            0b110000 | 0b110001 => Breakpoint,
+            0b110010 | 0b110011 => Step,
+            0b110100 | 0b110101 => Watchpoint,
+            0b111100 => Brk(imm),
+            _ => Other(esr),
+        }
+    }
+}
+
+impl fmt::Display for Syndrome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Syndrome::*;
+
+        match *self {
+            Unknown => write!(f, "undefined instruction"),
+            WfiWfe => write!(f, "trapped wfi/wfe"),
+            SimdFp => write!(f, "trapped SIMD/FP access"),
+            IllegalExecutionState => write!(f, "illegal execution state"),
+            Svc(imm) => write!(f, "svc #{}", imm),
+            Hvc(imm) => write!(f, "hvc #{}", imm),
+            Smc(imm) => write!(f, "smc #{}", imm),
+            MsrMrsSystem => write!(f, "trapped msr/mrs/system instruction"),
+            InstructionAbort { kind, level } => write!(f, "instruction abort: {} at level {}", kind, level),
+            PCAlignmentFault => write!(f, "misaligned pc"),
+            DataAbort { kind, level, write: true } => write!(f, "data abort writing: {} at level {}", kind, level),
+            DataAbort { kind, level, write: false } => write!(f, "data abort reading: {} at level {}", kind, level),
+            SpAlignmentFault => write!(f, "misaligned sp"),
+            TrappedFpu => write!(f, "trapped floating-point exception"),
+            SError => write!(f, "SError"),
+            Breakpoint => write!(f, "hardware breakpoint"),
+            Step => write!(f, "software step"),
+            Watchpoint => write!(f, "watchpoint"),
+            Brk(imm) => write!(f, "brk #{}", imm),
+            Other(esr) => write!(f, "exception class {:#08b}", esr >> 26),
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use core::mem::size_of;
+
+use super::{Fault, Info, Kind, Report, Source, Syndrome, TrapFrame};
+
+#[test]
+fn frame_matches_init_offsets() {
+    let tf = TrapFrame::default();
+    let base = &tf as *const TrapFrame as usize;
+    let offset = |field: *const u64| field as usize - base;
+
+    assert_eq!(offset(&tf.x[30]), 240);
+    assert_eq!(&tf.q[0] as *const u128 as usize - base, 256);
+    assert_eq!(offset(&tf.elr), 768);
+    assert_eq!(offset(&tf.spsr), 776);
+    assert_eq!(offset(&tf.esr), 784);
+    assert_eq!(offset(&tf.far), 792);
+    assert_eq!(offset(&tf.sp), 800);
+    assert_eq!(size_of::<TrapFrame>() % 16, 0);
+    assert_eq!(size_of::<Info>(), 4);
+}
+
+#[test]
+fn decodes_exception_classes() {
+    assert_eq!(Syndrome::from(0x0200_0000), Syndrome::Unknown);
+    assert_eq!(Syndrome::from(0x5600_002a), Syndrome::Svc(42));
+    assert_eq!(Syndrome::from(0xf200_0001), Syndrome::Brk(1));
+    assert_eq!(Syndrome::from(0x9a00_0000), Syndrome::SpAlignmentFault);
+    assert_eq!(Syndrome::from(0xfc00_0000), Syndrome::Other(0xfc00_0000));
+}
+
+#[test]
+fn decodes_aborts() {
+    assert_eq!(
+        Syndrome::from(0x9600_0045),
+        Syndrome::DataAbort { kind: Fault::Translation, level: 1, write: true }
+    );
+    assert_eq!(
+        Syndrome::from(0x9600_0021),
+        Syndrome::DataAbort { kind: Fault::Alignment, level: 1, write: false }
+    );
+    assert_eq!(
+        Syndrome::from(0x8600_000f),
+        Syndrome::InstructionAbort { kind: Fault::Permission, level: 3 }
+    );
+    assert_eq!(Fault::from(0b010000), Fault::Other(0b010000));
+}
+
+#[test]
+fn reports_fault_address_and_registers() {
+    let mut tf = TrapFrame::default();
+    tf.far = 0xdead_0000;
+    tf.x[2] = 0x1234;
+    tf.esr = 0x9600_0045;
+
+    let info = Info { source: Source::CurrentSpElx, kind: Kind::Synchronous };
+    let report = format!("{}", Report { info, syndrome: Some(Syndrome::from(tf.esr as u32)), frame: &tf });
+    let mut lines = report.lines();
+    assert_eq!(
+        lines.next(),
+        Some(
+            "synchronous exception from the current EL using SP_ELx: \
+             data abort writing: translation fault at level 1 at 0xdead0000"
+        )
+    );
+    assert!(report.contains("x2   0x0000000000001234"));
+    assert!(report.contains("x30  0x0000000000000000"));
+}
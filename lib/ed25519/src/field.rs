@@ -0,0 +1,171 @@
+//! Arithmetic in GF(2^255 - 19).
+//!
+//! An element is stored as sixteen signed 64-bit limbs of 16 bits each, the
+//! representation used by TweetNaCl. It is slow compared to the 51-bit limb
+//! implementations but small and easy to check, which is what the bootloader
+//! needs: it verifies exactly one signature per boot.
+
+/// A field element.
+pub type Fe = [i64; 16];
+
+pub const ZERO: Fe = [0; 16];
+pub const ONE: Fe = [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+/// The curve constant `d = -121665 / 121666`.
+pub const D: Fe = [
+    0x78a3, 0x1359, 0x4dca, 0x75eb, 0xd8ab, 0x4141, 0x0a4d, 0x0070,
+    0xe898, 0x7779, 0x4079, 0x8cc7, 0xfe73, 0x2b6f, 0x6cee, 0x5203,
+];
+
+/// `2 * d`.
+pub const D2: Fe = [
+    0xf159, 0x26b2, 0x9b94, 0xebd6, 0xb156, 0x8283, 0x149a, 0x00e0,
+    0xd130, 0xeef3, 0x80f2, 0x198e, 0xfce7, 0x56df, 0xd9dc, 0x2406,
+];
+
+/// `sqrt(-1)`.
+pub const SQRT_M1: Fe = [
+    0xa0b0, 0x4a0e, 0x1b27, 0xc4ee, 0xe478, 0xad2f, 0x1806, 0x2f43,
+    0xd7a7, 0x3dfb, 0x0099, 0x2b4d, 0xdf0b, 0x4fc1, 0x2480, 0x2b83,
+];
+
+/// Propagates carries so every limb is back in 16 bits.
+fn carry(o: &mut Fe) {
+    for i in 0..16 {
+        o[i] += 1 << 16;
+        let c = o[i] >> 16;
+        if i < 15 {
+            o[i + 1] += c - 1;
+        } else {
+            o[0] += 38 * (c - 1);
+        }
+        o[i] -= c << 16;
+    }
+}
+
+/// Swaps `p` and `q` if `b` is 1; leaves them alone if `b` is 0.
+fn select(p: &mut Fe, q: &mut Fe, b: i64) {
+    let c = !(b - 1);
+    for i in 0..16 {
+        let t = c & (p[i] ^ q[i]);
+        p[i] ^= t;
+        q[i] ^= t;
+    }
+}
+
+/// Returns the canonical little-endian encoding of `n`.
+pub fn pack(n: &Fe) -> [u8; 32] {
+    let mut t = *n;
+    carry(&mut t);
+    carry(&mut t);
+    carry(&mut t);
+
+    for _ in 0..2 {
+        let mut m = ZERO;
+        m[0] = t[0] - 0xffed;
+        for i in 1..15 {
+            m[i] = t[i] - 0xffff - ((m[i - 1] >> 16) & 1);
+            m[i - 1] &= 0xffff;
+        }
+        m[15] = t[15] - 0x7fff - ((m[14] >> 16) & 1);
+        let b = (m[15] >> 16) & 1;
+        m[14] &= 0xffff;
+        select(&mut t, &mut m, 1 - b);
+    }
+
+    let mut out = [0; 32];
+    for i in 0..16 {
+        out[2 * i] = t[i] as u8;
+        out[2 * i + 1] = (t[i] >> 8) as u8;
+    }
+
+    out
+}
+
+/// Decodes a little-endian field element, ignoring the top bit.
+pub fn unpack(n: &[u8; 32]) -> Fe {
+    let mut o = ZERO;
+    for i in 0..16 {
+        o[i] = n[2 * i] as i64 + ((n[2 * i + 1] as i64) << 8);
+    }
+    o[15] &= 0x7fff;
+    o
+}
+
+/// Returns `true` if `a` and `b` encode different field elements.
+pub fn not_equal(a: &Fe, b: &Fe) -> bool {
+    pack(a) != pack(b)
+}
+
+/// Returns the low bit of the canonical encoding of `a`.
+pub fn parity(a: &Fe) -> u8 {
+    pack(a)[0] & 1
+}
+
+pub fn add(a: &Fe, b: &Fe) -> Fe {
+    let mut o = ZERO;
+    for i in 0..16 {
+        o[i] = a[i] + b[i];
+    }
+    o
+}
+
+pub fn sub(a: &Fe, b: &Fe) -> Fe {
+    let mut o = ZERO;
+    for i in 0..16 {
+        o[i] = a[i] - b[i];
+    }
+    o
+}
+
+pub fn mul(a: &Fe, b: &Fe) -> Fe {
+    let mut t = [0i64; 31];
+    for i in 0..16 {
+        for j in 0..16 {
+            t[i + j] += a[i] * b[j];
+        }
+    }
+
+    for i in 0..15 {
+        t[i] += 38 * t[i + 16];
+    }
+
+    let mut o = ZERO;
+    o.copy_from_slice(&t[..16]);
+    carry(&mut o);
+    carry(&mut o);
+    o
+}
+
+pub fn square(a: &Fe) -> Fe {
+    mul(a, a)
+}
+
+/// Returns `i^-1`, computed as `i^(p - 2)`.
+pub fn invert(i: &Fe) -> Fe {
+    let mut c = *i;
+    for a in (0..=253).rev() {
+        c = square(&c);
+        if a != 2 && a != 4 {
+            c = mul(&c, i);
+        }
+    }
+    c
+}
+
+/// Returns `i^((p - 5) / 8)`, used for square roots during decompression.
+pub fn pow2523(i: &Fe) -> Fe {
+    let mut c = *i;
+    for a in (0..=250).rev() {
+        c = square(&c);
+        if a != 1 {
+            c = mul(&c, i);
+        }
+    }
+    c
+}
+
+/// Swaps `p` and `q` in constant time if `b` is 1.
+pub fn swap(p: &mut Fe, q: &mut Fe, b: u8) {
+    select(p, q, b as i64)
+}
@@ -0,0 +1,199 @@
+#![cfg_attr(not(test), no_std)]
+
+//! A small, dependency-free implementation of Ed25519 (RFC 8032).
+//!
+//! The crate is `no_std` so that the bootloader can verify kernel images
+//! before jumping to them. Signing is also provided for the host-side tools;
+//! it is _not_ constant time and should not be used where timing side
+//! channels matter.
+
+mod field;
+mod point;
+mod scalar;
+mod sha512;
+
+#[cfg(test)]
+mod tests;
+
+use core::fmt;
+
+pub use crate::sha512::Sha512;
+
+/// Length of a public key in bytes.
+pub const PUBLIC_KEY_LENGTH: usize = 32;
+
+/// Length of a secret key (the seed) in bytes.
+pub const SECRET_KEY_LENGTH: usize = 32;
+
+/// Length of a signature in bytes.
+pub const SIGNATURE_LENGTH: usize = 64;
+
+/// Error type for signature verification failures.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Error {
+    /// The public key is not the encoding of a curve point.
+    InvalidPublicKey,
+    /// The signature is malformed: its `S` half is not reduced modulo the
+    /// group order.
+    MalformedSignature,
+    /// The signature is well-formed but does not match the message and key.
+    BadSignature,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::InvalidPublicKey => write!(f, "invalid public key"),
+            Error::MalformedSignature => write!(f, "malformed signature"),
+            Error::BadSignature => write!(f, "signature does not match"),
+        }
+    }
+}
+
+/// An Ed25519 public key.
+#[derive(Copy, Clone, PartialEq)]
+pub struct PublicKey(pub [u8; PUBLIC_KEY_LENGTH]);
+
+/// An Ed25519 signature: the encoded point `R` followed by the scalar `S`.
+#[derive(Copy, Clone)]
+pub struct Signature(pub [u8; SIGNATURE_LENGTH]);
+
+/// A secret key together with its public key.
+pub struct Keypair {
+    seed: [u8; SECRET_KEY_LENGTH],
+    public: PublicKey,
+}
+
+impl PublicKey {
+    /// Verifies that `signature` is a valid signature of `message` under this
+    /// key.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidPublicKey` if `self` is not a point on the
+    /// curve, `Error::MalformedSignature` if the signature is not in canonical
+    /// form and `Error::BadSignature` if the signature does not match.
+    pub fn verify(&self, message: &[u8], signature: &Signature) -> Result<(), Error> {
+        let mut r = [0; 32];
+        let mut s = [0; 32];
+        r.copy_from_slice(&signature.0[..32]);
+        s.copy_from_slice(&signature.0[32..]);
+
+        if !scalar::is_canonical(&s) {
+            return Err(Error::MalformedSignature);
+        }
+
+        let minus_a = point::unpack_negated(&self.0).ok_or(Error::InvalidPublicKey)?;
+
+        let mut hasher = Sha512::new();
+        hasher.update(&r);
+        hasher.update(&self.0);
+        hasher.update(message);
+        let h = scalar::reduce(&hasher.finalize());
+
+        // Check that s * B - h * A == R.
+        let mut p = point::scalar_mul(&minus_a, &h);
+        point::add(&mut p, &point::scalar_mul_base(&s));
+
+        if point::pack(&p) == r {
+            Ok(())
+        } else {
+            Err(Error::BadSignature)
+        }
+    }
+}
+
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PublicKey(")?;
+        for byte in self.0.iter() {
+            write!(f, "{:02x}", byte)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl Signature {
+    /// Returns a signature from its 64 byte encoding.
+    pub fn from_bytes(bytes: &[u8; SIGNATURE_LENGTH]) -> Signature {
+        Signature(*bytes)
+    }
+}
+
+impl fmt::Debug for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Signature(")?;
+        for byte in self.0.iter() {
+            write!(f, "{:02x}", byte)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl PartialEq for Signature {
+    fn eq(&self, other: &Signature) -> bool {
+        self.0[..] == other.0[..]
+    }
+}
+
+impl Keypair {
+    /// Derives a key pair from a 32 byte secret seed. The seed should come
+    /// from a cryptographically secure random number generator.
+    pub fn from_seed(seed: &[u8; SECRET_KEY_LENGTH]) -> Keypair {
+        let a = Self::expand(seed).0;
+        Keypair {
+            seed: *seed,
+            public: PublicKey(point::pack(&point::scalar_mul_base(&a))),
+        }
+    }
+
+    /// Returns the secret seed of this key pair.
+    pub fn seed(&self) -> &[u8; SECRET_KEY_LENGTH] {
+        &self.seed
+    }
+
+    /// Returns the public half of this key pair.
+    pub fn public(&self) -> PublicKey {
+        self.public
+    }
+
+    /// Signs `message` and returns the signature.
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        let (a, prefix) = Self::expand(&self.seed);
+
+        let mut hasher = Sha512::new();
+        hasher.update(&prefix);
+        hasher.update(message);
+        let r = scalar::reduce(&hasher.finalize());
+        let big_r = point::pack(&point::scalar_mul_base(&r));
+
+        let mut hasher = Sha512::new();
+        hasher.update(&big_r);
+        hasher.update(&self.public.0);
+        hasher.update(message);
+        let h = scalar::reduce(&hasher.finalize());
+
+        let s = scalar::mul_add(&h, &a, &r);
+
+        let mut signature = [0; SIGNATURE_LENGTH];
+        signature[..32].copy_from_slice(&big_r);
+        signature[32..].copy_from_slice(&s);
+        Signature(signature)
+    }
+
+    /// Hashes `seed` into the clamped secret scalar and the nonce prefix.
+    fn expand(seed: &[u8; SECRET_KEY_LENGTH]) -> ([u8; 32], [u8; 32]) {
+        let hash = Sha512::digest(seed);
+
+        let mut a = [0; 32];
+        let mut prefix = [0; 32];
+        a.copy_from_slice(&hash[..32]);
+        prefix.copy_from_slice(&hash[32..]);
+
+        a[0] &= 248;
+        a[31] &= 127;
+        a[31] |= 64;
+
+        (a, prefix)
+    }
+}
@@ -0,0 +1,120 @@
+//! Points on the twisted Edwards curve in extended coordinates `(X, Y, Z, T)`.
+
+use crate::field::{self, Fe};
+
+pub type Point = [Fe; 4];
+
+/// The base point `B`, in affine coordinates.
+const BASE_X: Fe = [
+    0xd51a, 0x8f25, 0x2d60, 0xc956, 0xa7b2, 0x9525, 0xc760, 0x692c,
+    0xdc5c, 0xfdd6, 0xe231, 0xc0a4, 0x53fe, 0xcd6e, 0x36d3, 0x2169,
+];
+
+const BASE_Y: Fe = [
+    0x6658, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666,
+    0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666,
+];
+
+/// The neutral element.
+const IDENTITY: Point = [field::ZERO, field::ONE, field::ONE, field::ZERO];
+
+/// Sets `p` to `p + q`.
+pub fn add(p: &mut Point, q: &Point) {
+    let a = field::mul(&field::sub(&p[1], &p[0]), &field::sub(&q[1], &q[0]));
+    let b = field::mul(&field::add(&p[0], &p[1]), &field::add(&q[0], &q[1]));
+    let c = field::mul(&field::mul(&p[3], &q[3]), &field::D2);
+    let d = field::mul(&p[2], &q[2]);
+    let d = field::add(&d, &d);
+
+    let e = field::sub(&b, &a);
+    let f = field::sub(&d, &c);
+    let g = field::add(&d, &c);
+    let h = field::add(&b, &a);
+
+    p[0] = field::mul(&e, &f);
+    p[1] = field::mul(&h, &g);
+    p[2] = field::mul(&g, &f);
+    p[3] = field::mul(&e, &h);
+}
+
+fn swap(p: &mut Point, q: &mut Point, b: u8) {
+    for i in 0..4 {
+        field::swap(&mut p[i], &mut q[i], b);
+    }
+}
+
+/// Returns the 32 byte compressed encoding of `p`.
+pub fn pack(p: &Point) -> [u8; 32] {
+    let zi = field::invert(&p[2]);
+    let x = field::mul(&p[0], &zi);
+    let y = field::mul(&p[1], &zi);
+
+    let mut out = field::pack(&y);
+    out[31] ^= field::parity(&x) << 7;
+    out
+}
+
+/// Returns `s * q`, where `s` is a little-endian 256-bit scalar.
+pub fn scalar_mul(q: &Point, s: &[u8; 32]) -> Point {
+    let mut p = IDENTITY;
+    let mut q = *q;
+
+    for i in (0..256).rev() {
+        let b = (s[i / 8] >> (i & 7)) & 1;
+        swap(&mut p, &mut q, b);
+        let p_copy = p;
+        add(&mut q, &p_copy);
+        add(&mut p, &p_copy);
+        swap(&mut p, &mut q, b);
+    }
+
+    p
+}
+
+/// Returns `s * B` for the base point `B`.
+pub fn scalar_mul_base(s: &[u8; 32]) -> Point {
+    let base = [BASE_X, BASE_Y, field::ONE, field::mul(&BASE_X, &BASE_Y)];
+    scalar_mul(&base, s)
+}
+
+/// Decodes a compressed point and returns its _negation_, which is what
+/// signature verification needs. Returns `None` if `bytes` is not the encoding
+/// of a point on the curve.
+pub fn unpack_negated(bytes: &[u8; 32]) -> Option<Point> {
+    let one = field::ONE;
+    let y = field::unpack(bytes);
+
+    let num = field::square(&y);
+    let den = field::mul(&num, &field::D);
+    let num = field::sub(&num, &one);
+    let den = field::add(&one, &den);
+
+    let den2 = field::square(&den);
+    let den4 = field::square(&den2);
+    let den6 = field::mul(&den4, &den2);
+    let mut t = field::mul(&den6, &num);
+    t = field::mul(&t, &den);
+
+    t = field::pow2523(&t);
+    t = field::mul(&t, &num);
+    t = field::mul(&t, &den);
+    t = field::mul(&t, &den);
+    let mut x = field::mul(&t, &den);
+
+    let check = field::mul(&field::square(&x), &den);
+    if field::not_equal(&check, &num) {
+        x = field::mul(&x, &field::SQRT_M1);
+    }
+
+    let check = field::mul(&field::square(&x), &den);
+    if field::not_equal(&check, &num) {
+        return None;
+    }
+
+    if field::parity(&x) == (bytes[31] >> 7) {
+        x = field::sub(&field::ZERO, &x);
+    }
+
+    let t = field::mul(&x, &y);
+    Some([x, y, one, t])
+}
@@ -0,0 +1,83 @@
+//! Arithmetic modulo the group order
+//! `L = 2^252 + 27742317777372353535851937790883648493`.
+
+/// `L` in little-endian bytes.
+const L: [i64; 32] = [
+    0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58,
+    0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0x10,
+];
+
+/// Reduces the 512-bit little-endian number in `x` (one byte per limb) modulo
+/// `L`.
+fn mod_l(x: &mut [i64; 64]) -> [u8; 32] {
+    for i in (32..64).rev() {
+        let mut carry = 0;
+        let mut j = i - 32;
+        while j < i - 12 {
+            x[j] += carry - 16 * x[i] * L[j - (i - 32)];
+            carry = (x[j] + 128) >> 8;
+            x[j] -= carry << 8;
+            j += 1;
+        }
+        x[j] += carry;
+        x[i] = 0;
+    }
+
+    let mut carry = 0;
+    for j in 0..32 {
+        x[j] += carry - (x[31] >> 4) * L[j];
+        carry = x[j] >> 8;
+        x[j] &= 255;
+    }
+
+    for j in 0..32 {
+        x[j] -= carry * L[j];
+    }
+
+    let mut r = [0; 32];
+    for i in 0..32 {
+        x[i + 1] += x[i] >> 8;
+        r[i] = (x[i] & 255) as u8;
+    }
+
+    r
+}
+
+/// Reduces a 64 byte hash output modulo `L`.
+pub fn reduce(hash: &[u8; 64]) -> [u8; 32] {
+    let mut x = [0i64; 64];
+    for (limb, byte) in x.iter_mut().zip(hash.iter()) {
+        *limb = *byte as i64;
+    }
+    mod_l(&mut x)
+}
+
+/// Returns `(a * b + c) mod L`.
+pub fn mul_add(a: &[u8; 32], b: &[u8; 32], c: &[u8; 32]) -> [u8; 32] {
+    let mut x = [0i64; 64];
+    for i in 0..32 {
+        x[i] = c[i] as i64;
+    }
+
+    for i in 0..32 {
+        for j in 0..32 {
+            x[i + j] += a[i] as i64 * b[j] as i64;
+        }
+    }
+
+    mod_l(&mut x)
+}
+
+/// Returns `true` if `s < L`, i.e. `s` is a canonical scalar encoding.
+pub fn is_canonical(s: &[u8; 32]) -> bool {
+    for i in (0..32).rev() {
+        let (s, l) = (s[i] as i64, L[i]);
+        if s != l {
+            return s < l;
+        }
+    }
+
+    false
+}
@@ -0,0 +1,121 @@
+use super::*;
+
+fn hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn array32(s: &str) -> [u8; 32] {
+    let mut out = [0; 32];
+    out.copy_from_slice(&hex(s));
+    out
+}
+
+fn signature(s: &str) -> Signature {
+    let mut out = [0; 64];
+    out.copy_from_slice(&hex(s));
+    Signature(out)
+}
+
+/// Test vectors 1 through 3 from RFC 8032, section 7.1.
+const VECTORS: [(&str, &str, &str, &str); 3] = [
+    (
+        "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+        "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+        "",
+        "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+    ),
+    (
+        "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+        "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+        "72",
+        "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+    ),
+    (
+        "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+        "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+        "af82",
+        "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a",
+    ),
+];
+
+#[test]
+fn sha512_known_answers() {
+    assert_eq!(
+        &Sha512::digest(b"")[..],
+        &hex("cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e")[..]
+    );
+
+    assert_eq!(
+        &Sha512::digest(b"abc")[..],
+        &hex("ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f")[..]
+    );
+}
+
+#[test]
+fn sha512_incremental() {
+    let data: Vec<u8> = (0..1000u32).map(|i| (i * 7) as u8).collect();
+    let expected = Sha512::digest(&data);
+
+    for &split in &[0, 1, 111, 112, 127, 128, 129, 500, 999, 1000] {
+        let mut hasher = Sha512::new();
+        hasher.update(&data[..split]);
+        hasher.update(&data[split..]);
+        assert_eq!(&hasher.finalize()[..], &expected[..], "split at {}", split);
+    }
+}
+
+#[test]
+fn rfc8032_keys() {
+    for &(seed, public, _, _) in VECTORS.iter() {
+        let keypair = Keypair::from_seed(&array32(seed));
+        assert_eq!(keypair.public(), PublicKey(array32(public)));
+    }
+}
+
+#[test]
+fn rfc8032_signatures() {
+    for &(seed, _, message, sig) in VECTORS.iter() {
+        let keypair = Keypair::from_seed(&array32(seed));
+        assert_eq!(keypair.sign(&hex(message)), signature(sig));
+    }
+}
+
+#[test]
+fn rfc8032_verify() {
+    for &(_, public, message, sig) in VECTORS.iter() {
+        let key = PublicKey(array32(public));
+        assert_eq!(key.verify(&hex(message), &signature(sig)), Ok(()));
+    }
+}
+
+#[test]
+fn rejects_tampering() {
+    let keypair = Keypair::from_seed(&[7; 32]);
+    let message = b"kernel8.img contents";
+    let sig = keypair.sign(message);
+    let key = keypair.public();
+
+    assert_eq!(key.verify(message, &sig), Ok(()));
+    assert_eq!(key.verify(b"kernel8.img content", &sig), Err(Error::BadSignature));
+
+    let mut bad = sig;
+    bad.0[5] ^= 1;
+    assert!(key.verify(message, &bad).is_err());
+
+    let other = Keypair::from_seed(&[8; 32]).public();
+    assert_eq!(other.verify(message, &sig), Err(Error::BadSignature));
+}
+
+#[test]
+fn rejects_non_canonical_s() {
+    let keypair = Keypair::from_seed(&[1; 32]);
+    let mut sig = keypair.sign(b"");
+    for byte in sig.0[32..].iter_mut() {
+        *byte = 0xff;
+    }
+
+    assert_eq!(keypair.public().verify(b"", &sig), Err(Error::MalformedSignature));
+}
@@ -0,0 +1,133 @@
+#![cfg_attr(not(test), no_std)]
+
+//! The signed kernel image format shared by the bootloader and the host-side
+//! `kernsign` tool.
+//!
+//! A signed image is a `HEADER_SIZE` byte header followed by the raw kernel
+//! binary. The header is exactly one XMODEM packet long, so the bootloader can
+//! receive the image to `BINARY_START - HEADER_SIZE` and have the kernel land
+//! at its load address without moving it.
+//!
+//! ```text
+//! offset  size  field
+//!      0     8  magic, b"RPISIGN1"
+//!      8     8  length of the kernel binary in bytes (little endian)
+//!     16    64  Ed25519 signature of the kernel binary
+//!     80    48  reserved, zero
+//! ```
+
+pub use ed25519;
+
+use core::fmt;
+use ed25519::{Keypair, PublicKey, Signature, SIGNATURE_LENGTH};
+
+#[cfg(test)]
+mod tests;
+
+/// Size of the signed image header in bytes.
+pub const HEADER_SIZE: usize = 128;
+
+/// Magic bytes at the start of every signed image.
+pub const MAGIC: [u8; 8] = *b"RPISIGN1";
+
+/// Error type for signed image verification failures.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Error {
+    /// The data does not start with `MAGIC`: the image is not signed.
+    NotSigned,
+    /// The header claims more bytes than were received.
+    Truncated,
+    /// The signature did not verify.
+    Signature(ed25519::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::NotSigned => write!(f, "image is not signed (bad header magic)"),
+            Error::Truncated => write!(f, "image is shorter than its header claims"),
+            Error::Signature(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// The header of a signed image.
+pub struct Header {
+    /// Length of the kernel binary following the header.
+    pub length: u64,
+    /// Signature of the kernel binary.
+    pub signature: Signature,
+}
+
+impl Header {
+    /// Parses the header at the start of `data`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::NotSigned` if `data` is too short to hold a header or
+    /// does not start with `MAGIC`.
+    pub fn parse(data: &[u8]) -> Result<Header, Error> {
+        if data.len() < HEADER_SIZE || data[..8] != MAGIC {
+            return Err(Error::NotSigned);
+        }
+
+        let mut length = [0; 8];
+        length.copy_from_slice(&data[8..16]);
+
+        let mut signature = [0; SIGNATURE_LENGTH];
+        signature.copy_from_slice(&data[16..16 + SIGNATURE_LENGTH]);
+
+        Ok(Header {
+            length: u64::from_le_bytes(length),
+            signature: Signature(signature),
+        })
+    }
+
+    /// Returns the on-the-wire encoding of this header.
+    pub fn to_bytes(&self) -> [u8; HEADER_SIZE] {
+        let mut bytes = [0; HEADER_SIZE];
+        bytes[..8].copy_from_slice(&MAGIC);
+        bytes[8..16].copy_from_slice(&self.length.to_le_bytes());
+        bytes[16..16 + SIGNATURE_LENGTH].copy_from_slice(&self.signature.0);
+        bytes
+    }
+}
+
+/// Signs `image` with `keypair` and returns the header to prepend to it.
+pub fn sign(keypair: &Keypair, image: &[u8]) -> Header {
+    Header {
+        length: image.len() as u64,
+        signature: keypair.sign(image),
+    }
+}
+
+/// Verifies the signed image in `data` against `key`. `data` may have
+/// trailing bytes after the image, such as XMODEM padding.
+///
+/// On success, returns the kernel binary without the header or the padding.
+///
+/// # Errors
+///
+/// Returns `Error::NotSigned` if `data` does not start with a header,
+/// `Error::Truncated` if it is shorter than the header claims, and
+/// `Error::Signature` if the signature does not verify.
+pub fn verify<'a>(key: &PublicKey, data: &'a [u8]) -> Result<&'a [u8], Error> {
+    let header = Header::parse(data)?;
+    let image = image(&header, data)?;
+    key.verify(image, &header.signature).map_err(Error::Signature)?;
+    Ok(image)
+}
+
+/// Returns the kernel binary described by `header` in `data` _without_
+/// checking the signature.
+///
+/// # Errors
+///
+/// Returns `Error::Truncated` if `data` is shorter than the header claims.
+pub fn image<'a>(header: &Header, data: &'a [u8]) -> Result<&'a [u8], Error> {
+    let end = (header.length as usize)
+        .checked_add(HEADER_SIZE)
+        .ok_or(Error::Truncated)?;
+
+    data.get(HEADER_SIZE..end).ok_or(Error::Truncated)
+}
@@ -0,0 +1,125 @@
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::Path;
+use std::process;
+
+use kernsign::ed25519::{Keypair, PublicKey, PUBLIC_KEY_LENGTH, SECRET_KEY_LENGTH};
+
+const USAGE: &str = "\
+usage: kernsign <command> [args]
+
+commands:
+    keygen [-f] <secret> <public>       generate a new signing key pair,
+                                        overwriting existing keys with -f
+    sign <secret-key> <image> <output>  write a signed copy of <image>
+    verify <public-key> <signed-image>  check the signature of an image";
+
+fn read_key(path: &str, len: usize) -> io::Result<Vec<u8>> {
+    let key = fs::read(path)?;
+    if key.len() != len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: expected a {} byte key, found {} bytes", path, len, key.len()),
+        ));
+    }
+
+    Ok(key)
+}
+
+fn keypair(path: &str) -> io::Result<Keypair> {
+    let mut seed = [0; SECRET_KEY_LENGTH];
+    seed.copy_from_slice(&read_key(path, SECRET_KEY_LENGTH)?);
+    Ok(Keypair::from_seed(&seed))
+}
+
+/// Writes `data` to a new file at `path` that only its owner can read,
+/// replacing an existing one only if `force` is set.
+fn write_private(path: &str, data: &[u8], force: bool) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).mode(0o600);
+    match force {
+        true => options.create(true).truncate(true),
+        false => options.create_new(true),
+    };
+
+    let mut file = options.open(path)?;
+    // `mode` only applies to files that are created.
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(data)
+}
+
+fn keygen(secret_path: &str, public_path: &str, force: bool) -> io::Result<()> {
+    if !force {
+        for path in &[secret_path, public_path] {
+            if Path::new(path).exists() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} already exists; use -f to overwrite it", path),
+                ));
+            }
+        }
+    }
+
+    let mut seed = [0; SECRET_KEY_LENGTH];
+    File::open("/dev/urandom")?.read_exact(&mut seed)?;
+
+    let keypair = Keypair::from_seed(&seed);
+    write_private(secret_path, keypair.seed(), force)?;
+    fs::write(public_path, keypair.public().0)?;
+
+    println!("kernsign: wrote secret key to {}", secret_path);
+    println!("kernsign: wrote public key to {}", public_path);
+    Ok(())
+}
+
+fn sign(secret_path: &str, image_path: &str, output_path: &str) -> io::Result<()> {
+    let keypair = keypair(secret_path)?;
+    let image = fs::read(image_path)?;
+
+    let mut output = kernsign::sign(&keypair, &image).to_bytes().to_vec();
+    output.extend_from_slice(&image);
+    fs::write(output_path, output)?;
+
+    println!("kernsign: signed {} ({} bytes) -> {}", image_path, image.len(), output_path);
+    Ok(())
+}
+
+fn verify(public_path: &str, signed_path: &str) -> io::Result<()> {
+    let mut key = [0; PUBLIC_KEY_LENGTH];
+    key.copy_from_slice(&read_key(public_path, PUBLIC_KEY_LENGTH)?);
+    let data = fs::read(signed_path)?;
+
+    match kernsign::verify(&PublicKey(key), &data) {
+        Ok(image) => {
+            println!("kernsign: {}: good signature ({} bytes)", signed_path, image.len());
+            Ok(())
+        }
+        Err(e) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", signed_path, e),
+        )),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+
+    let result = match args.as_slice() {
+        ["keygen", "-f", secret, public] => keygen(secret, public, true),
+        ["keygen", secret, public] => keygen(secret, public, false),
+        ["sign", secret, image, output] => sign(secret, image, output),
+        ["verify", public, signed] => verify(public, signed),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("kernsign: error: {}", e);
+        process::exit(1);
+    }
+}
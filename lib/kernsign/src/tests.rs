@@ -0,0 +1,72 @@
+use super::*;
+
+fn signed(keypair: &Keypair, image: &[u8], padding: usize) -> Vec<u8> {
+    let mut data = sign(keypair, image).to_bytes().to_vec();
+    data.extend_from_slice(image);
+    data.resize(data.len() + padding, 0x1a);
+    data
+}
+
+#[test]
+fn header_is_one_xmodem_packet() {
+    let keypair = Keypair::from_seed(&[3; 32]);
+    assert_eq!(sign(&keypair, b"").to_bytes().len(), 128);
+}
+
+#[test]
+fn round_trip() {
+    let keypair = Keypair::from_seed(&[3; 32]);
+    let image: Vec<u8> = (0..3000u32).map(|i| i as u8).collect();
+
+    for &padding in &[0, 1, 72, 127] {
+        let data = signed(&keypair, &image, padding);
+        assert_eq!(verify(&keypair.public(), &data), Ok(&image[..]));
+    }
+}
+
+#[test]
+fn rejects_unsigned() {
+    let keypair = Keypair::from_seed(&[3; 32]);
+    let raw = vec![0u8; 4096];
+    assert_eq!(verify(&keypair.public(), &raw), Err(Error::NotSigned));
+    assert_eq!(verify(&keypair.public(), &[]), Err(Error::NotSigned));
+}
+
+#[test]
+fn rejects_truncated() {
+    let keypair = Keypair::from_seed(&[3; 32]);
+    let mut data = signed(&keypair, &[0xAB; 500], 0);
+    data.truncate(HEADER_SIZE + 499);
+    assert_eq!(verify(&keypair.public(), &data), Err(Error::Truncated));
+}
+
+#[test]
+fn rejects_modified_image() {
+    let keypair = Keypair::from_seed(&[3; 32]);
+    let mut data = signed(&keypair, &[0xAB; 500], 12);
+    data[HEADER_SIZE + 250] ^= 0x80;
+    assert_eq!(
+        verify(&keypair.public(), &data),
+        Err(Error::Signature(ed25519::Error::BadSignature))
+    );
+}
+
+#[test]
+fn rejects_foreign_key() {
+    let ours = Keypair::from_seed(&[3; 32]);
+    let theirs = Keypair::from_seed(&[4; 32]);
+    let data = signed(&theirs, b"not our kernel", 0);
+    assert_eq!(
+        verify(&ours.public(), &data),
+        Err(Error::Signature(ed25519::Error::BadSignature))
+    );
+}
+
+#[test]
+fn rejects_length_extension() {
+    let keypair = Keypair::from_seed(&[3; 32]);
+    let mut data = signed(&keypair, &[0x11; 256], 128);
+    data[8] = 0; // claim a longer image: 256 -> 512 bytes
+    data[9] = 2;
+    assert!(verify(&keypair.public(), &data).is_err());
+}
@@ -0,0 +1,219 @@
+use core::time::Duration;
+
+use shim::const_assert_size;
+
+use volatile::prelude::*;
+use volatile::{ReadVolatile, Reserved, Volatile};
+
+/// The base address of the cores' local interrupt controller (ref: QA7
+/// ARM Quad A7 core, 4.1).
+const LOCAL_INT_BASE: usize = 0x4000_0000;
+
+/// `CNTP_CTL_EL0.ENABLE`: the timer compares.
+const CTL_ENABLE: u64 = 1 << 0;
+
+/// `CNTP_CTL_EL0.IMASK`: the timer's interrupt is masked.
+const CTL_IMASK: u64 = 1 << 1;
+
+/// `CNTP_CTL_EL0.ISTATUS`: the timer condition is met.
+const CTL_ISTATUS: u64 = 1 << 2;
+
+/// A per-core interrupt source of the local interrupt controller, numbered
+/// as in the cores' IRQ source registers.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LocalInterrupt {
+    CntPsIrq = 0,
+    CntPnsIrq = 1,
+    CntHpIrq = 2,
+    CntVIrq = 3,
+    Mailbox0 = 4,
+    Mailbox1 = 5,
+    Mailbox2 = 6,
+    Mailbox3 = 7,
+    Gpu = 8,
+    Pmu = 9,
+    AxiOutstanding = 10,
+    LocalTimer = 11,
+}
+
+impl LocalInterrupt {
+    /// The interrupt of the physical timer the kernel uses at EL1.
+    pub const TIMER: LocalInterrupt = LocalInterrupt::CntPnsIrq;
+}
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct Registers {
+    __r0: [Reserved<u32>; 16],
+    CORE_TIMER_INT_CONTROL: [Volatile<u32>; 4],
+    CORE_MAILBOX_INT_CONTROL: [Volatile<u32>; 4],
+    CORE_IRQ_SOURCE: [ReadVolatile<u32>; 4],
+    CORE_FIQ_SOURCE: [ReadVolatile<u32>; 4],
+}
+
+const_assert_size!(Registers, 0x4000_0080 - 0x4000_0000);
+
+/// One core's view of the local interrupt controller, which routes that
+/// core's generic timer interrupts to it as IRQs.
+pub struct LocalController {
+    core: usize,
+    registers: &'static mut Registers,
+}
+
+impl LocalController {
+    /// Returns a handle to the local interrupt controller for `core`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `core` is not below `NCORES`.
+    pub fn new(core: usize) -> LocalController {
+        assert!(core < crate::common::NCORES, "no core {}", core);
+        LocalController {
+            core,
+            registers: unsafe { &mut *(LOCAL_INT_BASE as *mut Registers) },
+        }
+    }
+
+    /// Routes the core's physical timer interrupt to it as an IRQ.
+    pub fn enable_timer(&mut self) {
+        self.registers.CORE_TIMER_INT_CONTROL[self.core].or_mask(1 << LocalInterrupt::TIMER as u32);
+    }
+
+    /// Stops routing the core's physical timer interrupt to it.
+    pub fn disable_timer(&mut self) {
+        self.registers.CORE_TIMER_INT_CONTROL[self.core].and_mask(!(1 << LocalInterrupt::TIMER as u32));
+    }
+
+    /// Returns `true` if `int` is raising an IRQ on the core.
+    pub fn is_pending(&self, int: LocalInterrupt) -> bool {
+        self.registers.CORE_IRQ_SOURCE[self.core].has_mask(1 << int as u32)
+    }
+}
+
+/// Returns the frequency of the generic timer's counter, in Hz.
+#[cfg(all(target_arch = "aarch64", not(test)))]
+#[inline(always)]
+pub fn frequency() -> u64 {
+    let freq: u64;
+    unsafe { asm!("mrs $0, CNTFRQ_EL0" : "=r"(freq) ::: "volatile") };
+    freq
+}
+
+/// Returns the generic timer's counter.
+#[cfg(all(target_arch = "aarch64", not(test)))]
+#[inline(always)]
+pub fn counter() -> u64 {
+    let count: u64;
+    unsafe { asm!("isb; mrs $0, CNTPCT_EL0" : "=r"(count) ::: "volatile") };
+    count
+}
+
+#[cfg(all(target_arch = "aarch64", not(test)))]
+#[inline(always)]
+fn control() -> u64 {
+    let ctl: u64;
+    unsafe { asm!("mrs $0, CNTP_CTL_EL0" : "=r"(ctl) ::: "volatile") };
+    ctl
+}
+
+#[cfg(all(target_arch = "aarch64", not(test)))]
+#[inline(always)]
+fn set_control(ctl: u64) {
+    unsafe { asm!("msr CNTP_CTL_EL0, $0" :: "r"(ctl) :: "volatile") };
+}
+
+#[cfg(all(target_arch = "aarch64", not(test)))]
+#[inline(always)]
+fn set_compare(cval: u64) {
+    unsafe { asm!("msr CNTP_CVAL_EL0, $0" :: "r"(cval) :: "volatile") };
+}
+
+// On the host, a counter that never moves.
+#[cfg(not(all(target_arch = "aarch64", not(test))))]
+pub fn frequency() -> u64 {
+    19_200_000
+}
+
+#[cfg(not(all(target_arch = "aarch64", not(test))))]
+pub fn counter() -> u64 {
+    0
+}
+
+#[cfg(not(all(target_arch = "aarch64", not(test))))]
+fn control() -> u64 {
+    0
+}
+
+#[cfg(not(all(target_arch = "aarch64", not(test))))]
+fn set_control(_: u64) {}
+
+#[cfg(not(all(target_arch = "aarch64", not(test))))]
+fn set_compare(_: u64) {}
+
+/// Returns the time `ticks` of a counter running at `freq` Hz take.
+fn to_duration(ticks: u64, freq: u64) -> Duration {
+    let nanos = (ticks % freq) * 1_000_000_000 / freq;
+    Duration::new(ticks / freq, nanos as u32)
+}
+
+/// Returns the ticks of a counter running at `freq` Hz that `t` takes,
+/// rounded up.
+fn to_ticks(t: Duration, freq: u64) -> u64 {
+    let nanos = t.subsec_nanos() as u64 * freq;
+    t.as_secs() * freq + (nanos + 999_999_999) / 1_000_000_000
+}
+
+/// Returns the time since the counter started.
+pub fn current_time() -> Duration {
+    to_duration(counter(), frequency())
+}
+
+/// Spins until `t` duration have passed.
+pub fn spin_sleep(t: Duration) {
+    let end = counter() + to_ticks(t, frequency());
+    while counter() < end {}
+}
+
+/// Arms this core's timer to raise its interrupt once `t` has passed,
+/// acknowledging any earlier one. The interrupt reaches the core as an IRQ
+/// once `LocalController::enable_timer()` routes it.
+pub fn tick_in(t: Duration) {
+    set_compare(counter() + to_ticks(t, frequency()));
+    set_control(CTL_ENABLE);
+}
+
+/// Returns `true` if this core's timer is armed and has expired.
+pub fn is_pending() -> bool {
+    control() & (CTL_ENABLE | CTL_ISTATUS) == CTL_ENABLE | CTL_ISTATUS
+}
+
+/// Disarms this core's timer, which also acknowledges its interrupt.
+pub fn stop() {
+    set_control(CTL_IMASK);
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::{to_duration, to_ticks};
+
+    #[test]
+    fn converts_ticks_to_time() {
+        let freq = 19_200_000;
+        assert_eq!(to_duration(0, freq), Duration::from_secs(0));
+        assert_eq!(to_duration(freq * 3 + freq / 4, freq), Duration::from_millis(3_250));
+        assert_eq!(to_duration(1, freq), Duration::from_nanos(52));
+        // Counts that would overflow if multiplied out first.
+        assert_eq!(to_duration(u64::max_value() / freq * freq, freq).as_secs(), u64::max_value() / freq);
+    }
+
+    #[test]
+    fn converts_time_to_ticks_rounding_up() {
+        let freq = 19_200_000;
+        assert_eq!(to_ticks(Duration::from_millis(3_250), freq), freq * 3 + freq / 4);
+        assert_eq!(to_ticks(Duration::from_nanos(1), freq), 1);
+        assert_eq!(to_ticks(Duration::from_secs(0), freq), 0);
+        assert!(to_duration(to_ticks(Duration::from_micros(7), freq), freq) >= Duration::from_micros(7));
+    }
+}
@@ -1,11 +1,17 @@
 use core::marker::PhantomData;
+use core::time::Duration;
 
 use crate::common::{states, GPIO_BASE};
+use crate::timer;
 use volatile::prelude::*;
 use volatile::{ReadVolatile, Reserved, Volatile, WriteVolatile};
 
+/// The number of GPIO pins.
+pub const PIN_COUNT: u8 = 54;
+
 /// An alternative GPIO function.
 #[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Function {
     Input = 0b000,
     Output = 0b001,
@@ -17,6 +23,32 @@ pub enum Function {
     Alt5 = 0b010,
 }
 
+impl Function {
+    /// Returns the function selected by the three-bit field `bits` of a
+    /// `GPFSEL` register.
+    fn from_bits(bits: u32) -> Function {
+        match bits & 0b111 {
+            0b000 => Function::Input,
+            0b001 => Function::Output,
+            0b100 => Function::Alt0,
+            0b101 => Function::Alt1,
+            0b110 => Function::Alt2,
+            0b111 => Function::Alt3,
+            0b011 => Function::Alt4,
+            _ => Function::Alt5,
+        }
+    }
+}
+
+/// The pull-up/down resistor setting of a GPIO pin.
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Pull {
+    Off = 0b00,
+    Down = 0b01,
+    Up = 0b10,
+}
+
 #[repr(C)]
 #[allow(non_snake_case)]
 struct Registers {
@@ -49,7 +81,7 @@ struct Registers {
 /// Possible states for a GPIO pin.
 #[allow(unused_doc_comments)]
 states! {
-    Uninitialized, Input, Output, Alt
+    Uninitialized, Input, Output, Alt, Dynamic
 }
 
 /// A GPIO pin in state `State`.
@@ -59,6 +91,10 @@ states! {
 /// structure starts in the `Uninitialized` state and must be transitions into
 /// one of `Input`, `Output`, or `Alt` via the `into_input`, `into_output`, and
 /// `into_alt` methods before it can be used.
+///
+/// For pins chosen at runtime, `into_dynamic` gives a `Gpio` in the `Dynamic`
+/// state instead, on which every operation is available and checked by the
+/// caller rather than the type system.
 pub struct Gpio<State> {
     pin: u8,
     registers: &'static mut Registers,
@@ -77,6 +113,41 @@ impl<T> Gpio<T> {
             _state: PhantomData,
         }
     }
+
+    /// Returns the index of the register holding this pin's bit and the
+    /// bit's mask within it, for the `SET`, `CLR`, `LEV` and `PUDCLK`
+    /// register pairs.
+    #[inline(always)]
+    fn bit(&self) -> (usize, u32) {
+        ((self.pin / 32) as usize, 1 << (self.pin % 32))
+    }
+
+    /// Selects `function` for this pin.
+    fn select(&mut self, function: Function) {
+        let sel_reg = (self.pin / 10) as usize;
+        let bit_offset = (self.pin % 10) * 3;
+
+        self.registers.FSEL[sel_reg].and_mask(!(0b111 << bit_offset));
+        self.registers.FSEL[sel_reg].or_mask((function as u32) << bit_offset);
+    }
+
+    /// Drives the pin high.
+    fn set_bit(&mut self) {
+        let (reg, mask) = self.bit();
+        self.registers.SET[reg].write(mask);
+    }
+
+    /// Drives the pin low.
+    fn clear_bit(&mut self) {
+        let (reg, mask) = self.bit();
+        self.registers.CLR[reg].write(mask);
+    }
+
+    /// Returns `true` if the pin's level is high.
+    fn read_level(&self) -> bool {
+        let (reg, mask) = self.bit();
+        self.registers.LEV[reg].has_mask(mask)
+    }
 }
 
 impl Gpio<Uninitialized> {
@@ -84,9 +155,9 @@ impl Gpio<Uninitialized> {
     ///
     /// # Panics
     ///
-    /// Panics if `pin` > `53`.
+    /// Panics if `pin` >= `PIN_COUNT`.
     pub fn new(pin: u8) -> Gpio<Uninitialized> {
-        if pin > 53 {
+        if pin >= PIN_COUNT {
             panic!("Gpio::new(): pin {} exceeds maximum of 53", pin);
         }
 
@@ -99,12 +170,8 @@ impl Gpio<Uninitialized> {
 
     /// Enables the alternative function `function` for `self`. Consumes self
     /// and returns a `Gpio` structure in the `Alt` state.
-    pub fn into_alt(self, function: Function) -> Gpio<Alt> {
-        // Integer division to the right GPFSEL[n] register 
-        let sel_reg = (self.pin / 10) as usize;
-        let bit_offset = (self.pin % 10) * 3;
-
-        self.registers.FSEL[sel_reg].or_mask((function as u32) << bit_offset);
+    pub fn into_alt(mut self, function: Function) -> Gpio<Alt> {
+        self.select(function);
         self.transition()
     }
 
@@ -119,23 +186,23 @@ impl Gpio<Uninitialized> {
     pub fn into_input(self) -> Gpio<Input> {
         self.into_alt(Function::Input).transition()
     }
+
+    /// Leaves the pin's function as it is and returns a `Gpio` structure in
+    /// the `Dynamic` state, for pins whose use is only known at runtime.
+    pub fn into_dynamic(self) -> Gpio<Dynamic> {
+        self.transition()
+    }
 }
 
 impl Gpio<Output> {
     /// Sets (turns on) the pin.
     pub fn set(&mut self) {
-        let sel_reg = (self.pin / 32) as usize;
-        let bit_offset = self.pin % 32;
-
-        self.registers.SET[sel_reg].write(1 << bit_offset);
+        self.set_bit();
     }
 
     /// Clears (turns off) the pin.
     pub fn clear(&mut self) {
-        let sel_reg = (self.pin / 32) as usize;
-        let bit_offset = self.pin % 32;
-
-        self.registers.CLR[sel_reg].write(1 << bit_offset);
+        self.clear_bit();
     }
 }
 
@@ -143,9 +210,74 @@ impl Gpio<Input> {
     /// Reads the pin's value. Returns `true` if the level is high and `false`
     /// if the level is low.
     pub fn level(&mut self) -> bool {
-        let sel_reg = (self.pin / 32) as usize;
-        let bit_offset = self.pin % 32;
+        self.read_level()
+    }
+}
+
+impl Gpio<Dynamic> {
+    /// Returns the pin number.
+    pub fn pin(&self) -> u8 {
+        self.pin
+    }
+
+    /// Returns the function currently selected for the pin.
+    pub fn function(&self) -> Function {
+        let sel_reg = (self.pin / 10) as usize;
+        let bit_offset = (self.pin % 10) * 3;
+
+        Function::from_bits(self.registers.FSEL[sel_reg].read() >> bit_offset)
+    }
+
+    /// Selects `function` for the pin.
+    pub fn set_function(&mut self, function: Function) {
+        self.select(function);
+    }
+
+    /// Sets (turns on) the pin. Has no visible effect unless the pin is an
+    /// output.
+    pub fn set(&mut self) {
+        self.set_bit();
+    }
+
+    /// Clears (turns off) the pin. Has no visible effect unless the pin is an
+    /// output.
+    pub fn clear(&mut self) {
+        self.clear_bit();
+    }
+
+    /// Sets the pin if `level` is `true` and clears it otherwise.
+    pub fn write(&mut self, level: bool) {
+        if level {
+            self.set_bit();
+        } else {
+            self.clear_bit();
+        }
+    }
+
+    /// Inverts the pin's level.
+    pub fn toggle(&mut self) {
+        let level = self.read_level();
+        self.write(!level);
+    }
+
+    /// Reads the pin's value. Returns `true` if the level is high and `false`
+    /// if the level is low, whatever the pin's function.
+    pub fn level(&self) -> bool {
+        self.read_level()
+    }
+
+    /// Sets the pin's pull-up/down resistor using the sequence in section
+    /// 6.1 of the BCM2837 peripherals manual.
+    pub fn set_pull(&mut self, pull: Pull) {
+        // The control signal needs 150 cycles of setup and hold time.
+        let settle = Duration::from_micros(1);
+        let (reg, mask) = self.bit();
 
-        self.registers.LEV[sel_reg].has_mask(1 << bit_offset)
+        self.registers.PUD.write(pull as u32);
+        timer::spin_sleep(settle);
+        self.registers.PUDCLK[reg].write(mask);
+        timer::spin_sleep(settle);
+        self.registers.PUD.write(0);
+        self.registers.PUDCLK[reg].write(0);
     }
 }
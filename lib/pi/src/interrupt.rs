@@ -0,0 +1,142 @@
+use shim::const_assert_size;
+
+use volatile::prelude::*;
+use volatile::{ReadVolatile, Volatile, WriteVolatile};
+
+use crate::common::IO_BASE;
+
+/// The base address of the interrupt controller's registers.
+const INT_BASE: usize = IO_BASE + 0xB000 + 0x200;
+
+/// A peripheral interrupt source, numbered as in the BCM2837's IRQ table
+/// (ref: BCM2835 ARM Peripherals, 7.5).
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Interrupt {
+    Timer1 = 1,
+    Timer3 = 3,
+    Usb = 9,
+    Aux = 29,
+    Gpio0 = 49,
+    Gpio1 = 50,
+    Gpio2 = 51,
+    Gpio3 = 52,
+    Uart = 57,
+}
+
+impl Interrupt {
+    /// The number of interrupt sources.
+    pub const MAX: usize = 9;
+
+    /// Returns every interrupt source, in the order of their indices.
+    pub fn iter() -> impl Iterator<Item = Interrupt> {
+        use self::Interrupt::*;
+
+        [Timer1, Timer3, Usb, Aux, Gpio0, Gpio1, Gpio2, Gpio3, Uart].iter().cloned()
+    }
+
+    /// Returns a unique index in `0..Interrupt::MAX` for this source, to
+    /// index per-source tables with.
+    pub fn to_index(self) -> usize {
+        use self::Interrupt::*;
+
+        match self {
+            Timer1 => 0,
+            Timer3 => 1,
+            Usb => 2,
+            Aux => 3,
+            Gpio0 => 4,
+            Gpio1 => 5,
+            Gpio2 => 6,
+            Gpio3 => 7,
+            Uart => 8,
+        }
+    }
+
+    /// Returns the source with the index `index`, if there is one.
+    pub fn from_index(index: usize) -> Option<Interrupt> {
+        Interrupt::iter().nth(index)
+    }
+
+    /// Returns the register bank (0 for IRQs 0-31, 1 for 32-63) and the bit
+    /// within it that stand for this source.
+    fn bank_and_bit(self) -> (usize, u32) {
+        let irq = self as usize;
+        (irq / 32, 1 << (irq % 32))
+    }
+}
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct Registers {
+    IRQ_BASIC_PENDING: ReadVolatile<u32>,
+    IRQ_PENDING: [ReadVolatile<u32>; 2],
+    FIQ_CONTROL: Volatile<u32>,
+    ENABLE_IRQS: [Volatile<u32>; 2],
+    ENABLE_BASIC_IRQS: Volatile<u32>,
+    DISABLE_IRQS: [WriteVolatile<u32>; 2],
+    DISABLE_BASIC_IRQS: WriteVolatile<u32>,
+}
+
+const_assert_size!(Registers, 0x7E00B228 - 0x7E00B200);
+
+/// The Raspberry Pi's interrupt controller, which routes peripheral
+/// interrupts to the ARM cores as IRQs.
+pub struct Controller {
+    registers: &'static mut Registers,
+}
+
+impl Controller {
+    /// Returns a new handle to the interrupt controller.
+    pub fn new() -> Controller {
+        Controller {
+            registers: unsafe { &mut *(INT_BASE as *mut Registers) },
+        }
+    }
+
+    /// Lets the interrupt `int` raise IRQs.
+    pub fn enable(&mut self, int: Interrupt) {
+        let (bank, bit) = int.bank_and_bit();
+        self.registers.ENABLE_IRQS[bank].write(bit);
+    }
+
+    /// Stops the interrupt `int` from raising IRQs.
+    pub fn disable(&mut self, int: Interrupt) {
+        let (bank, bit) = int.bank_and_bit();
+        self.registers.DISABLE_IRQS[bank].write(bit);
+    }
+
+    /// Returns `true` if the interrupt `int` may raise IRQs.
+    pub fn is_enabled(&self, int: Interrupt) -> bool {
+        let (bank, bit) = int.bank_and_bit();
+        self.registers.ENABLE_IRQS[bank].has_mask(bit)
+    }
+
+    /// Returns `true` if the interrupt `int` is pending.
+    pub fn is_pending(&self, int: Interrupt) -> bool {
+        let (bank, bit) = int.bank_and_bit();
+        self.registers.IRQ_PENDING[bank].has_mask(bit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Interrupt;
+
+    #[test]
+    fn indexes_every_source() {
+        assert_eq!(Interrupt::iter().count(), Interrupt::MAX);
+        for (index, int) in Interrupt::iter().enumerate() {
+            assert_eq!(int.to_index(), index);
+            assert_eq!(Interrupt::from_index(index), Some(int));
+        }
+        assert_eq!(Interrupt::from_index(Interrupt::MAX), None);
+    }
+
+    #[test]
+    fn maps_sources_to_banks() {
+        assert_eq!(Interrupt::Timer1.bank_and_bit(), (0, 1 << 1));
+        assert_eq!(Interrupt::Aux.bank_and_bit(), (0, 1 << 29));
+        assert_eq!(Interrupt::Gpio0.bank_and_bit(), (1, 1 << 17));
+        assert_eq!(Interrupt::Uart.bank_and_bit(), (1, 1 << 25));
+    }
+}
@@ -7,6 +7,9 @@
 
 pub mod atags;
 pub mod common;
+pub mod generic_timer;
 pub mod gpio;
+pub mod interrupt;
+pub mod mailbox;
 pub mod timer;
 pub mod uart;
@@ -0,0 +1,200 @@
+use core::ptr;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use shim::const_assert_size;
+
+use volatile::prelude::*;
+use volatile::{ReadVolatile, Reserved, Volatile, WriteVolatile};
+
+use crate::common::IO_BASE;
+
+/// The base address of the VideoCore mailbox registers.
+const MAILBOX_BASE: usize = IO_BASE + 0xB880;
+
+/// The channel of the property tag interface.
+pub const PROPERTY_CHANNEL: u8 = 8;
+
+/// Set in `STATUS` when there is nothing to read.
+const EMPTY: u32 = 1 << 30;
+
+/// Set in `WRITE_STATUS` when no more can be written.
+const FULL: u32 = 1 << 31;
+
+/// The code of a request, and of a response to one that succeeded.
+const REQUEST: u32 = 0;
+const SUCCESS: u32 = 1 << 31;
+
+/// The most values a single property can take or return.
+pub const MAX_VALUES: usize = 8;
+
+/// Property tags, from the firmware's mailbox property interface.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Tag {
+    /// Returns the board revision.
+    BoardRevision = 0x0001_0002,
+    /// Returns the base and size of the memory the ARM cores get.
+    ArmMemory = 0x0001_0005,
+    /// Returns the base and size of the memory the VideoCore keeps.
+    VcMemory = 0x0001_0006,
+}
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct Registers {
+    READ: ReadVolatile<u32>,
+    __r0: [Reserved<u32>; 3],
+    PEEK: ReadVolatile<u32>,
+    SENDER: ReadVolatile<u32>,
+    STATUS: ReadVolatile<u32>,
+    CONFIG: Volatile<u32>,
+    WRITE: WriteVolatile<u32>,
+    __r1: [Reserved<u32>; 5],
+    WRITE_STATUS: ReadVolatile<u32>,
+}
+
+const_assert_size!(Registers, 0x7E00B8BC - 0x7E00B880);
+
+/// A property message: the buffer the firmware reads the request from and
+/// writes the response to. The mailbox only passes the upper 28 bits of its
+/// address, so it is 16-byte aligned.
+#[repr(C, align(16))]
+struct Message([u32; 6 + MAX_VALUES]);
+
+impl Message {
+    /// Returns a request for `tag` with `values` as its arguments. The
+    /// response may be as long as `response_len` values.
+    fn new(tag: Tag, values: &[u32], response_len: usize) -> Message {
+        let len = values.len().max(response_len);
+        let mut words = [0; 6 + MAX_VALUES];
+        words[0] = ((6 + len) * 4) as u32;
+        words[1] = REQUEST;
+        words[2] = tag as u32;
+        words[3] = (len * 4) as u32;
+        words[4] = REQUEST;
+        words[5..5 + values.len()].copy_from_slice(values);
+        // The end tag, words[5 + len], is 0 already.
+        Message(words)
+    }
+
+    /// Returns the word at `index`. The firmware writes the response behind
+    /// the compiler's back, so it is read from memory every time.
+    fn word(&self, index: usize) -> u32 {
+        unsafe { ptr::read_volatile(&self.0[index]) }
+    }
+
+    /// Copies the values of a successful response into `values`. Returns
+    /// `None` if the request failed.
+    fn response(&self, values: &mut [u32]) -> Option<()> {
+        let status = self.word(4);
+        if self.word(1) != SUCCESS || status & SUCCESS == 0 {
+            return None;
+        }
+
+        let len = (status & !SUCCESS) as usize / 4;
+        let n = len.min(values.len()).min(MAX_VALUES);
+        for (i, value) in values[..n].iter_mut().enumerate() {
+            *value = self.word(5 + i);
+        }
+        Some(())
+    }
+}
+
+/// The mailbox through which the ARM cores talk to the VideoCore firmware.
+pub struct Mailbox {
+    registers: &'static mut Registers,
+}
+
+impl Mailbox {
+    /// Returns a new instance of `Mailbox`.
+    pub fn new() -> Mailbox {
+        Mailbox {
+            registers: unsafe { &mut *(MAILBOX_BASE as *mut Registers) },
+        }
+    }
+
+    /// Sends the 16-byte aligned `addr` to the firmware on `channel` and
+    /// waits for its reply on the same channel.
+    fn call(&mut self, channel: u8, addr: usize) {
+        let message = (addr as u32 & !0xF) | channel as u32;
+
+        // The firmware must see the message the way it was written.
+        compiler_fence(Ordering::SeqCst);
+        while self.registers.WRITE_STATUS.has_mask(FULL) {}
+        self.registers.WRITE.write(message);
+
+        loop {
+            while self.registers.STATUS.has_mask(EMPTY) {}
+            if self.registers.READ.read() == message {
+                break;
+            }
+        }
+        compiler_fence(Ordering::SeqCst);
+    }
+
+    /// Asks the firmware for the property `tag`, passing `values` as its
+    /// arguments and returning its response in them. Returns `None` if the
+    /// firmware did not answer the request.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `values` has more than `MAX_VALUES` values.
+    pub fn property(&mut self, tag: Tag, values: &mut [u32]) -> Option<()> {
+        assert!(values.len() <= MAX_VALUES, "at most {} values fit in a message", MAX_VALUES);
+
+        let mut message = Message::new(tag, values, values.len());
+        self.call(PROPERTY_CHANNEL, &mut message as *mut Message as usize);
+        message.response(values)
+    }
+}
+
+/// Returns the `(base, size)` of a region reported by the firmware for `tag`.
+fn region(tag: Tag) -> Option<(usize, usize)> {
+    let mut values = [0; 2];
+    Mailbox::new().property(tag, &mut values)?;
+    Some((values[0] as usize, values[1] as usize))
+}
+
+/// Returns the base and size of the memory the ARM cores get, as split from
+/// the VideoCore's by the firmware.
+pub fn arm_memory() -> Option<(usize, usize)> {
+    region(Tag::ArmMemory)
+}
+
+/// Returns the base and size of the memory the VideoCore keeps for itself.
+pub fn vc_memory() -> Option<(usize, usize)> {
+    region(Tag::VcMemory)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Message, Tag, SUCCESS};
+
+    #[test]
+    fn encodes_requests() {
+        let message = Message::new(Tag::ArmMemory, &[], 2);
+        assert_eq!(message.0[..9], [32, 0, 0x0001_0005, 8, 0, 0, 0, 0, 0]);
+        assert_eq!(&message as *const Message as usize % 16, 0);
+
+        let message = Message::new(Tag::BoardRevision, &[7, 9, 11], 1);
+        assert_eq!(message.0[..9], [36, 0, 0x0001_0002, 12, 0, 7, 9, 11, 0]);
+    }
+
+    #[test]
+    fn decodes_responses() {
+        let mut message = Message::new(Tag::VcMemory, &[], 2);
+        let mut values = [0; 2];
+        assert_eq!(message.response(&mut values), None);
+
+        message.0[1] = SUCCESS;
+        message.0[4] = SUCCESS | 8;
+        message.0[5] = 0x3c00_0000;
+        message.0[6] = 0x0400_0000;
+        assert_eq!(message.response(&mut values), Some(()));
+        assert_eq!(values, [0x3c00_0000, 0x0400_0000]);
+
+        // A tag the firmware did not understand.
+        message.0[4] = 0;
+        assert_eq!(message.response(&mut values), None);
+    }
+}
@@ -7,6 +7,14 @@ use volatile::{ReadVolatile, Volatile};
 /// The base address for the ARM system timer registers.
 const TIMER_REG_BASE: usize = IO_BASE + 0x3000;
 
+/// The compare channel `tick_in()` arms. The GPU uses channels 0 and 2, so
+/// of the four only 1 and 3 are free for the ARM cores; channel 1 raises
+/// `Interrupt::Timer1`.
+pub const CHANNEL: usize = 1;
+
+/// The shortest time `tick_in()` arms the timer for, in microseconds.
+const MIN_TICK_US: u32 = 10;
+
 #[repr(C)]
 #[allow(non_snake_case)]
 struct Registers {
@@ -36,6 +44,32 @@ impl Timer {
 
         Duration::from_micros(usecs)
     }
+
+    /// Arms compare channel `CHANNEL` to match, raising `Interrupt::Timer1`,
+    /// once `t` has passed, and acknowledges any earlier match.
+    ///
+    /// The channel compares only the low 32 bits of the counter, so `t` must
+    /// be shorter than about 71 minutes.
+    pub fn tick_in(&mut self, t: Duration) {
+        // A compare value the counter has already passed by the time it is
+        // written would only match once the counter wraps.
+        let usecs = (t.as_micros() as u32).max(MIN_TICK_US);
+        self.acknowledge();
+        self.registers.COMPARE[CHANNEL].write(self.registers.CLO.read().wrapping_add(usecs));
+    }
+
+    /// Returns `true` if compare channel `CHANNEL` has matched since it was
+    /// last acknowledged.
+    pub fn is_matched(&self) -> bool {
+        self.registers.CS.has_mask(1 << CHANNEL)
+    }
+
+    /// Acknowledges a match on compare channel `CHANNEL`, which clears its
+    /// interrupt.
+    pub fn acknowledge(&mut self) {
+        // Bits in `CS` are cleared by writing 1 to them.
+        self.registers.CS.write(1 << CHANNEL);
+    }
 }
 
 /// Returns current time.
@@ -43,6 +77,11 @@ pub fn current_time() -> Duration {
     Timer::new().read()
 }
 
+/// Arms the timer to raise `Interrupt::Timer1` once `t` has passed.
+pub fn tick_in(t: Duration) {
+    Timer::new().tick_in(t)
+}
+
 /// Spins until `t` duration have passed.
 pub fn spin_sleep(t: Duration) {
     let timer = Timer::new();
@@ -21,9 +21,69 @@ const AUX_ENABLES: *mut Volatile<u8> = (IO_BASE + 0x215004) as *mut Volatile<u8>
 #[repr(u8)]
 enum LsrStatus {
     DataReady = 1,
+    ReceiverOverrun = 1 << 1,
     TxAvailable = 1 << 5,
 }
 
+/// `AUX_MU_IER_REG` bits. The BCM2837 documentation has the receive and
+/// transmit bits swapped, and leaves out that bits 2 and 3 must be set for
+/// either interrupt to be raised.
+const IER_RX: u8 = 1 << 0;
+const IER_TX: u8 = 1 << 1;
+const IER_REQUIRED: u8 = 0b1100;
+
+/// The size of the receive and transmit buffers in interrupt mode.
+pub const BUFFER_SIZE: usize = 256;
+
+/// A fixed-size FIFO of bytes.
+struct Ring {
+    bytes: [u8; BUFFER_SIZE],
+    start: usize,
+    len: usize,
+}
+
+impl Ring {
+    const fn new() -> Ring {
+        Ring { bytes: [0; BUFFER_SIZE], start: 0, len: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Appends `byte`, returning `false` if the ring is full.
+    fn push(&mut self, byte: u8) -> bool {
+        if self.len == BUFFER_SIZE {
+            return false;
+        }
+
+        self.bytes[(self.start + self.len) % BUFFER_SIZE] = byte;
+        self.len += 1;
+        true
+    }
+
+    /// Removes and returns the oldest byte.
+    fn pop(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let byte = self.bytes[self.start];
+        self.start = (self.start + 1) % BUFFER_SIZE;
+        self.len -= 1;
+        Some(byte)
+    }
+}
+
+/// Counts of received bytes that were lost.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Overruns {
+    /// Times the UART's receive FIFO overflowed before it was read.
+    pub fifo: u64,
+    /// Bytes dropped because the receive buffer was full.
+    pub buffer: u64,
+}
+
 #[repr(C)]
 #[allow(non_snake_case)]
 struct Registers {
@@ -52,9 +112,20 @@ struct Registers {
 const_assert_size!(Registers, 0x7E21506C - 0x7E215040);
 
 /// The Raspberry Pi's "mini UART".
+///
+/// It starts out polled: reads and writes go straight to the UART. After
+/// `enable_interrupts()`, received bytes are buffered as they arrive and
+/// written bytes are buffered until the UART has room, both by
+/// `handle_interrupt()`, which must then be called on the `Aux` IRQ. Calls
+/// that wait for the UART also do its work, so they never wait on an IRQ
+/// that is masked.
 pub struct MiniUart {
     registers: &'static mut Registers,
     timeout: Option<Duration>,
+    interrupts: bool,
+    rx: Ring,
+    tx: Ring,
+    overruns: Overruns,
 }
 
 impl MiniUart {
@@ -82,27 +153,115 @@ impl MiniUart {
 
         MiniUart {
             registers: registers,
-            timeout: None
+            timeout: None,
+            interrupts: false,
+            rx: Ring::new(),
+            tx: Ring::new(),
+            overruns: Overruns::default(),
         }
     }
 
+    /// Switches to interrupt mode: the UART raises the `Aux` interrupt when
+    /// it receives a byte or, while bytes wait to be written, has room for
+    /// more. `handle_interrupt()` must be called when it does.
+    pub fn enable_interrupts(&mut self) {
+        self.interrupts = true;
+        self.service();
+    }
+
+    /// Returns `true` in interrupt mode.
+    pub fn interrupts_enabled(&self) -> bool {
+        self.interrupts
+    }
+
+    /// Moves received bytes into the receive buffer and buffered bytes out
+    /// to the UART. Called on the `Aux` IRQ in interrupt mode.
+    pub fn handle_interrupt(&mut self) {
+        self.service();
+    }
+
+    /// Returns the counts of received bytes lost so far.
+    pub fn overruns(&self) -> Overruns {
+        self.overruns
+    }
+
+    /// Does the work of `handle_interrupt()`, then asks for the transmit
+    /// interrupt only while there are bytes waiting to be written.
+    fn service(&mut self) {
+        loop {
+            let lsr = self.registers.LSR.read();
+            if lsr & LsrStatus::ReceiverOverrun as u8 != 0 {
+                self.overruns.fifo += 1;
+            }
+            if lsr & LsrStatus::DataReady as u8 == 0 {
+                break;
+            }
+
+            let byte = self.registers.IO.read();
+            if !self.rx.push(byte) {
+                self.overruns.buffer += 1;
+            }
+        }
+
+        while !self.tx.is_empty() && self.registers.LSR.has_mask(LsrStatus::TxAvailable as u8) {
+            let byte = self.tx.pop().unwrap();
+            self.registers.IO.write(byte);
+        }
+
+        let ier = match self.tx.is_empty() {
+            true => IER_REQUIRED | IER_RX,
+            false => IER_REQUIRED | IER_RX | IER_TX,
+        };
+        self.registers.IER.write(ier);
+    }
+
+    /// Returns a received byte if there is one, without blocking.
+    pub fn try_read(&mut self) -> Option<u8> {
+        if !self.interrupts {
+            return match self.has_byte() {
+                true => Some(self.registers.IO.read()),
+                false => None,
+            };
+        }
+
+        if self.rx.is_empty() {
+            self.service();
+        }
+        self.rx.pop()
+    }
+
+    /// Writes `byte` if the UART, or in interrupt mode the transmit buffer,
+    /// has room for it, returning `false` if neither did.
+    pub fn try_write(&mut self, byte: u8) -> bool {
+        let available = self.registers.LSR.has_mask(LsrStatus::TxAvailable as u8);
+        if !self.interrupts || (available && self.tx.is_empty()) {
+            if available {
+                self.registers.IO.write(byte);
+            }
+            return available;
+        }
+
+        let buffered = self.tx.push(byte);
+        self.service();
+        buffered
+    }
+
     /// Set the read timeout to `t` duration.
     pub fn set_read_timeout(&mut self, t: Duration) {
         self.timeout = Some(t);
     }
 
     /// Write the byte `byte`. This method blocks until there is space available
-    /// in the output FIFO.
+    /// in the output FIFO, or in interrupt mode, in the transmit buffer.
     pub fn write_byte(&mut self, byte: u8) {
-        while !self.registers.LSR.has_mask(LsrStatus::TxAvailable as u8) {}
-        self.registers.IO.write(byte);
+        while !self.try_write(byte) {}
     }
 
     /// Returns `true` if there is at least one byte ready to be read. If this
     /// method returns `true`, a subsequent call to `read_byte` is guaranteed to
     /// return immediately. This method does not block.
     pub fn has_byte(&self) -> bool {
-        self.registers.LSR.has_mask(LsrStatus::DataReady as u8)
+        !self.rx.is_empty() || self.registers.LSR.has_mask(LsrStatus::DataReady as u8)
     }
 
     /// Blocks until there is a byte ready to read. If a read timeout is set,
@@ -135,8 +294,18 @@ impl MiniUart {
 
     /// Reads a byte. Blocks indefinitely until a byte is ready to be read.
     pub fn read_byte(&mut self) -> u8 {
-        while !self.has_byte() {}
-        self.registers.IO.read()
+        loop {
+            if let Some(byte) = self.try_read() {
+                return byte;
+            }
+        }
+    }
+
+    /// Blocks until every buffered byte has been handed to the UART.
+    pub fn flush(&mut self) {
+        while !self.tx.is_empty() {
+            self.service();
+        }
     }
 }
 
@@ -171,7 +340,8 @@ mod uart_io {
     impl io::Write for MiniUart {
 
         fn flush(&mut self) -> io::Result<()> {
-            unimplemented!()
+            MiniUart::flush(self);
+            Ok(())
         }
 
         fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
@@ -202,3 +372,41 @@ mod uart_io {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Ring, BUFFER_SIZE};
+
+    #[test]
+    fn ring_is_first_in_first_out() {
+        let mut ring = Ring::new();
+        assert!(ring.is_empty());
+        assert_eq!(ring.pop(), None);
+
+        for byte in 0..10 {
+            assert!(ring.push(byte));
+        }
+        for byte in 0..10 {
+            assert_eq!(ring.pop(), Some(byte));
+        }
+        assert!(ring.is_empty());
+    }
+
+    #[test]
+    fn ring_wraps_and_refuses_when_full() {
+        let mut ring = Ring::new();
+        for i in 0..BUFFER_SIZE + 10 {
+            let byte = i as u8;
+            assert!(ring.push(byte));
+            if i >= 10 {
+                assert_eq!(ring.pop(), Some((i - 10) as u8));
+            }
+        }
+
+        while ring.push(0xFF) {}
+        assert_eq!(ring.len, BUFFER_SIZE);
+        assert_eq!(ring.pop(), Some(BUFFER_SIZE as u8));
+        assert!(ring.push(0xAA));
+        assert!(!ring.push(0xAA));
+    }
+}